@@ -0,0 +1,146 @@
+// Packs the files embedded with `asset!` into OUT_DIR for compo builds, in
+// the format `pack::unpack` reads.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+#[path = "src/pack.rs"]
+mod pack;
+
+use pack::MIN_MATCH;
+
+const ASSET_DIRS: &[&str] = &["shaders", "fonts"];
+const WINDOW: usize = u16::MAX as usize + 1;
+const MAX_MATCH: usize = u8::MAX as usize + MIN_MATCH;
+// How many earlier positions with the same first bytes to try
+const MAX_CHAIN: usize = 256;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    for dir in ASSET_DIRS {
+        println!("cargo:rerun-if-changed=src/{}", dir);
+    }
+
+    if env::var_os("CARGO_FEATURE_COMPO").is_none() {
+        return Ok(());
+    }
+
+    let out_dir = env::var("OUT_DIR")?;
+    for dir in ASSET_DIRS {
+        pack_dir(&Path::new("src").join(dir), &Path::new(&out_dir).join(dir))?;
+    }
+
+    Ok(())
+}
+
+fn pack_dir(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().ok_or("no file name")?);
+
+        if path.is_dir() {
+            pack_dir(&path, &target)?;
+            continue;
+        }
+
+        let data = fs::read(&path)?;
+        let packed = pack(&data);
+
+        if pack::unpack(&packed) != data {
+            return Err(format!("{} doesn't survive packing", path.display()).into());
+        }
+
+        fs::write(target, packed)?;
+    }
+
+    Ok(())
+}
+
+// Greedy LZ77, the longest match among the latest positions that start with
+// the same three bytes
+fn pack(data: &[u8]) -> Vec<u8> {
+    let mut out = (data.len() as u32).to_le_bytes().to_vec();
+    let mut head = vec![usize::MAX; 1 << 16];
+    let mut prev = vec![usize::MAX; data.len()];
+
+    let mut flags_at = 0;
+    let mut token = 8;
+    let mut i = 0;
+
+    while i < data.len() {
+        if token == 8 {
+            flags_at = out.len();
+            out.push(0);
+            token = 0;
+        }
+
+        let (offset, len) = longest_match(data, i, &head, &prev);
+
+        if len >= MIN_MATCH {
+            out[flags_at] |= 1 << token;
+            out.extend_from_slice(&((offset - 1) as u16).to_le_bytes());
+            out.push((len - MIN_MATCH) as u8);
+
+            for j in i..i + len {
+                insert(data, j, &mut head, &mut prev);
+            }
+            i += len;
+        } else {
+            out.push(data[i]);
+            insert(data, i, &mut head, &mut prev);
+            i += 1;
+        }
+
+        token += 1;
+    }
+
+    out
+}
+
+fn longest_match(data: &[u8], i: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    let mut best = (0, 0);
+    if i + MIN_MATCH > data.len() {
+        return best;
+    }
+
+    let max_len = MAX_MATCH.min(data.len() - i);
+    let mut candidate = head[hash(data, i)];
+
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || i - candidate > WINDOW {
+            break;
+        }
+
+        let len = (0..max_len)
+            .take_while(|&j| data[candidate + j] == data[i + j])
+            .count();
+
+        if len > best.1 {
+            best = (i - candidate, len);
+        }
+        if len == max_len {
+            break;
+        }
+
+        candidate = prev[candidate];
+    }
+
+    best
+}
+
+// Chains every position to the previous one with the same hash
+fn insert(data: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + MIN_MATCH <= data.len() {
+        let h = hash(data, i);
+        prev[i] = head[h];
+        head[h] = i;
+    }
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    ((data[i] as usize) << 8) ^ ((data[i + 1] as usize) << 4) ^ data[i + 2] as usize
+}
@@ -0,0 +1,230 @@
+// Size breakdown of a built binary, per section, against the compo limit.
+// Reads ELF and PE executables and the wasm of the web build.
+//
+//     cargo build --release --no-default-features --features compo
+//     cargo run --example size --features compo -- target/release/leipae
+
+use std::cmp::Reverse;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const COMPO_LIMIT: usize = 64 * 1024;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or(format!("target/release/leipae{}", env::consts::EXE_SUFFIX));
+    let data = fs::read(&path).map_err(|err| format!("{}: {}", path, err))?;
+
+    let mut sections = if data.starts_with(b"\x7fELF") {
+        elf_sections(&data)?
+    } else if data.starts_with(b"MZ") {
+        pe_sections(&data)?
+    } else if data.starts_with(b"\0asm") {
+        wasm_sections(&data)?
+    } else {
+        return Err(format!("{} is not an ELF, PE or wasm file", path).into());
+    };
+    sections.sort_by_key(|(_, size)| Reverse(*size));
+
+    println!("{:<24} {:>9}", path, data.len());
+    for (name, size) in sections.iter().filter(|(_, size)| *size > 0) {
+        println!(
+            "  {:<22} {:>9} {:>5.1}%",
+            name,
+            size,
+            *size as f32 * 100.0 / data.len() as f32
+        );
+    }
+
+    let difference = data.len() as isize - COMPO_LIMIT as isize;
+    println!(
+        "{} bytes {} the {} KiB limit",
+        difference.abs(),
+        if difference > 0 { "over" } else { "under" },
+        COMPO_LIMIT / 1024
+    );
+
+    print_assets()
+}
+
+// Embedded files as they are and as packed by build.rs, which only packs
+// with the compo feature
+fn print_assets() -> Result<(), Box<dyn Error>> {
+    let packed_dir = Path::new(env!("OUT_DIR"));
+    let source_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+
+    let mut assets = Vec::new();
+    collect_files(packed_dir, packed_dir, &mut assets)?;
+    if assets.is_empty() {
+        println!("\nBuild with --features compo to see the packed assets");
+        return Ok(());
+    }
+    assets.sort();
+
+    println!("\n{:<34} {:>9} {:>9}", "assets", "raw", "packed");
+    let mut totals = (0, 0);
+
+    for asset in assets {
+        let raw = fs::metadata(source_dir.join(&asset))?.len();
+        let packed = fs::metadata(packed_dir.join(&asset))?.len();
+        totals = (totals.0 + raw, totals.1 + packed);
+
+        println!("  {:<32} {:>9} {:>9}", asset, raw, packed);
+    }
+    println!("  {:<32} {:>9} {:>9}", "total", totals.0, totals.1);
+
+    Ok(())
+}
+
+// Paths of the files under `dir` relative to `root`
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+
+    Ok(())
+}
+
+// Names and sizes in the file, sections that take no space like .bss are 0
+fn elf_sections(data: &[u8]) -> Result<Vec<(String, usize)>, Box<dyn Error>> {
+    if data.get(4) != Some(&2) {
+        return Err("only 64-bit ELF is supported".into());
+    }
+
+    let offset = read_u64(data, 0x28)? as usize;
+    let entry_size = read_u16(data, 0x3a)? as usize;
+    let count = read_u16(data, 0x3c)? as usize;
+    let names = offset + read_u16(data, 0x3e)? as usize * entry_size;
+    let names_offset = read_u64(data, names + 0x18)? as usize;
+
+    let mut sections = Vec::with_capacity(count);
+
+    // The first section is always empty
+    for i in 1..count {
+        let header = offset + i * entry_size;
+        let name = read_name(data, names_offset + read_u32(data, header)? as usize);
+        let is_nobits = read_u32(data, header + 4)? == 8;
+        let size = if is_nobits {
+            0
+        } else {
+            read_u64(data, header + 0x20)? as usize
+        };
+
+        sections.push((name, size));
+    }
+
+    Ok(sections)
+}
+
+fn pe_sections(data: &[u8]) -> Result<Vec<(String, usize)>, Box<dyn Error>> {
+    let pe = read_u32(data, 0x3c)? as usize;
+    if data.get(pe..pe + 4) != Some(b"PE\0\0") {
+        return Err("no PE signature".into());
+    }
+
+    let count = read_u16(data, pe + 6)? as usize;
+    let table = pe + 24 + read_u16(data, pe + 20)? as usize;
+
+    (0..count)
+        .map(|i| {
+            let header = table + i * 40;
+            let name = data.get(header..header + 8).ok_or("truncated file")?;
+            let name = String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string();
+
+            Ok((name, read_u32(data, header + 16)? as usize))
+        })
+        .collect()
+}
+
+fn wasm_sections(data: &[u8]) -> Result<Vec<(String, usize)>, Box<dyn Error>> {
+    const NAMES: [&str; 13] = [
+        "custom",
+        "type",
+        "import",
+        "function",
+        "table",
+        "memory",
+        "global",
+        "export",
+        "start",
+        "element",
+        "code",
+        "data",
+        "datacount",
+    ];
+
+    let mut sections = Vec::new();
+    let mut i = 8;
+
+    while i < data.len() {
+        let id = data[i] as usize;
+        let (size, len) = read_leb128(data, i + 1)?;
+        let body = i + 1 + len;
+
+        // Custom sections are named, the name is what makes them big or small
+        let name = if id == 0 {
+            let (name_len, len) = read_leb128(data, body)?;
+            let name = data
+                .get(body + len..body + len + name_len)
+                .ok_or("truncated file")?;
+            format!("custom {}", String::from_utf8_lossy(name))
+        } else {
+            NAMES.get(id).unwrap_or(&"unknown").to_string()
+        };
+
+        sections.push((name, 1 + len + size));
+        i = body + size;
+    }
+
+    Ok(sections)
+}
+
+fn read_name(data: &[u8], offset: usize) -> String {
+    let name = data[offset.min(data.len())..]
+        .split(|&b| b == 0)
+        .next()
+        .unwrap_or_default();
+
+    String::from_utf8_lossy(name).into_owned()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Box<dyn Error>> {
+    let bytes = data.get(offset..offset + 2).ok_or("truncated file")?;
+    Ok(u16::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Box<dyn Error>> {
+    let bytes = data.get(offset..offset + 4).ok_or("truncated file")?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, Box<dyn Error>> {
+    let bytes = data.get(offset..offset + 8).ok_or("truncated file")?;
+    Ok(u64::from_le_bytes(bytes.try_into()?))
+}
+
+// The value and how many bytes it took
+fn read_leb128(data: &[u8], offset: usize) -> Result<(usize, usize), Box<dyn Error>> {
+    let mut value = 0;
+
+    for (i, &byte) in data.get(offset..).unwrap_or_default().iter().enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+
+    Err("truncated file".into())
+}
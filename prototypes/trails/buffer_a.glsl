@@ -0,0 +1,8 @@
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec4 previous = texture(iChannel0, fragCoord / iChannelResolution[0].xy);
+
+    vec2 position = dotPosition(iTime, iMouse, iResolution.xy);
+    float brightness = smoothstep(12.0, 8.0, length(fragCoord - position));
+
+    fragColor = vec4(max(previous.rgb * FADE, vec3(brightness)), 1.0);
+}
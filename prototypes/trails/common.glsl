@@ -0,0 +1,10 @@
+const float FADE = 0.96;
+
+// Position of the dot at the given time, or the mouse while it's held
+vec2 dotPosition(float time, vec4 mouse, vec2 resolution) {
+    if (mouse.z > 0.0) {
+        return mouse.xy;
+    }
+
+    return resolution * (0.5 + vec2(0.35 * cos(time), 0.3 * sin(2.0 * time)));
+}
@@ -0,0 +1,8 @@
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    float trail = texture(iChannel0, fragCoord / iResolution.xy).r;
+
+    vec3 color = mix(vec3(0.06, 0.03, 0.69), vec3(0.94, 0.12, 0.58), trail);
+    color = mix(color, vec3(1.0, 1.0, 0.5), smoothstep(0.9, 1.0, trail));
+
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,203 @@
+use std::error::Error;
+use std::ffi::CStr;
+use std::time::{Duration, Instant};
+
+use glutin::event::{Event, StartCause, WindowEvent};
+use glutin::event_loop::{ControlFlow, EventLoop};
+use glutin::platform::run_return::EventLoopExtRunReturn;
+use glutin::window::Window;
+use glutin::{ContextWrapper, PossiblyCurrent};
+
+use crate::clock::{Clock, FixedClock};
+use crate::demo::{Demo, Scene, Timeline};
+use crate::renderer::Renderer;
+use crate::stats::Stats;
+
+// Every run places the leipae the same way so the frames are comparable
+pub const BENCHMARK_SEED: u64 = 2022;
+
+// Plays the timeline with a fixed timestep as fast as the GPU renders
+// it, and prints a JSON summary of the frame times to stdout. The resolution
+// in the summary is the window's at the end.
+#[derive(Debug)]
+pub struct Benchmark {
+    fps: f32,
+    timeline: Timeline,
+}
+
+impl Benchmark {
+    pub fn new(fps: f32, timeline: Timeline) -> Self {
+        Self { fps, timeline }
+    }
+
+    pub unsafe fn run(
+        &self,
+        event_loop: &mut EventLoop<()>,
+        renderer: &mut Renderer,
+        gl_window: &ContextWrapper<PossiblyCurrent, Window>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut demo = Demo::with_seed(self.timeline.clone(), BENCHMARK_SEED);
+        let mut clock = FixedClock::new(Duration::from_secs_f32(1.0 / self.fps));
+
+        // Milliseconds per frame, by scene in the order they were played
+        let mut frames: Vec<(Scene, Vec<f32>)> = Vec::new();
+        let mut result = Ok(());
+        let start = Instant::now();
+
+        event_loop.run_return(|event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+
+            match event {
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                        gl_window.resize(size);
+                        result = renderer.resize(size.width, size.height);
+                    }
+                    _ => (),
+                },
+                Event::NewEvents(StartCause::Poll) => {
+                    demo.update(clock.tick());
+                    if demo.should_exit() {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+
+                    let frame_start = Instant::now();
+
+                    result = renderer
+                        .draw(&demo)
+                        .and_then(|_| gl_window.swap_buffers().map_err(Into::into));
+                    // Without waiting the frame time would only measure the driver queue
+                    gl::Finish();
+
+                    let frame_time = frame_start.elapsed().as_secs_f32() * 1000.0;
+
+                    match frames.iter_mut().find(|(scene, _)| *scene == demo.scene()) {
+                        Some((_, times)) => times.push(frame_time),
+                        None => frames.push((demo.scene(), vec![frame_time])),
+                    }
+                }
+                _ => (),
+            }
+
+            if result.is_err() {
+                *control_flow = ControlFlow::Exit;
+            }
+        });
+        result?;
+
+        let total_time = start.elapsed().as_secs_f32();
+        println!("{}", self.summary(renderer, total_time, &frames));
+
+        Ok(())
+    }
+
+    unsafe fn summary(
+        &self,
+        renderer: &Renderer,
+        total_time: f32,
+        frames: &[(Scene, Vec<f32>)],
+    ) -> String {
+        let (width, height) = renderer.resolution();
+        let (internal_width, internal_height) = renderer.internal_resolution();
+
+        let all: Vec<f32> = frames.iter().flat_map(|(_, times)| times.clone()).collect();
+        let frame_count = all.len();
+
+        let mut scenes = Vec::with_capacity(frames.len());
+        for (scene, times) in frames {
+            if let Some(stats) = Stats::new(times.clone()) {
+                scenes.push(format!(
+                    "    {{\"scene\": \"{:?}\", {}}}",
+                    scene,
+                    stats_json(&stats)
+                ));
+            }
+        }
+
+        let overall = match Stats::new(all) {
+            Some(stats) => stats_json(&stats),
+            None => "\"frames\": 0".to_string(),
+        };
+
+        format!(
+            "{{\n  \"renderer\": \"{}\",\n  \"vendor\": \"{}\",\n  \"version\": \"{}\",\n  \"resolution\": [{}, {}],\n  \"internal_resolution\": [{}, {}],\n  \"fps\": {},\n  \"seed\": {},\n  \"frames\": {},\n  \"total_time_s\": {:.3},\n  \"average_fps\": {:.2},\n  \"overall\": {{{}}},\n  \"scenes\": [\n{}\n  ]\n}}",
+            escape(&gl_string(gl::RENDERER)),
+            escape(&gl_string(gl::VENDOR)),
+            escape(&gl_string(gl::VERSION)),
+            width,
+            height,
+            internal_width,
+            internal_height,
+            self.fps,
+            BENCHMARK_SEED,
+            frame_count,
+            total_time,
+            frame_count as f32 / total_time,
+            overall,
+            scenes.join(",\n")
+        )
+    }
+}
+
+fn stats_json(stats: &Stats) -> String {
+    format!(
+        "\"frames\": {}, \"min_ms\": {:.3}, \"avg_ms\": {:.3}, \"p95_ms\": {:.3}, \"max_ms\": {:.3}",
+        stats.count, stats.min, stats.avg, stats.p95, stats.max
+    )
+}
+
+unsafe fn gl_string(name: gl::types::GLenum) -> String {
+    let string = gl::GetString(name);
+
+    if string.is_null() {
+        return String::new();
+    }
+
+    CStr::from_ptr(string as *const _)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Escapes the driver strings for a JSON string, they can have anything
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(
+            escape(r#"Mesa "Intel(R)" C:\drivers"#),
+            r#"Mesa \"Intel(R)\" C:\\drivers"#
+        );
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        assert_eq!(escape("4.6\n(Core)\t\u{1}"), "4.6\\n(Core)\\t\\u0001");
+    }
+
+    #[test]
+    fn keeps_unicode() {
+        assert_eq!(escape("Mesa — llvmpipe 🍞"), "Mesa — llvmpipe 🍞");
+    }
+}
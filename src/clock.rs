@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    // Returns the time elapsed since the previous tick
+    fn tick(&mut self) -> Duration;
+}
+
+// Wall clock time, for realtime playback
+#[derive(Debug)]
+pub struct SystemClock {
+    last_tick: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            last_tick: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick);
+        self.last_tick = now;
+
+        elapsed
+    }
+}
+
+// The browser's wall clock, `Instant` isn't available on the web
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug)]
+pub struct PerformanceClock {
+    performance: web_sys::Performance,
+    // Milliseconds
+    last_tick: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl PerformanceClock {
+    pub fn new() -> Self {
+        let performance = web_sys::window()
+            .and_then(|window| window.performance())
+            .expect("performance.now() is not available");
+        let last_tick = performance.now();
+
+        Self {
+            performance,
+            last_tick,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Default for PerformanceClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for PerformanceClock {
+    fn tick(&mut self) -> Duration {
+        let now = self.performance.now();
+        // The browser may coarsen the timer enough for two ticks to be equal
+        let elapsed = (now - self.last_tick).max(0.0);
+        self.last_tick = now;
+
+        Duration::from_secs_f64(elapsed / 1000.0)
+    }
+}
+
+// Wall clock of the platform being built for
+#[cfg(not(target_arch = "wasm32"))]
+pub type RealtimeClock = SystemClock;
+#[cfg(target_arch = "wasm32")]
+pub type RealtimeClock = PerformanceClock;
+
+// Advances by the same step on every tick regardless of how long frames take,
+// for offline rendering
+#[derive(Debug)]
+pub struct FixedClock {
+    step: Duration,
+}
+
+impl FixedClock {
+    pub fn new(step: Duration) -> Self {
+        Self { step }
+    }
+}
+
+impl Clock for FixedClock {
+    fn tick(&mut self) -> Duration {
+        self.step
+    }
+}
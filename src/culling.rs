@@ -0,0 +1,204 @@
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+
+use gl::types::*;
+
+use crate::program::ShaderProgram;
+use crate::sdf::{self, Vec3, FOV, WATER_LEVEL};
+
+// Size of the block, MAX_LEIPAE in fragment.glsl
+pub const MAX_LEIPAE: usize = 256;
+// Radius of a leipae at scale 1, `sdLeipaeRound` is never below the distance
+// to this sphere
+pub const LEIPAE_RADIUS: f32 = 2.5;
+
+// Binding point of the `Leipae` block, after the materials
+const LEIPAE_BINDING: GLuint = 1;
+
+// The view of the camera for culling the leipae. `sdScene` goes through every
+// leipae it gets at every step of every ray, so only the ones that can show
+// up in the image are uploaded.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    camera: Vec3,
+    forward: Vec3,
+    // The sides of the view, pointing in
+    planes: [Vec3; 4],
+    // Where the rays and the shadows give up, `MAX_DIST` of the quality
+    max_dist: f32,
+}
+
+impl Frustum {
+    // The view of `rayDirection` and `lookAt` in the shader, `aspect` is the
+    // width of the image divided by the height
+    pub fn new(camera: Vec3, target: Vec3, aspect: f32, max_dist: f32) -> Self {
+        let forward = sdf::normalize(sdf::sub(target, camera));
+        let side = sdf::normalize(sdf::cross([0.0, 1.0, 0.0], forward));
+        let up = sdf::cross(forward, side);
+
+        // The image plane is twice as far as the field of view says, see
+        // `rayDirection`
+        let tan_y = f32::tan(FOV.to_radians() / 2.0) / 2.0;
+        let tan_x = tan_y * aspect;
+
+        let plane = |tan: f32, edge: Vec3| sdf::normalize(sdf::add(sdf::scale(forward, tan), edge));
+
+        Self {
+            camera,
+            forward,
+            planes: [
+                plane(tan_x, side),
+                plane(tan_x, sdf::scale(side, -1.0)),
+                plane(tan_y, up),
+                plane(tan_y, sdf::scale(up, -1.0)),
+            ],
+            max_dist,
+        }
+    }
+
+    // Whether any part of the sphere is in view before the rays give up
+    pub fn contains(&self, center: Vec3, radius: f32) -> bool {
+        self.contains_segment(center, center, radius)
+    }
+
+    // Whether any part of the sphere swept from `start` to `end` may be in
+    // view. Only rejects it when both ends are behind the same plane, so some
+    // that just miss a corner are kept.
+    pub fn contains_segment(&self, start: Vec3, end: Vec3, radius: f32) -> bool {
+        let start = sdf::sub(start, self.camera);
+        let end = sdf::sub(end, self.camera);
+
+        let beyond_far = |offset: Vec3| sdf::dot(offset, self.forward) - radius >= self.max_dist;
+        if beyond_far(start) && beyond_far(end) {
+            return false;
+        }
+
+        self.planes
+            .iter()
+            .all(|&plane| sdf::dot(start, plane) > -radius || sdf::dot(end, plane) > -radius)
+    }
+
+    // The leipae in view, reflected into it by the water or casting a shadow
+    // into it from the sun in direction `sun`, nearest first and at most
+    // `MAX_LEIPAE` of them
+    pub fn cull(&self, leipae: &[[f32; 4]], sun: Vec3) -> Vec<[f32; 4]> {
+        // Shadow rays start from surfaces in view and go up to `max_dist`
+        // towards the sun
+        let shadow = sdf::scale(sun, -self.max_dist);
+        let reflect = |[x, y, z]: Vec3| [x, 2.0 * WATER_LEVEL - y, z];
+
+        let mut visible: Vec<[f32; 4]> = leipae
+            .iter()
+            .copied()
+            .filter(|&[x, y, z, scale]| {
+                let radius = LEIPAE_RADIUS / scale;
+                let center = [x, y, z];
+                let shadow_end = sdf::add(center, shadow);
+
+                self.contains_segment(center, shadow_end, radius)
+                    || self.contains_segment(reflect(center), reflect(shadow_end), radius)
+            })
+            .collect();
+
+        let distance = |leipae: &[f32; 4]| {
+            sdf::length(sdf::sub([leipae[0], leipae[1], leipae[2]], self.camera))
+        };
+        visible.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        visible.truncate(MAX_LEIPAE);
+
+        visible
+    }
+}
+
+// Uniform buffer of the leipae, followed by how many there are in `iLeipaeCount`
+#[derive(Debug)]
+pub struct LeipaeBuffer {
+    ubo: GLuint,
+}
+
+impl LeipaeBuffer {
+    pub unsafe fn new() -> Self {
+        let mut ubo = 0;
+        gl::GenBuffers(1, &mut ubo);
+
+        // The whole block once, only the leipae in use are uploaded per frame
+        gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+        gl::BufferData(
+            gl::UNIFORM_BUFFER,
+            (MAX_LEIPAE * mem::size_of::<[f32; 4]>()) as GLsizeiptr,
+            ptr::null(),
+            gl::DYNAMIC_DRAW,
+        );
+        gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+
+        Self { ubo }
+    }
+
+    // Points the program's `Leipae` block to the buffer, needed once for
+    // every program
+    pub unsafe fn attach(&self, program: &ShaderProgram) {
+        let name = CString::new("Leipae").unwrap();
+        let index = gl::GetUniformBlockIndex(program.id(), name.as_ptr());
+
+        // Not there when the leipae are compiled out
+        if index != gl::INVALID_INDEX {
+            gl::UniformBlockBinding(program.id(), index, LEIPAE_BINDING);
+        }
+    }
+
+    // Uploads the leipae and their count to the active program, the ones
+    // past `MAX_LEIPAE` are left out
+    pub unsafe fn bind(&self, program: &ShaderProgram, leipae: &[[f32; 4]]) {
+        let leipae = &leipae[..leipae.len().min(MAX_LEIPAE)];
+
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, LEIPAE_BINDING, self.ubo);
+        gl::BufferSubData(
+            gl::UNIFORM_BUFFER,
+            0,
+            mem::size_of_val(leipae) as GLsizeiptr,
+            leipae.as_ptr() as *const _,
+        );
+
+        program.set_uniform_i32("iLeipaeCount", leipae.len() as i32);
+    }
+}
+
+impl Drop for LeipaeBuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.ubo) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    // Looking down the negative z axis, like the first scenes
+    fn frustum(max_dist: f32) -> Frustum {
+        Frustum::new([0.0, 5.0, 0.0], [0.0, 5.0, -1.0], 16.0 / 9.0, max_dist)
+    }
+
+    #[test]
+    fn culls_to_the_max_dist() {
+        let leipae = [[0.0, 5.0, -120.0, 1.0]];
+        let up = [0.0, 1.0, 0.0];
+
+        assert!(frustum(100.0).cull(&leipae, up).is_empty());
+        assert_eq!(frustum(150.0).cull(&leipae, up), leipae);
+    }
+
+    #[test]
+    fn keeps_shadows_cast_into_the_view() {
+        // Above the view. With the sun overhead it shadows the ground in
+        // front of the camera, with the sun behind the camera the shadow
+        // passes over the view.
+        let leipae = [[0.0, 60.0, -20.0, 1.0]];
+
+        assert_eq!(frustum(100.0).cull(&leipae, [0.0, 1.0, 0.0]), leipae);
+        assert!(frustum(100.0).cull(&leipae, [0.0, 0.0, 1.0]).is_empty());
+    }
+}
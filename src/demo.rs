@@ -1,8 +1,16 @@
-use std::ops::{Sub, SubAssign};
-use std::time::{Duration, SystemTime};
-
-use rand::Rng;
-
+use std::mem;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::culling::{Frustum, LEIPAE_RADIUS};
+use crate::focus::{Bokeh, Focus, FocusTarget, Lens, DEFAULT_FOCUS};
+use crate::material::{Material, MaterialId, DEFAULT_MATERIALS, MATERIAL_COUNT};
+use crate::postprocess::Effect;
+use crate::random::Random;
+use crate::sdf::{self, SceneSdf, WATER_LEVEL};
+use crate::texture::{TextureSpec, CHANNEL_COUNT};
+
+// How many leipae fall unless `--leipae` says otherwise
 pub const LEIPAE_COUNT: usize = 20;
 pub const SCENE_ORDER: &[Scene] = &[
     Scene::Init,
@@ -11,11 +19,31 @@ pub const SCENE_ORDER: &[Scene] = &[
     Scene::TopToForward,    // 15sec
     Scene::Intro,           // 30sec
     Scene::BackwardsCircle, // 10sec
-    Scene::MovingUp,        // 15sec
+    Scene::MovingUp,        // 20sec
     Scene::Ending,
 ];
 
-#[derive(Clone, Copy)]
+// Playback speeds are powers of two between these, in either direction
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 4.0;
+
+// Length of a single frame step
+const FRAME_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const DEFAULT_EFFECTS: &[Effect] = &[Effect::Grade, Effect::Bloom, Effect::FilmGrain];
+
+// Leipae fall with this acceleration and start over from the top below the water
+pub const GRAVITY: f32 = -0.25;
+pub const LEIPAE_TOP: f32 = 15.0;
+pub const LEIPAE_BOTTOM: f32 = -2.5;
+// Leipae placed by clicking are all the same size, a bit larger than average
+const SPAWN_SCALE: f32 = 2.0;
+// Leipae falling through the water leave ripples that spread for this long.
+// The shader has room for the latest few.
+pub const RIPPLE_COUNT: usize = 8;
+pub const RIPPLE_LIFETIME: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Scene {
     Init,
     Intro,
@@ -28,99 +56,381 @@ pub enum Scene {
     Ending,
 }
 
+impl Scene {
+    // Length of the scene in seconds, the markers at both ends take no time
+    pub fn duration(self) -> f32 {
+        match self {
+            Scene::Init | Scene::Ending => 0.0,
+            Scene::Intro => 30.0,
+            Scene::Closeup => 5.0,
+            Scene::MovingForward => 15.0,
+            Scene::TopToForward => 15.0,
+            Scene::ForwardToTop => 15.0,
+            Scene::MovingUp => 20.0,
+            Scene::BackwardsCircle => 10.0,
+        }
+    }
+
+    // Camera position `t` seconds into the scene
+    pub fn camera(self, t: f32) -> [f32; 3] {
+        match self {
+            Scene::Init | Scene::Ending => [0.0, 0.0, 0.0],
+            Scene::Intro => [-20.0 * f32::cos(t / 20.0), 2.0, 30.0 * f32::sin(t / 20.0)],
+            Scene::Closeup => [5.0 * f32::cos(t / 40.0), 2.0, 4.0 * f32::sin(t / 40.0)],
+            Scene::TopToForward => [0.0, 3.0 - t / 10.0, -t / 10.0],
+            Scene::ForwardToTop => [0.0, 1.5 + t / 10.0, -2.0 + t / 10.0],
+            Scene::MovingForward => [3.0, 1.1, t / 15.0 * -20.0],
+            Scene::MovingUp => [3.0, 0.9 + t / 10.0, 0.0],
+            Scene::BackwardsCircle => [
+                10.0 + -10.0 * f32::sin(t / 20.0),
+                2.0,
+                -10.0 * f32::cos(t / 20.0),
+            ],
+        }
+    }
+
+    // Point the camera looks at `t` seconds into the scene
+    pub fn target(self, t: f32) -> [f32; 3] {
+        match self {
+            Scene::Init | Scene::Ending => [0.0, 0.0, 0.0],
+            Scene::Intro => [0.0, 2.0 * f32::sin(t / 10.0), 0.0],
+            Scene::Closeup => [0.0, 0.0, 0.0],
+            Scene::TopToForward => [0.0, 0.0, -1.0 - t],
+            Scene::ForwardToTop => [0.0, 0.0, -20.0 + t],
+            Scene::MovingForward => [3.0, 0.8, -100.0],
+            Scene::MovingUp => [3.0, 0.0, -50.0],
+            Scene::BackwardsCircle => [-10.0 + t, 2.0, -100.0],
+        }
+    }
+
+    // Time from the beginning of the demo to the start of the scene, none if
+    // the scene isn't part of the demo
+    pub fn start(self) -> Option<f32> {
+        let index = SCENE_ORDER.iter().position(|&scene| scene == self)?;
+
+        Some(
+            SCENE_ORDER[..index]
+                .iter()
+                .map(|scene| scene.duration())
+                .sum(),
+        )
+    }
+
+    // Textures of `iChannel0` to `iChannel3`, `--channel` overrides these.
+    // Every scene has the noise texture for now.
+    pub fn channels(self) -> [Option<TextureSpec>; CHANNEL_COUNT] {
+        [Some(TextureSpec::noise()), None, None, None]
+    }
+
+    // The material table of the scene shader
+    pub fn materials(self) -> [Material; MATERIAL_COUNT] {
+        let mut materials = DEFAULT_MATERIALS;
+
+        match self {
+            // The grid glows brighter under the scanlines
+            Scene::TopToForward => {
+                materials[MaterialId::NeonPink.index()].emissive = [0.6, 0.0, 0.6];
+                materials[MaterialId::NeonCyan.index()].emissive = [0.0, 0.6, 0.6];
+            }
+            // Shinier crust up close
+            Scene::Closeup => materials[MaterialId::Crust.index()].specular = 0.5,
+            _ => (),
+        }
+
+        materials
+    }
+
+    // Depth of field `t` seconds into the scene, used by scenes with the
+    // `DepthOfField` effect
+    pub fn focus(self, t: f32) -> Focus {
+        match self {
+            // Follows the nearest leipae, opening up over the shot
+            Scene::Closeup => Focus::new(
+                FocusTarget::NearestLeipae,
+                0.01 + 0.02 * (t / self.duration()).min(1.0),
+                Bokeh::Polygon(6),
+            ),
+            _ => DEFAULT_FOCUS,
+        }
+    }
+
+    fn effects(self) -> &'static [Effect] {
+        match self {
+            Scene::TopToForward => &[
+                Effect::Grade,
+                Effect::Bloom,
+                Effect::Scanlines,
+                Effect::FilmGrain,
+            ],
+            Scene::BackwardsCircle => &[
+                Effect::Grade,
+                Effect::Bloom,
+                Effect::ChromaticAberration,
+                Effect::FilmGrain,
+            ],
+            // Contact shadows and inked edges up close
+            Scene::Closeup => &[
+                Effect::Occlusion,
+                Effect::Outlines,
+                Effect::DepthOfField,
+                Effect::Grade,
+                Effect::Bloom,
+                Effect::FilmGrain,
+            ],
+            _ => DEFAULT_EFFECTS,
+        }
+    }
+}
+
+impl FromStr for Scene {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let scenes = [
+            Scene::Intro,
+            Scene::Closeup,
+            Scene::MovingForward,
+            Scene::TopToForward,
+            Scene::ForwardToTop,
+            Scene::MovingUp,
+            Scene::BackwardsCircle,
+        ];
+
+        scenes
+            .into_iter()
+            .find(|scene| format!("{:?}", scene).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown scene \"{}\"", s))
+    }
+}
+
+// The part of the demo to play. Scenes that aren't part of the demo, like
+// the closeup, get a timeline of their own.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    scenes: Vec<Scene>,
+    start: Duration,
+    // Exits here instead of at the ending when set
+    end: Option<Duration>,
+    leipae: usize,
+}
+
+impl Timeline {
+    // Starts from the beginning of the scene and plays to the end of the demo
+    pub fn from_scene(scene: Scene) -> Self {
+        match scene.start() {
+            Some(start) => Self::default().starting_at(start),
+            None => Self {
+                scenes: vec![Scene::Init, scene, Scene::Ending],
+                start: Duration::default(),
+                end: None,
+                leipae: LEIPAE_COUNT,
+            },
+        }
+    }
+
+    // Plays the scene and exits
+    pub fn only_scene(scene: Scene) -> Self {
+        let timeline = Self::from_scene(scene);
+        let end = timeline.start.as_secs_f32() + scene.duration();
+
+        timeline.ending_at(end)
+    }
+
+    pub fn starting_at(mut self, seconds: f32) -> Self {
+        self.start = Duration::from_secs_f32(seconds);
+        self
+    }
+
+    pub fn ending_at(mut self, seconds: f32) -> Self {
+        self.end = Some(Duration::from_secs_f32(seconds));
+        self
+    }
+
+    // Up to `MAX_LEIPAE` can be in view at once, the rest are culled
+    pub fn with_leipae(mut self, count: usize) -> Self {
+        self.leipae = count;
+        self
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            scenes: SCENE_ORDER.to_vec(),
+            start: Duration::default(),
+            end: None,
+            leipae: LEIPAE_COUNT,
+        }
+    }
+}
+
+// What a left click does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickMode {
+    // Skips to the next scene
+    Skip,
+    // Picks the leipae under the cursor for the HUD
+    Select,
+    // Places a new leipae where the cursor points at
+    Spawn,
+}
+
+impl ClickMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Skip => Self::Select,
+            Self::Select => Self::Spawn,
+            Self::Spawn => Self::Skip,
+        }
+    }
+}
+
 struct Leipae {
     x: f32,
+    // Height at the start of the demo
     y: f32,
     z: f32,
-    v: f32,
     scale: f32,
+    // The ones spawned by clicking stay where they were put
+    is_falling: bool,
 }
 
 impl Leipae {
-    fn new() -> Self {
-        let mut rng = rand::thread_rng();
-
+    fn new(rng: &mut Random) -> Self {
         Self {
-            x: rng.gen_range(-10.0..10.0),
-            y: rng.gen_range(2.0..15.0),
-            z: rng.gen_range(-10.0..10.0),
-            scale: rng.gen_range(1.0..5.0),
-            v: 0.0,
+            x: rng.range(-10.0..10.0),
+            y: rng.range(2.0..15.0),
+            z: rng.range(-10.0..10.0),
+            scale: rng.range(1.0..5.0),
+            is_falling: true,
         }
     }
 
-    fn update(&mut self, dt: f32) {
-        let a = -0.25;
+    // Position `time` seconds into the demo. Every leipae first falls from its
+    // starting height, then over and over again from the top, so the position
+    // can be computed for any time in either direction.
+    fn uniform4_f32(&self, time: f32) -> [f32; 4] {
+        if !self.is_falling {
+            return [self.x, self.y, self.z, self.scale];
+        }
 
-        self.y += self.v * dt + 0.5 * a * dt * dt;
-        self.v += a * dt;
+        let first_fall = fall_time(self.y - LEIPAE_BOTTOM);
 
-        if self.y < -2.5 {
-            self.y = 15.0;
-            self.v = 0.0;
-        }
+        let y = if time < first_fall {
+            self.y + 0.5 * GRAVITY * time * time
+        } else {
+            let t = (time - first_fall) % fall_time(LEIPAE_TOP - LEIPAE_BOTTOM);
+            LEIPAE_TOP + 0.5 * GRAVITY * t * t
+        };
+
+        [self.x, y, self.z, self.scale]
     }
 
-    fn uniform4_f32(&self) -> [f32; 4] {
-        [self.x, self.y, self.z, self.scale]
+    // When the leipae last fell through the water at or before `time`, none
+    // if it hasn't yet or stays where it was put
+    fn last_impact(&self, time: f32) -> Option<f32> {
+        let first_impact = fall_time(self.y - WATER_LEVEL);
+        if !self.is_falling || time < first_impact {
+            return None;
+        }
+
+        // The later falls start from the top
+        let first_fall = fall_time(self.y - LEIPAE_BOTTOM);
+        let impact = fall_time(LEIPAE_TOP - WATER_LEVEL);
+        if time < first_fall + impact {
+            return Some(first_impact);
+        }
+
+        let period = fall_time(LEIPAE_TOP - LEIPAE_BOTTOM);
+        let falls = ((time - first_fall - impact) / period).floor();
+
+        Some(first_fall + falls * period + impact)
     }
 }
 
-impl Default for Leipae {
-    fn default() -> Self {
-        Self::new()
-    }
+// Starting position and scale of every leipae placed with the seed, in the
+// same order as the uniforms
+pub fn leipae_origins(seed: u64) -> Vec<[f32; 4]> {
+    let mut rng = Random::with_seed(seed);
+
+    (0..LEIPAE_COUNT)
+        .map(|_| {
+            let leipae = Leipae::new(&mut rng);
+            [leipae.x, leipae.y, leipae.z, leipae.scale]
+        })
+        .collect()
+}
+
+// Time to fall the given height from rest
+fn fall_time(height: f32) -> f32 {
+    f32::sqrt(2.0 * height / -GRAVITY)
 }
 
 pub struct Demo {
     leipaes: Vec<Leipae>,
+    // Index of the leipae picked for inspection
+    selected: Option<usize>,
+    timeline: Timeline,
 
     scene: Scene,
     scene_idx: usize,
 
-    epoch: SystemTime,
-    last_tick: SystemTime,
-    start: SystemTime,
-    end: Duration,
+    // Position on the timeline, the scene time and when the scene ends
     day_time: Duration,
     time: Duration,
-
-    update_camera: fn(&[f32; 3], f32) -> [f32; 3],
-    update_target: fn(&[f32; 3], f32) -> [f32; 3],
+    end: Duration,
 
     camera: [f32; 3],
     target: [f32; 3],
 
+    previous_camera: [f32; 3],
+    previous_target: [f32; 3],
+    // Set when the camera jumped since the previous update
+    is_cut: bool,
+
+    effects: &'static [Effect],
+
     is_paused: bool,
+    // Multiplier for the elapsed time, negative plays backwards
+    speed: f32,
     is_exit: bool,
 }
 
-fn noop_movement(pos: &[f32; 3], _time: f32) -> [f32; 3] {
-    *pos
-}
-
 impl Demo {
     pub fn new() -> Self {
-        let mut leipaes = Vec::with_capacity(LEIPAE_COUNT);
+        Self::with_timeline(Timeline::default())
+    }
 
-        for _ in 0..LEIPAE_COUNT {
-            leipaes.push(Leipae::new());
-        }
+    pub fn with_timeline(timeline: Timeline) -> Self {
+        Self::with_rng(timeline, &mut Random::from_entropy())
+    }
+
+    // Places the leipae the same way on every run with the same seed
+    pub fn with_seed(timeline: Timeline, seed: u64) -> Self {
+        Self::with_rng(timeline, &mut Random::with_seed(seed))
+    }
 
-        let epoch = SystemTime::now();
+    fn with_rng(timeline: Timeline, rng: &mut Random) -> Self {
+        let start = timeline.start;
 
-        Self {
+        let mut leipaes = Vec::with_capacity(timeline.leipae);
+
+        for _ in 0..timeline.leipae {
+            leipaes.push(Leipae::new(rng));
+        }
+
+        let mut demo = Self {
             is_paused: false,
+            speed: 1.0,
             is_exit: false,
 
             leipaes,
+            selected: None,
+            timeline,
 
             scene: Scene::Init,
             scene_idx: 0,
 
-            epoch,
-            last_tick: epoch,
-            start: epoch,
             end: Duration::default(),
             time: Duration::default(),
             day_time: Duration::default(),
@@ -128,33 +438,132 @@ impl Demo {
             camera: [0.0, 0.0, 0.0],
             target: [0.0, 0.0, 0.0],
 
-            update_camera: noop_movement,
-            update_target: noop_movement,
-        }
+            previous_camera: [0.0, 0.0, 0.0],
+            previous_target: [0.0, 0.0, 0.0],
+            is_cut: true,
+
+            effects: DEFAULT_EFFECTS,
+        };
+
+        demo.seek(start);
+        demo.previous_camera = demo.camera;
+        demo.previous_target = demo.target;
+
+        demo
     }
 
+    // Restarts the current scene
     pub fn reset(&mut self) {
-        self.start = SystemTime::now();
-        self.last_tick = self.epoch;
-        self.time = Duration::default();
+        self.seek(self.day_time - self.time);
+        self.is_cut = true;
     }
 
-    pub fn pause(&mut self) {
+    pub fn toggle_pause(&mut self) {
+        self.is_paused = !self.is_paused;
+    }
+
+    // Pauses and moves the given number of frames forward or backward
+    pub fn step(&mut self, frames: i32) {
         self.is_paused = true;
+
+        let step = FRAME_STEP * frames.unsigned_abs();
+        if frames >= 0 {
+            self.seek(self.day_time + step);
+        } else {
+            self.seek(self.day_time.saturating_sub(step));
+        }
     }
 
-    pub fn resume(&mut self) {
-        self.is_paused = false;
-        self.start = SystemTime::now().sub(self.time);
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).clamp(-MAX_SPEED, MAX_SPEED);
     }
 
-    pub fn leipae(&self) -> [[f32; 4]; LEIPAE_COUNT] {
+    pub fn slower(&mut self) {
+        let speed = self.speed / 2.0;
+
+        if speed.abs() >= MIN_SPEED {
+            self.speed = speed;
+        }
+    }
+
+    pub fn reverse(&mut self) {
+        self.speed = -self.speed;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // Every leipae, in the order the picking and the HUD index them
+    pub fn leipae(&self) -> Vec<[f32; 4]> {
+        let time = self.day_time();
+
         self.leipaes
             .iter()
-            .map(|leipae| leipae.uniform4_f32())
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap()
+            .map(|leipae| leipae.uniform4_f32(time))
+            .collect()
+    }
+
+    // The leipae that can show up in an image of the given aspect ratio when
+    // the rays give up at `max_dist`, nearest first, what the renderer
+    // uploads to the `Leipae` block
+    pub fn visible_leipae(&self, aspect: f32, max_dist: f32) -> Vec<[f32; 4]> {
+        let scene = SceneSdf::from_demo(self);
+
+        Frustum::new(self.camera, self.target, aspect, max_dist).cull(scene.leipae(), scene.sun())
+    }
+
+    // Centre x and z, start time and strength of the latest ripples, the
+    // `iRipples` uniform. The rest have no strength.
+    pub fn ripples(&self) -> [[f32; 4]; RIPPLE_COUNT] {
+        let time = self.day_time();
+        let mut impacts: Vec<[f32; 4]> = self
+            .leipaes
+            .iter()
+            .filter_map(|leipae| {
+                let start = leipae.last_impact(time)?;
+
+                // The scale shrinks the leipae, smaller ones make smaller waves
+                (time - start <= RIPPLE_LIFETIME).then_some([
+                    leipae.x,
+                    leipae.z,
+                    start,
+                    1.0 / leipae.scale,
+                ])
+            })
+            .collect();
+        impacts.sort_by(|a, b| b[2].total_cmp(&a[2]));
+
+        let mut ripples = [[0.0; 4]; RIPPLE_COUNT];
+        for (ripple, impact) in ripples.iter_mut().zip(impacts) {
+            *ripple = impact;
+        }
+
+        ripples
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&index| index < self.leipaes.len());
+    }
+
+    // Places a new leipae at the given point. The culling keeps the ones
+    // uploaded to the shader under `MAX_LEIPAE`, so there can be any number.
+    // Returns the index it got.
+    pub fn spawn_leipae(&mut self, position: [f32; 3]) -> usize {
+        // Resting on the surface rather than halfway in it
+        self.leipaes.push(Leipae {
+            x: position[0],
+            y: position[1] + 1.0 / SPAWN_SCALE,
+            z: position[2],
+            scale: SPAWN_SCALE,
+            is_falling: false,
+        });
+
+        self.leipaes.len() - 1
     }
 
     pub fn should_exit(&self) -> bool {
@@ -166,9 +575,20 @@ impl Demo {
     }
 
     pub fn skip_to_next(&mut self) {
-        self.day_time += self.end - self.time;
-        self.epoch.sub_assign(self.end - self.time);
-        self.next_scene()
+        self.seek(self.day_time - self.time + self.end);
+    }
+
+    pub fn scene(&self) -> Scene {
+        self.scene
+    }
+
+    // Time since the current scene started
+    pub fn scene_time(&self) -> f32 {
+        self.time.as_secs_f32()
+    }
+
+    pub fn scene_duration(&self) -> f32 {
+        self.end.as_secs_f32()
     }
 
     pub fn day_time(&self) -> f32 {
@@ -183,105 +603,125 @@ impl Demo {
         self.target
     }
 
-    pub fn update(&mut self) {
-        self.time = self.start.elapsed().unwrap();
-        self.day_time = self.epoch.elapsed().unwrap();
-
-        if self.time >= self.end {
-            self.next_scene();
-        }
+    pub fn previous_camera(&self) -> [f32; 3] {
+        self.previous_camera
+    }
 
-        let dt = self.last_tick.elapsed().unwrap().as_secs_f32();
-        let t = self.time.as_secs_f32();
+    pub fn previous_target(&self) -> [f32; 3] {
+        self.previous_target
+    }
 
-        self.camera = (self.update_camera)(&self.camera, t);
-        self.target = (self.update_target)(&self.target, t);
+    pub fn is_cut(&self) -> bool {
+        self.is_cut
+    }
 
-        for leipae in self.leipaes.iter_mut() {
-            leipae.update(dt);
-        }
+    pub fn materials(&self) -> [Material; MATERIAL_COUNT] {
+        self.scene.materials()
+    }
 
-        self.last_tick = SystemTime::now();
+    pub fn effects(&self) -> &[Effect] {
+        self.effects
     }
 
-    fn set_scene_duration(&mut self, duration: f32) {
-        self.last_tick = SystemTime::now();
-        self.time = Duration::default();
-        self.start = SystemTime::now();
-        self.end = Duration::from_secs_f32(duration);
+    // The scene's focus with the target found, `aspect` is the width of the
+    // image divided by the height and `max_dist` where the rays give up
+    pub fn lens(&self, aspect: f32, max_dist: f32) -> Lens {
+        let focus = self.scene.focus(self.scene_time());
+        let distance = |point: [f32; 3]| sdf::length(sdf::sub(point, self.camera));
+
+        let focus_distance = match focus.target {
+            FocusTarget::Distance(distance) => distance,
+            FocusTarget::Target => distance(self.target),
+            FocusTarget::NearestLeipae => {
+                let frustum = Frustum::new(self.camera, self.target, aspect, max_dist);
+
+                self.leipae()
+                    .iter()
+                    .filter(|&&[x, y, z, scale]| frustum.contains([x, y, z], LEIPAE_RADIUS / scale))
+                    .map(|&[x, y, z, _]| distance([x, y, z]))
+                    .min_by(f32::total_cmp)
+                    .unwrap_or_else(|| distance(self.target))
+            }
+        };
+
+        Lens {
+            focus_distance,
+            aperture: focus.aperture,
+            bokeh: focus.bokeh,
+        }
     }
 
-    fn next_scene(&mut self) {
-        self.scene_idx += 1;
-        self.scene = SCENE_ORDER[self.scene_idx % SCENE_ORDER.len()];
+    // Advances the demo by `dt`, the time elapsed since the previous update,
+    // scaled by the playback speed. Nothing moves while paused.
+    pub fn update(&mut self, dt: Duration) {
+        // Scene skips between updates count as cuts too
+        let is_cut = mem::replace(&mut self.is_cut, false);
 
-        match self.scene {
-            Scene::Init => {
-                self.scene = Scene::Intro;
-            }
-            Scene::Intro => {
-                self.set_scene_duration(30.0);
+        self.previous_camera = self.camera;
+        self.previous_target = self.target;
+
+        if !self.is_paused {
+            let delta = dt.mul_f64(self.speed.abs() as f64);
 
-                self.update_camera = |_pos: &[f32; 3], t: f32| {
-                    [-20.0 * f32::cos(t / 20.0), 2.0, 30.0 * f32::sin(t / 20.0)]
-                };
-                self.update_target = |_pos: &[f32; 3], t: f32| [0.0, 2.0 * f32::sin(t / 10.0), 0.0];
+            if self.speed >= 0.0 {
+                self.seek(self.day_time + delta);
+            } else {
+                self.seek(self.day_time.saturating_sub(delta));
             }
-            Scene::Closeup => {
-                self.set_scene_duration(5.0);
+        }
 
-                self.target = [0.0, 0.0, 0.0];
+        self.is_cut |= is_cut;
+        if self.is_cut {
+            self.previous_camera = self.camera;
+            self.previous_target = self.target;
+        }
+    }
 
-                self.update_camera = |_pos: &[f32; 3], t: f32| {
-                    [5.0 * f32::cos(t / 40.0), 2.0, 4.0 * f32::sin(t / 40.0)]
-                };
-                self.update_target = noop_movement;
-            }
-            Scene::TopToForward => {
-                self.set_scene_duration(15.0);
+    // Jumps to the given time on the timeline. Reaching the end of the
+    // timeline exits.
+    pub fn seek(&mut self, day_time: Duration) {
+        if matches!(self.timeline.end, Some(end) if day_time >= end) {
+            self.is_exit = true;
+            return;
+        }
 
-                self.update_camera = |_pos: &[f32; 3], t: f32| [0.0, 3.0 - t / 10.0, -t / 10.0];
-                self.update_target = |_pos: &[f32; 3], t: f32| [0.0, 0.0, -1.0 - t];
-            }
-            Scene::ForwardToTop => {
-                self.set_scene_duration(15.0);
+        let scenes = &self.timeline.scenes;
+        let mut start = Duration::default();
+        let mut scene_idx = scenes.len() - 1;
+
+        for (i, scene) in scenes.iter().enumerate() {
+            let duration = Duration::from_secs_f32(scene.duration());
 
-                self.update_camera =
-                    |_pos: &[f32; 3], t: f32| [0.0, 1.5 + t / 10.0, -2.0 + t / 10.0];
-                self.update_target = |_pos: &[f32; 3], t: f32| [0.0, 0.0, -20.0 + t];
+            if *scene == Scene::Ending || day_time < start + duration {
+                scene_idx = i;
+                break;
             }
-            Scene::MovingForward => {
-                self.set_scene_duration(15.0);
 
-                self.target = [3.0, 0.8, -100.0];
+            start += duration;
+        }
 
-                self.update_camera = |_pos: &[f32; 3], t: f32| [3.0, 1.1, t / 15.0 * -20.0];
-                self.update_target = noop_movement;
-            }
-            Scene::MovingUp => {
-                self.set_scene_duration(20.0);
+        if scene_idx != self.scene_idx {
+            self.is_cut = true;
+        }
 
-                self.target = [3.0, 0.0, -50.0];
+        self.scene_idx = scene_idx;
+        self.scene = scenes[scene_idx];
+        self.end = Duration::from_secs_f32(self.scene.duration());
+        self.effects = self.scene.effects();
 
-                self.update_camera = |_pos: &[f32; 3], t: f32| [3.0, 0.9 + t / 10.0, 0.0];
-                self.update_target = noop_movement;
-            }
-            Scene::BackwardsCircle => {
-                self.set_scene_duration(10.0);
-
-                self.update_camera = |_pos: &[f32; 3], t: f32| {
-                    [
-                        10.0 + -10.0 * f32::sin(t / 20.0),
-                        2.0,
-                        -10.0 * f32::cos(t / 20.0),
-                    ]
-                };
-                self.update_target = |_pos: &[f32; 3], t: f32| [-10.0 + t, 2.0, -100.0];
-            }
-            Scene::Ending => {
-                self.is_exit = true;
-            }
+        if self.scene == Scene::Ending {
+            self.day_time = start;
+            self.time = Duration::default();
+            self.is_exit = true;
+            return;
         }
+
+        self.day_time = day_time;
+        self.time = day_time - start;
+
+        let t = self.time.as_secs_f32();
+        self.camera = self.scene.camera(t);
+        self.target = self.scene.target(t);
     }
 }
 
@@ -289,4 +729,121 @@ impl Default for Demo {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    const SEED: u64 = 2022;
+
+    fn seconds(seconds: f32) -> Duration {
+        Duration::from_secs_f32(seconds)
+    }
+
+    #[test]
+    fn seek_finds_the_scene() {
+        let mut demo = Demo::with_seed(Timeline::default(), SEED);
+
+        // 5 seconds into the second scene
+        demo.seek(seconds(20.0));
+        assert_eq!(demo.scene(), Scene::ForwardToTop);
+        assert_eq!(demo.scene_time(), 5.0);
+        assert_eq!(demo.day_time(), 20.0);
+        assert!(!demo.should_exit());
+
+        demo.seek(seconds(15.0));
+        assert_eq!(demo.scene(), Scene::ForwardToTop);
+        assert_eq!(demo.scene_time(), 0.0);
+    }
+
+    #[test]
+    fn seek_past_the_last_scene_exits() {
+        let mut demo = Demo::with_seed(Timeline::default(), SEED);
+
+        demo.seek(seconds(1000.0));
+        assert_eq!(demo.scene(), Scene::Ending);
+        assert!(demo.should_exit());
+    }
+
+    #[test]
+    fn timeline_starts_and_ends_where_set() {
+        let timeline = Timeline::default().starting_at(40.0).ending_at(50.0);
+        let mut demo = Demo::with_seed(timeline, SEED);
+
+        assert_eq!(demo.scene(), Scene::TopToForward);
+        assert_eq!(demo.day_time(), 40.0);
+
+        demo.update(seconds(9.0));
+        assert!(!demo.should_exit());
+        demo.update(seconds(1.0));
+        assert!(demo.should_exit());
+    }
+
+    #[test]
+    fn timeline_of_a_scene_outside_the_demo() {
+        let mut demo = Demo::with_seed(Timeline::only_scene(Scene::Closeup), SEED);
+
+        // The init marker takes no time
+        assert_eq!(demo.scene(), Scene::Closeup);
+
+        demo.update(seconds(4.0));
+        assert_eq!(demo.scene(), Scene::Closeup);
+        demo.update(seconds(1.0));
+        assert!(demo.should_exit());
+    }
+
+    #[test]
+    fn timeline_sets_the_leipae_count() {
+        let demo = Demo::with_seed(Timeline::default().with_leipae(3), SEED);
+        assert_eq!(demo.leipae().len(), 3);
+
+        // The same seed places them the same way
+        let again = Demo::with_seed(Timeline::default().with_leipae(3), SEED);
+        assert_eq!(demo.leipae(), again.leipae());
+    }
+
+    #[test]
+    fn speed_doubles_and_halves_within_the_limits() {
+        let mut demo = Demo::with_seed(Timeline::default(), SEED);
+
+        for _ in 0..10 {
+            demo.faster();
+        }
+        assert_eq!(demo.speed(), MAX_SPEED);
+
+        for _ in 0..10 {
+            demo.slower();
+        }
+        assert_eq!(demo.speed(), MIN_SPEED);
+
+        demo.reverse();
+        assert_eq!(demo.speed(), -MIN_SPEED);
+        demo.faster();
+        assert_eq!(demo.speed(), -2.0 * MIN_SPEED);
+    }
+
+    #[test]
+    fn speed_scales_the_updates() {
+        let mut demo = Demo::with_seed(Timeline::default().starting_at(10.0), SEED);
+
+        demo.faster();
+        demo.update(seconds(1.0));
+        assert_eq!(demo.day_time(), 12.0);
+
+        // Backwards stops at the start of the demo
+        demo.reverse();
+        demo.update(seconds(1.0));
+        assert_eq!(demo.day_time(), 10.0);
+        demo.update(seconds(10.0));
+        assert_eq!(demo.day_time(), 0.0);
+
+        demo.toggle_pause();
+        demo.reverse();
+        demo.update(seconds(1.0));
+        assert_eq!(demo.day_time(), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::clock::{Clock, FixedClock};
+use crate::demo::Demo;
+use crate::renderer::Renderer;
+use crate::supersampling::{self, Sample, SampleFilter};
+use crate::temporal;
+
+// Renders the demo offline with a fixed timestep and writes every frame as a
+// numbered PPM image, ready for encoding with for example
+// `ffmpeg -framerate 60 -i frame_%05d.ppm leipae.mp4`
+#[derive(Debug)]
+pub struct Exporter {
+    directory: PathBuf,
+    fps: f32,
+    // Number of sub-frames averaged into each output frame, spread over the
+    // frame time and subpixel offsets for motion blur and antialiasing
+    sub_frames: u32,
+    // Subpixel offsets and weights rendered for every sub-frame when supersampling
+    pattern: Option<Vec<Sample>>,
+}
+
+impl Exporter {
+    pub fn new(
+        directory: PathBuf,
+        fps: f32,
+        sub_frames: u32,
+        supersample: u32,
+        filter: SampleFilter,
+    ) -> Self {
+        let pattern = if supersample > 1 {
+            Some(supersampling::sample_pattern(supersample, filter))
+        } else {
+            None
+        };
+
+        Self {
+            directory,
+            fps,
+            sub_frames,
+            pattern,
+        }
+    }
+
+    pub unsafe fn run(
+        &self,
+        demo: &mut Demo,
+        renderer: &mut Renderer,
+    ) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.directory)?;
+
+        let step = Duration::from_secs_f32(1.0 / self.fps) / self.sub_frames;
+        let mut clock = FixedClock::new(step);
+
+        let mut frame = 0;
+        while !demo.should_exit() {
+            let mut accumulated: Vec<f32> = Vec::new();
+
+            // Only the scene is accumulated. The temporal filter would blend
+            // every sample with the previous ones and the grain would average
+            // out, so the effects run once on the result.
+            for sub_frame in 0..self.sub_frames {
+                // The first frame is at the start of the demo
+                if frame > 0 || sub_frame > 0 {
+                    demo.update(clock.tick());
+                }
+
+                for sample in self.samples(sub_frame) {
+                    let pixels = renderer.render_scene(demo, sample.offset)?.read_rgba();
+                    let weight = sample.weight / self.sub_frames as f32;
+
+                    accumulated.resize(pixels.len(), 0.0);
+                    for (sum, value) in accumulated.iter_mut().zip(pixels) {
+                        *sum += value * weight;
+                    }
+                }
+            }
+
+            renderer.scene_target().write_rgba(&accumulated);
+            let output = renderer.post_process(demo);
+            let pixels = output.read_pixels();
+
+            let path = self.directory.join(format!("frame_{:05}.ppm", frame));
+            write_ppm(
+                &path,
+                output.width() as usize,
+                output.height() as usize,
+                &pixels,
+            )?;
+
+            frame += 1;
+        }
+
+        println!("Exported {} frames to {}", frame, self.directory.display());
+
+        Ok(())
+    }
+
+    fn samples(&self, sub_frame: u32) -> Vec<Sample> {
+        match &self.pattern {
+            Some(pattern) => pattern.clone(),
+            // Without supersampling the sub-frames are spread over the pixel instead
+            None if self.sub_frames > 1 => vec![Sample {
+                offset: temporal::jitter(sub_frame),
+                weight: 1.0,
+            }],
+            None => vec![Sample {
+                offset: [0.0, 0.0],
+                weight: 1.0,
+            }],
+        }
+    }
+}
+
+// Writes bottom to top RGB rows as a binary PPM
+fn write_ppm(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[f32],
+) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", width, height)?;
+
+    for row in pixels.chunks_exact(width * 3).rev() {
+        let bytes: Vec<u8> = row
+            .iter()
+            .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+        file.write_all(&bytes)?;
+    }
+
+    file.flush()?;
+
+    Ok(())
+}
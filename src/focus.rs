@@ -0,0 +1,60 @@
+// What the camera focuses on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusTarget {
+    // A fixed distance from the camera
+    Distance(f32),
+    // The point the camera looks at, `Demo::target`
+    Target,
+    // The nearest leipae in view, or the target when there are none
+    NearestLeipae,
+}
+
+// Shape of the out of focus highlights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bokeh {
+    Circle,
+    // An aperture of this many straight blades, at least 3
+    Polygon(u32),
+}
+
+impl Bokeh {
+    // `iBokehBlades`, 0 for the circle
+    pub fn blades(self) -> u32 {
+        match self {
+            Bokeh::Circle => 0,
+            Bokeh::Polygon(blades) => blades.max(3),
+        }
+    }
+}
+
+// Depth of field of the camera, chosen per scene by `Scene::focus`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Focus {
+    pub target: FocusTarget,
+    // Blur radius of points at infinity relative to the image height, 0 keeps
+    // everything sharp
+    pub aperture: f32,
+    pub bokeh: Bokeh,
+}
+
+impl Focus {
+    pub const fn new(target: FocusTarget, aperture: f32, bokeh: Bokeh) -> Self {
+        Self {
+            target,
+            aperture,
+            bokeh,
+        }
+    }
+}
+
+// Everything sharp
+pub const DEFAULT_FOCUS: Focus = Focus::new(FocusTarget::Target, 0.0, Bokeh::Circle);
+
+// The focus of the current frame with the target resolved to a distance, see
+// `Demo::lens`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lens {
+    pub focus_distance: f32,
+    pub aperture: f32,
+    pub bokeh: Bokeh,
+}
@@ -0,0 +1,223 @@
+use std::error::Error;
+use std::ptr;
+
+use gl::types::*;
+
+#[derive(Debug)]
+pub struct Framebuffer {
+    fbo: GLuint,
+    // Color attachments in order and their internal formats, the first one is
+    // what gets blitted and read back
+    textures: Vec<GLuint>,
+    formats: Vec<GLenum>,
+
+    width: i32,
+    height: i32,
+}
+
+impl Framebuffer {
+    pub unsafe fn new(width: i32, height: i32) -> Result<Self, Box<dyn Error>> {
+        Self::with_format(width, height, gl::RGBA16F)
+    }
+
+    // Full precision floats for reading values back rather than colors
+    pub unsafe fn with_format(
+        width: i32,
+        height: i32,
+        format: GLenum,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_attachments(width, height, &[format])
+    }
+
+    // Multiple render targets, the fragment shader writes to the attachments
+    // with `layout(location = N)` in the order of the formats
+    pub unsafe fn with_attachments(
+        width: i32,
+        height: i32,
+        formats: &[GLenum],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut fbo: GLuint = 0;
+        let mut textures = vec![0; formats.len()];
+
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::GenTextures(textures.len() as GLsizei, textures.as_mut_ptr());
+
+        let mut framebuffer = Self {
+            fbo,
+            textures,
+            formats: formats.to_vec(),
+            width,
+            height,
+        };
+
+        framebuffer.resize(width, height)?;
+
+        Ok(framebuffer)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn texture(&self) -> GLuint {
+        self.textures[0]
+    }
+
+    // Texture of the color attachment at the given index
+    pub fn attachment(&self, index: usize) -> GLuint {
+        self.textures[index]
+    }
+
+    // Reallocates the color attachments, contents are undefined afterwards
+    pub unsafe fn resize(&mut self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        self.width = width;
+        self.height = height;
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+
+        let mut draw_buffers = Vec::with_capacity(self.textures.len());
+        for (index, (&texture, &format)) in self.textures.iter().zip(&self.formats).enumerate() {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                ptr::null(),
+            );
+            // Full floats can't be filtered on GLES and WebGL
+            let filter = if format == gl::RGBA32F {
+                gl::NEAREST
+            } else {
+                gl::LINEAR
+            };
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as GLint);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+
+            let attachment = gl::COLOR_ATTACHMENT0 + index as GLenum;
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, texture, 0);
+            draw_buffers.push(attachment);
+        }
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        gl::DrawBuffers(draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(format!("framebuffer incomplete: 0x{:x}", status).into());
+        }
+
+        Ok(())
+    }
+
+    // Binds the framebuffer as the render target and sets the viewport to cover it
+    pub unsafe fn bind(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        gl::Viewport(0, 0, self.width, self.height);
+    }
+
+    // Copies the contents to another framebuffer of the same size
+    pub unsafe fn blit_to(&self, target: &Framebuffer) {
+        self.blit(target.fbo, target.width, target.height, gl::NEAREST);
+    }
+
+    // Copies the contents to the default framebuffer, scaling to the given size
+    pub unsafe fn blit_to_screen(&self, width: i32, height: i32, filter: GLenum) {
+        self.blit(0, width, height, filter);
+    }
+
+    // Reads back the RGB contents, rows from bottom to top
+    pub unsafe fn read_pixels(&self) -> Vec<f32> {
+        self.read(gl::RGB, 3)
+    }
+
+    // Reads back all four channels, rows from bottom to top
+    pub unsafe fn read_rgba(&self) -> Vec<f32> {
+        self.read(gl::RGBA, 4)
+    }
+
+    // Replaces the contents of the first attachment with RGBA rows from bottom
+    // to top, as `read_rgba` returns them
+    pub unsafe fn write_rgba(&self, pixels: &[f32]) {
+        gl::BindTexture(gl::TEXTURE_2D, self.textures[0]);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            0,
+            self.width,
+            self.height,
+            gl::RGBA,
+            gl::FLOAT,
+            pixels.as_ptr() as *const _,
+        );
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+
+    unsafe fn read(&self, format: GLenum, channels: i32) -> Vec<f32> {
+        let mut pixels = vec![0.0; (self.width * self.height * channels) as usize];
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            self.width,
+            self.height,
+            format,
+            gl::FLOAT,
+            pixels.as_mut_ptr() as *mut std::ffi::c_void,
+        );
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+
+        pixels
+    }
+
+    unsafe fn blit(&self, fbo: GLuint, width: i32, height: i32, filter: GLenum) {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+        gl::BlitFramebuffer(
+            0,
+            0,
+            self.width,
+            self.height,
+            0,
+            0,
+            width,
+            height,
+            gl::COLOR_BUFFER_BIT,
+            filter,
+        );
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(self.textures.len() as GLsizei, self.textures.as_ptr());
+            gl::DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
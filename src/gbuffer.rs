@@ -0,0 +1,58 @@
+use std::error::Error;
+
+use gl::types::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::program::ShaderProgram;
+
+// The attachments in the order of the `layout(location = N)` outputs of the
+// scene shader, and the samplers they are bound to
+const ATTACHMENTS: [(GLenum, &str); 3] = [
+    // Hit position and depth, negative for the sky. Full floats, the shadow
+    // rays start from these.
+    (gl::RGBA32F, "iGPosition"),
+    // Surface normal and the marching steps of the ray
+    (gl::RGBA32F, "iGNormal"),
+    // Material ID and the variation of the albedo
+    (gl::RGBA16F, "iGMaterial"),
+];
+
+// What the raymarching pass hits, shaded by the lighting pass and read by
+// the screen space effects like ambient occlusion and outlines
+#[derive(Debug)]
+pub struct GBuffer {
+    target: Framebuffer,
+}
+
+impl GBuffer {
+    pub unsafe fn new(width: i32, height: i32) -> Result<Self, Box<dyn Error>> {
+        let formats: Vec<GLenum> = ATTACHMENTS.iter().map(|&(format, _)| format).collect();
+
+        Ok(Self {
+            target: Framebuffer::with_attachments(width, height, &formats)?,
+        })
+    }
+
+    pub unsafe fn resize(&mut self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        self.target.resize(width, height)
+    }
+
+    // Binds every attachment as the render target
+    pub unsafe fn bind(&self) {
+        self.target.bind();
+    }
+
+    // Binds the attachments to consecutive texture units from `first_unit` and
+    // points the program's samplers to them
+    pub unsafe fn bind_textures(&self, program: &ShaderProgram, first_unit: u32) {
+        for (index, &(_, sampler)) in ATTACHMENTS.iter().enumerate() {
+            let unit = first_unit + index as u32;
+
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.target.attachment(index));
+            program.set_uniform_i32(sampler, unit as i32);
+        }
+
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+}
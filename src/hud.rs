@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::error::Error;
+
+use crate::demo::{ClickMode, Demo};
+use crate::quality::Preset;
+use crate::renderer::DebugView;
+use crate::text::TextRenderer;
+
+const TEXT_SCALE: u32 = 2;
+const MARGIN: f32 = 16.0;
+const PADDING: f32 = 8.0;
+
+// Number of frames in the frame time graph, the fps is averaged over all of them
+const HISTORY_LENGTH: usize = 120;
+const GRAPH_BAR_WIDTH: f32 = 3.0;
+const GRAPH_HEIGHT: f32 = 80.0;
+// Frame time at the top of the graph in seconds
+const GRAPH_MAX_FRAME_TIME: f32 = 1.0 / 20.0;
+
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HIGHLIGHT: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const GRAPH_GUIDE: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+const GRAPH_FAST: [f32; 4] = [0.3, 0.9, 0.4, 0.9];
+const GRAPH_SLOW: [f32; 4] = [0.9, 0.3, 0.3, 0.9];
+
+// Debug overlay with the frame timing and the state of the demo, for rehearsals
+#[derive(Debug)]
+pub struct Hud {
+    text: TextRenderer,
+    // Seconds, most recent last
+    frame_times: VecDeque<f32>,
+    is_visible: bool,
+    click_mode: ClickMode,
+    // Uploaded to the scene shader in the latest frame
+    drawn_leipae: usize,
+}
+
+impl Hud {
+    pub unsafe fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            text: TextRenderer::new(TEXT_SCALE)?,
+            frame_times: VecDeque::with_capacity(HISTORY_LENGTH),
+            is_visible: false,
+            click_mode: ClickMode::Skip,
+            drawn_leipae: 0,
+        })
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_visible = !self.is_visible;
+    }
+
+    pub fn set_click_mode(&mut self, mode: ClickMode) {
+        self.click_mode = mode;
+    }
+
+    pub fn set_drawn_leipae(&mut self, count: usize) {
+        self.drawn_leipae = count;
+    }
+
+    // Recorded also while hidden so the graph is full as soon as it's shown
+    pub fn record_frame_time(&mut self, frame_time: f32) {
+        if self.frame_times.len() == HISTORY_LENGTH {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(frame_time);
+    }
+
+    // Draws the overlay on top of the default framebuffer of the given size
+    pub unsafe fn draw(
+        &mut self,
+        demo: &Demo,
+        view: DebugView,
+        quality: Preset,
+        scale: f32,
+        width: i32,
+        height: i32,
+    ) {
+        if !self.is_visible {
+            return;
+        }
+
+        let frame_time = if self.frame_times.is_empty() {
+            0.0
+        } else {
+            self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+        };
+        let fps = if frame_time > 0.0 {
+            1.0 / frame_time
+        } else {
+            0.0
+        };
+
+        let camera = demo.camera();
+        let target = demo.target();
+
+        let mut lines = vec![
+            format!("{:6.1} fps {:6.2} ms", fps, frame_time * 1000.0),
+            format!("scene  {:?}", demo.scene()),
+            format!(
+                "time   {:6.2} / {:6.2} s",
+                demo.scene_time(),
+                demo.scene_duration()
+            ),
+            format!("day    {:6.2} s", demo.day_time()),
+            format!(
+                "camera {:7.2} {:7.2} {:7.2}",
+                camera[0], camera[1], camera[2]
+            ),
+            format!(
+                "target {:7.2} {:7.2} {:7.2}",
+                target[0], target[1], target[2]
+            ),
+            format!(
+                "leipae {} / {} drawn",
+                self.drawn_leipae,
+                demo.leipae().len()
+            ),
+            format!("scale  {:.2}", scale),
+            format!("click  {:?}", self.click_mode),
+            format!("view   {:?}", view),
+            format!("detail {:?}", quality),
+        ];
+        if let Some(index) = demo.selected() {
+            let leipae = demo.leipae()[index];
+            lines.push(format!(
+                "leipae #{} {:.2} {:.2} {:.2} scale {:.2}",
+                index, leipae[0], leipae[1], leipae[2], leipae[3]
+            ));
+        }
+        let status = if demo.is_paused() {
+            "PAUSED".to_string()
+        } else {
+            format!("playing {:.2}x", demo.speed())
+        };
+
+        let line_height = self.text.line_height() + 2.0;
+        let graph_width = HISTORY_LENGTH as f32 * GRAPH_BAR_WIDTH;
+        let text_width = lines
+            .iter()
+            .map(|line| self.text.width(line))
+            .fold(graph_width, f32::max);
+
+        let panel_width = text_width + 2.0 * PADDING;
+        let panel_height = (lines.len() + 1) as f32 * line_height + GRAPH_HEIGHT + 3.0 * PADDING;
+        self.text
+            .rect(MARGIN, MARGIN, panel_width, panel_height, BACKGROUND);
+
+        let x = MARGIN + PADDING;
+        let mut y = MARGIN + PADDING;
+
+        for line in lines.iter() {
+            self.text.text(x, y, line, TEXT);
+            y += line_height;
+        }
+
+        let status_color = if demo.is_paused() { HIGHLIGHT } else { TEXT };
+        self.text.text(x, y, &status, status_color);
+        y += line_height + PADDING;
+
+        self.draw_graph(x, y);
+        self.text.draw(width, height);
+    }
+
+    // Bars of the recent frame times, with guides at 60 and 30 fps
+    fn draw_graph(&mut self, x: f32, y: f32) {
+        let bottom = y + GRAPH_HEIGHT;
+
+        for fps in [60.0, 30.0] {
+            let guide = bottom - GRAPH_HEIGHT / (fps * GRAPH_MAX_FRAME_TIME);
+            let width = HISTORY_LENGTH as f32 * GRAPH_BAR_WIDTH;
+            self.text.rect(x, guide, width, 1.0, GRAPH_GUIDE);
+        }
+
+        let offset = HISTORY_LENGTH - self.frame_times.len();
+        for (i, &frame_time) in self.frame_times.iter().enumerate() {
+            let height = (frame_time / GRAPH_MAX_FRAME_TIME).min(1.0) * GRAPH_HEIGHT;
+            let color = if frame_time > 1.0 / 30.0 {
+                GRAPH_SLOW
+            } else {
+                GRAPH_FAST
+            };
+
+            self.text.rect(
+                x + (offset + i) as f32 * GRAPH_BAR_WIDTH,
+                bottom - height,
+                GRAPH_BAR_WIDTH - 1.0,
+                height,
+                color,
+            );
+        }
+    }
+}
@@ -1,42 +1,166 @@
 #![allow(clippy::missing_safety_doc)]
 
+#[cfg(not(target_arch = "wasm32"))]
 use std::error::Error;
-use glutin::window::Fullscreen;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
 
+#[cfg(not(target_arch = "wasm32"))]
+use benchmark::Benchmark;
+#[cfg(not(target_arch = "wasm32"))]
+use demo::Demo;
+#[cfg(not(target_arch = "wasm32"))]
+use export::Exporter;
+#[cfg(not(target_arch = "wasm32"))]
+use glutin::window::Fullscreen;
+#[cfg(not(target_arch = "wasm32"))]
+use options::Options;
+#[cfg(all(not(target_arch = "wasm32"), feature = "player"))]
+use player::{Manifest, ShaderPlayer};
+#[cfg(not(target_arch = "wasm32"))]
 use processor::EventProcessor;
+#[cfg(not(target_arch = "wasm32"))]
 use renderer::Renderer;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+pub mod clock;
+pub mod culling;
+pub mod demo;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod export;
+pub mod focus;
+pub mod framebuffer;
+pub mod gbuffer;
+pub mod hud;
+pub mod material;
+pub mod noise;
+pub mod options;
+pub mod pack;
+#[cfg(all(not(target_arch = "wasm32"), feature = "player"))]
+pub mod player;
+pub mod postprocess;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod processor;
+pub mod profiler;
 pub mod program;
+pub mod quality;
+pub mod random;
 pub mod renderer;
+pub mod scaling;
+pub mod sdf;
 pub mod shader;
-pub mod demo;
+#[cfg(all(not(target_arch = "wasm32"), feature = "shadertoy"))]
+pub mod shadertoy;
+pub mod stats;
+pub mod supersampling;
+pub mod temporal;
+pub mod terrain;
+pub mod text;
+pub mod texture;
+#[cfg(all(not(target_arch = "wasm32"), feature = "verify"))]
+pub mod verify;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+#[cfg(target_arch = "wasm32")]
+pub mod webgl;
 
+#[cfg(not(target_arch = "wasm32"))]
 const WIDTH: f32 = 1920.0;
+#[cfg(not(target_arch = "wasm32"))]
 const HEIGHT: f32 = 1080.0;
 
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::start();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Box<dyn Error>> {
-    let event_loop = glutin::event_loop::EventLoop::new();
+    let options = Options::from_args()?;
+
+    // Generated from the shader sources alone, no window needed
+    #[cfg(feature = "shadertoy")]
+    if let Some(path) = &options.shadertoy {
+        return shadertoy::export(path);
+    }
+
+    let mut event_loop = glutin::event_loop::EventLoop::new();
     let window = glutin::window::WindowBuilder::new()
         .with_title("🍞 Leipae - by Cadiac 🍞")
-        .with_inner_size(glutin::dpi::LogicalSize::new(WIDTH, HEIGHT));
+        .with_inner_size(glutin::dpi::LogicalSize::new(WIDTH, HEIGHT))
+        .with_visible(options.export.is_none());
 
     let gl_window = glutin::ContextBuilder::new()
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3)))
         .with_gl_profile(glutin::GlProfile::Core)
+        .with_vsync(false)
         .build_windowed(window, &event_loop)
         .expect("failed to build gl_window");
 
     let gl_window = unsafe { gl_window.make_current() }.expect("failed to make context current");
 
-    gl_window.window().set_cursor_visible(false);
-    gl_window.window().set_fullscreen(Some(Fullscreen::Borderless(None)));
-
     gl::load_with(|symbol| gl_window.get_proc_address(symbol));
 
-    let renderer = Renderer::new(WIDTH, HEIGHT)?;
+    #[cfg(feature = "player")]
+    if let Some(path) = &options.play {
+        let size = gl_window.window().inner_size();
+        let player = unsafe { ShaderPlayer::new(Manifest::load(path)?, size.width, size.height)? };
+
+        return player.run(event_loop, gl_window);
+    }
+
+    #[cfg(feature = "verify")]
+    if options.verify_sdf {
+        return unsafe { verify::run(options.quality()) };
+    }
+
+    // The window only provides the context for exporting, frames are
+    // rendered offscreen at the full resolution regardless of its size
+    let (width, height) = match options.export {
+        Some(_) => (WIDTH, HEIGHT),
+        None => {
+            let size = gl_window.window().inner_size();
+            (size.width as f32, size.height as f32)
+        }
+    };
+    let mut renderer = Renderer::new(width, height, &options)?;
+
+    if let Some(directory) = options.export.clone() {
+        let exporter = Exporter::new(
+            directory,
+            options.fps,
+            options.sub_frames,
+            options.supersample,
+            options.supersample_filter,
+        );
+        return unsafe {
+            exporter.run(&mut Demo::with_timeline(options.timeline()), &mut renderer)?;
+            renderer.finish_profile()
+        };
+    }
+
+    if options.benchmark {
+        // Windowed at the default size so results compare between machines
+        return unsafe {
+            Benchmark::new(options.fps, options.timeline()).run(
+                &mut event_loop,
+                &mut renderer,
+                &gl_window,
+            )?;
+            renderer.finish_profile()
+        };
+    }
+
+    gl_window.window().set_cursor_visible(false);
+    gl_window
+        .window()
+        .set_fullscreen(Some(Fullscreen::Borderless(None)));
 
-    let mut processor = EventProcessor::new();
+    let mut processor = EventProcessor::new(
+        options.timeline(),
+        options.loop_gap.map(Duration::from_secs_f32),
+    );
 
     processor.run(event_loop, gl_window, renderer)
 }
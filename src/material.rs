@@ -0,0 +1,118 @@
+// The material table of the scene shader. The SDFs return a material ID and
+// the shading looks the rest up in the `Materials` uniform block, which is
+// filled from the timeline every frame.
+
+use std::ffi::CString;
+use std::mem;
+
+use gl::types::*;
+
+use crate::program::ShaderProgram;
+
+pub const MATERIAL_COUNT: usize = 5;
+
+// Binding point of the `Materials` block
+const MATERIALS_BINDING: GLuint = 0;
+
+// Index in the table, the MATERIAL_ constants of fragment.glsl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialId {
+    Terrain,
+    Water,
+    // The grid lines on the water along z and along x
+    NeonPink,
+    NeonCyan,
+    Crust,
+}
+
+impl MaterialId {
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// A `Material` of the block, laid out like the std140 struct
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub albedo: [f32; 3],
+    // 0 is a mirror, 1 a diffuse surface
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    // Strength of the sun's highlight
+    pub specular: f32,
+}
+
+impl Material {
+    pub const fn new(albedo: [f32; 3], roughness: f32, specular: f32, emissive: [f32; 3]) -> Self {
+        Self {
+            albedo,
+            roughness,
+            emissive,
+            specular,
+        }
+    }
+}
+
+pub const DEFAULT_MATERIALS: [Material; MATERIAL_COUNT] = [
+    // Terrain, darkened by the shader as the day goes on
+    Material::new([0.6, 0.6, 0.6], 1.0, 0.0, [0.0; 3]),
+    // Water
+    Material::new([0.0, 0.0, 0.0], 0.2, 0.5, [0.0; 3]),
+    Material::new([1.0, 0.0, 1.0], 1.0, 0.0, [0.3, 0.0, 0.3]),
+    Material::new([0.0, 1.0, 1.0], 1.0, 0.0, [0.0, 0.3, 0.3]),
+    // Crust, the shader adds noise to the albedo
+    Material::new([0.88, 0.52, 0.07], 0.6, 0.2, [0.0; 3]),
+];
+
+// Uniform buffer of the table
+#[derive(Debug)]
+pub struct MaterialBuffer {
+    ubo: GLuint,
+    // What the buffer holds, to upload only on changes
+    materials: Option<[Material; MATERIAL_COUNT]>,
+}
+
+impl MaterialBuffer {
+    pub unsafe fn new() -> Self {
+        let mut ubo = 0;
+        gl::GenBuffers(1, &mut ubo);
+
+        Self {
+            ubo,
+            materials: None,
+        }
+    }
+
+    // Points the program's `Materials` block to the buffer, needed once for
+    // every program
+    pub unsafe fn attach(&self, program: &ShaderProgram) {
+        let name = CString::new("Materials").unwrap();
+        let index = gl::GetUniformBlockIndex(program.id(), name.as_ptr());
+
+        // Not there when nothing in the shader uses the materials
+        if index != gl::INVALID_INDEX {
+            gl::UniformBlockBinding(program.id(), index, MATERIALS_BINDING);
+        }
+    }
+
+    pub unsafe fn bind(&mut self, materials: &[Material; MATERIAL_COUNT]) {
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, MATERIALS_BINDING, self.ubo);
+
+        if self.materials.as_ref() != Some(materials) {
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                mem::size_of_val(materials) as GLsizeiptr,
+                materials.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+            self.materials = Some(*materials);
+        }
+    }
+}
+
+impl Drop for MaterialBuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.ubo) }
+    }
+}
@@ -0,0 +1,151 @@
+use std::f32::consts::PI;
+
+// Operation for operation like fragment.glsl, for the terrain to agree as
+// closely as floats allow. The shader's 3.14159265 rounds to the same f32 as
+// `PI`.
+pub fn hash(x: [f32; 2]) -> f32 {
+    let ua = [50.0 * fract(x[0] / PI), 50.0 * fract(x[1] / PI)];
+
+    2.0 * fract(ua[0] * ua[1] * (ua[0] + ua[1])) - 1.0
+}
+
+pub fn valuenoise(x: [f32; 2]) -> f32 {
+    let integer = [x[0].floor(), x[1].floor()];
+    let fractional = [fract(x[0]), fract(x[1])];
+
+    let u = [
+        fractional[0] * fractional[0] * (3.0 - 2.0 * fractional[0]),
+        fractional[1] * fractional[1] * (3.0 - 2.0 * fractional[1]),
+    ];
+
+    let a = hash([integer[0], integer[1]]);
+    let b = hash([integer[0] + 1.0, integer[1]]);
+    let c = hash([integer[0], integer[1] + 1.0]);
+    let d = hash([integer[0] + 1.0, integer[1] + 1.0]);
+
+    let k0 = a;
+    let k1 = b - a;
+    let k2 = c - a;
+    let k4 = a - b - c + d;
+
+    k0 + k1 * u[0] + k2 * u[1] + k4 * u[0] * u[1]
+}
+
+pub fn fbm(x: [f32; 2], h: f32, octaves: u32) -> f32 {
+    let g = (-h).exp2();
+    let mut f = 1.0;
+    let mut a = 0.5;
+    let mut t = 0.0;
+
+    for _ in 0..octaves {
+        t += a * valuenoise([f * x[0], f * x[1]]);
+        f *= 1.9;
+        a *= g;
+    }
+
+    t
+}
+
+// Sine hash of the leipae texture, the sine of large arguments is where the
+// CPU and the GPU differ the most. The constants are as written in the shader.
+#[allow(clippy::excessive_precision)]
+pub fn rand(co: [f32; 2]) -> f32 {
+    fract((co[0] * 12.9898 + co[1] * 78.233).sin() * 43758.5453)
+}
+
+pub fn noise(p: [f32; 2]) -> f32 {
+    let ip = [p[0].floor(), p[1].floor()];
+    let u = [fract(p[0]), fract(p[1])];
+    let u = [
+        u[0] * u[0] * (3.0 - 2.0 * u[0]),
+        u[1] * u[1] * (3.0 - 2.0 * u[1]),
+    ];
+
+    let res = mix(
+        mix(rand(ip), rand([ip[0] + 1.0, ip[1]]), u[0]),
+        mix(
+            rand([ip[0], ip[1] + 1.0]),
+            rand([ip[0] + 1.0, ip[1] + 1.0]),
+            u[0],
+        ),
+        u[1],
+    );
+
+    res * res
+}
+
+pub fn mix(x: f32, y: f32, a: f32) -> f32 {
+    x * (1.0 - a) + y * a
+}
+
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+
+    t * t * (3.0 - 2.0 * t)
+}
+
+// GLSL's fract, unlike `f32::fract` positive for negative numbers too
+pub fn fract(x: f32) -> f32 {
+    x - x.floor()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn fract_of_negatives() {
+        assert_eq!(fract(-0.25), 0.75);
+        assert_eq!(fract(-3.0), 0.0);
+        assert_eq!(fract(2.5), 0.5);
+    }
+
+    #[test]
+    fn hash_of_the_origin() {
+        assert_eq!(hash([0.0, 0.0]), -1.0);
+    }
+
+    #[test]
+    fn hash_stays_in_range() {
+        for i in -50..50 {
+            let value = hash([i as f32 * 1.3, i as f32 * -0.7]);
+            assert!((-1.0..=1.0).contains(&value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn valuenoise_is_the_hash_at_integers() {
+        for point in [[0.0, 0.0], [3.0, -2.0], [-7.0, 11.0]] {
+            assert_eq!(valuenoise(point), hash(point));
+        }
+    }
+
+    #[test]
+    fn fbm_without_octaves() {
+        assert_eq!(fbm([1.5, -2.5], 1.0, 0), 0.0);
+    }
+
+    #[test]
+    fn fbm_octaves_halve() {
+        // With h = 1 every octave has half the amplitude of the previous one
+        let x = [0.37, -1.21];
+        let first = fbm(x, 1.0, 1);
+
+        assert_eq!(first, 0.5 * valuenoise(x));
+        assert_eq!(
+            fbm(x, 1.0, 2),
+            first + 0.25 * valuenoise([1.9 * x[0], 1.9 * x[1]])
+        );
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        for i in -50..50 {
+            let value = noise([i as f32 * 0.31, i as f32 * 0.17]);
+            assert!((0.0..=1.0).contains(&value), "{}", value);
+        }
+    }
+}
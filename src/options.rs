@@ -0,0 +1,364 @@
+use std::env;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::demo::{Scene, Timeline, LEIPAE_COUNT};
+use crate::profiler::ShaderSection;
+use crate::quality::{Override, Preset, Quality};
+use crate::renderer::DebugView;
+use crate::scaling::{UpscaleFilter, MAX_SCALE, MIN_SCALE};
+use crate::supersampling::SampleFilter;
+use crate::texture::ChannelBinding;
+
+// Upper limit of the times in seconds, far past the end of the demo but
+// small enough to be a `Duration`
+const MAX_SECONDS: f32 = 3600.0;
+// The fixed timestep gets too short to measure above this
+const MAX_FPS: f32 = 1000.0;
+const MAX_SUB_FRAMES: u32 = 256;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // Internal render resolution relative to the window size
+    pub scale: f32,
+    pub filter: UpscaleFilter,
+    // Enables dynamic resolution scaling when set
+    pub target_fps: Option<f32>,
+
+    pub antialiasing: bool,
+    // Fraction of the frame time the virtual shutter is open, zero disables
+    pub motion_blur: f32,
+    // Shows one term of the shading instead of the image
+    pub debug_view: DebugView,
+    pub quality: Preset,
+    // Values replacing the ones of the preset
+    pub quality_overrides: Vec<Override>,
+
+    // Renders the frames to this directory instead of a window when set
+    pub export: Option<PathBuf>,
+    // Frame rate of the fixed timestep when exporting or benchmarking
+    pub fps: f32,
+    pub sub_frames: u32,
+    // Renders N×N subpixel samples per exported frame
+    pub supersample: u32,
+    pub supersample_filter: SampleFilter,
+
+    // Part of the timeline to play, in seconds from the beginning of the demo
+    pub scene: Option<Scene>,
+    pub start: Option<f32>,
+    pub end: Option<f32>,
+    pub only_scene: Option<Scene>,
+    // How many leipae fall
+    pub leipae: usize,
+
+    // Starts over after a black gap of this many seconds instead of exiting
+    pub loop_gap: Option<f32>,
+
+    // Plays the timeline as fast as possible and prints the frame times
+    pub benchmark: bool,
+    // Writes GPU timings per pass and scene to this file on exit when set
+    pub profile: Option<PathBuf>,
+    // Parts of the scene shader compiled out, for measuring their cost
+    pub disabled_sections: Vec<ShaderSection>,
+
+    // Textures bound to the scene shader on top of the ones in the timeline
+    pub channels: Vec<ChannelBinding>,
+
+    // Writes the intro as a single Shadertoy shader to this file and exits
+    pub shadertoy: Option<PathBuf>,
+    // Plays a Shadertoy style shader or manifest of passes instead of the demo
+    pub play: Option<PathBuf>,
+    // Compares the CPU copy of the scene in sdf.rs with the shader and exits
+    pub verify_sdf: bool,
+}
+
+impl Options {
+    pub fn from_args() -> Result<Self, Box<dyn Error>> {
+        Self::parse(env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scale" => options.scale = value(&arg, args.next())?,
+                "--filter" => options.filter = value(&arg, args.next())?,
+                "--target-fps" => options.target_fps = Some(value(&arg, args.next())?),
+                "--taa" => options.antialiasing = true,
+                "--motion-blur" => options.motion_blur = value(&arg, args.next())?,
+                "--debug-view" => options.debug_view = value(&arg, args.next())?,
+                "--quality" => options.quality = value(&arg, args.next())?,
+                "--define" => options.quality_overrides.push(value(&arg, args.next())?),
+                "--export" => options.export = Some(value(&arg, args.next())?),
+                "--fps" => options.fps = value(&arg, args.next())?,
+                "--sub-frames" => options.sub_frames = value(&arg, args.next())?,
+                "--supersample" => options.supersample = value(&arg, args.next())?,
+                "--supersample-filter" => options.supersample_filter = value(&arg, args.next())?,
+                "--scene" => options.scene = Some(value(&arg, args.next())?),
+                "--start" => options.start = Some(value(&arg, args.next())?),
+                "--end" => options.end = Some(value(&arg, args.next())?),
+                "--only-scene" => options.only_scene = Some(value(&arg, args.next())?),
+                "--leipae" => options.leipae = value(&arg, args.next())?,
+                "--loop" => options.loop_gap = Some(options.loop_gap.unwrap_or(0.0)),
+                "--loop-gap" => options.loop_gap = Some(value(&arg, args.next())?),
+                "--benchmark" => options.benchmark = true,
+                "--profile" => options.profile = Some(value(&arg, args.next())?),
+                "--disable" => options.disabled_sections.push(value(&arg, args.next())?),
+                "--channel" => options.channels.push(value(&arg, args.next())?),
+                "--shadertoy" => options.shadertoy = Some(value(&arg, args.next())?),
+                "--play" => options.play = Some(value(&arg, args.next())?),
+                "--verify-sdf" => options.verify_sdf = true,
+                _ => return Err(format!("unknown argument: {}", arg).into()),
+            }
+        }
+
+        // Tools left out of the build with their features
+        let tools = [
+            (
+                "--shadertoy",
+                "shadertoy",
+                options.shadertoy.is_some(),
+                cfg!(feature = "shadertoy"),
+            ),
+            (
+                "--play",
+                "player",
+                options.play.is_some(),
+                cfg!(feature = "player"),
+            ),
+            (
+                "--verify-sdf",
+                "verify",
+                options.verify_sdf,
+                cfg!(feature = "verify"),
+            ),
+            (
+                "--profile",
+                "profiler",
+                options.profile.is_some(),
+                cfg!(feature = "profiler"),
+            ),
+        ];
+        for (name, feature, is_set, is_built) in tools {
+            if is_set && !is_built {
+                return Err(format!("{} needs the {} feature", name, feature).into());
+            }
+        }
+
+        if !(MIN_SCALE..=MAX_SCALE).contains(&options.scale) {
+            return Err(format!("--scale must be between {} and {}", MIN_SCALE, MAX_SCALE).into());
+        }
+
+        if let Some(fps) = options.target_fps {
+            if !(fps > 0.0 && fps <= MAX_FPS) {
+                return Err(
+                    format!("--target-fps must be positive and at most {}", MAX_FPS).into(),
+                );
+            }
+        }
+
+        if options.leipae == 0 {
+            return Err("--leipae must be at least 1".into());
+        }
+
+        if !(0.0..=1.0).contains(&options.motion_blur) {
+            return Err("--motion-blur must be between 0 and 1".into());
+        }
+
+        if options.benchmark && options.export.is_some() {
+            return Err("--benchmark can't be combined with --export".into());
+        }
+
+        if options.play.is_some() && (options.benchmark || options.export.is_some()) {
+            return Err("--play can't be combined with --benchmark or --export".into());
+        }
+
+        if options.verify_sdf
+            && (options.play.is_some() || options.benchmark || options.export.is_some())
+        {
+            return Err(
+                "--verify-sdf can't be combined with --play, --benchmark or --export".into(),
+            );
+        }
+
+        if options.scene.is_some() && options.start.is_some() {
+            return Err("--scene and --start can't be combined".into());
+        }
+
+        if options.only_scene.is_some()
+            && (options.scene.is_some() || options.start.is_some() || options.end.is_some())
+        {
+            return Err("--only-scene can't be combined with --scene, --start or --end".into());
+        }
+
+        if let Some(start) = options.start {
+            if !(0.0..=MAX_SECONDS).contains(&start) {
+                return Err(format!("--start must be between 0 and {}", MAX_SECONDS).into());
+            }
+        }
+
+        if let Some(end) = options.end {
+            if !(0.0..=MAX_SECONDS).contains(&end) {
+                return Err(format!("--end must be between 0 and {}", MAX_SECONDS).into());
+            }
+
+            let start = options
+                .start
+                .or_else(|| options.scene.and_then(Scene::start))
+                .unwrap_or(0.0);
+
+            if end <= start {
+                return Err("--end must be after the start".into());
+            }
+        }
+
+        if let Some(gap) = options.loop_gap {
+            if !(0.0..=MAX_SECONDS).contains(&gap) {
+                return Err(format!("--loop-gap must be between 0 and {}", MAX_SECONDS).into());
+            }
+        }
+
+        if !(options.fps > 0.0 && options.fps <= MAX_FPS) {
+            return Err(format!("--fps must be positive and at most {}", MAX_FPS).into());
+        }
+
+        if !(1..=MAX_SUB_FRAMES).contains(&options.sub_frames) {
+            return Err(format!("--sub-frames must be between 1 and {}", MAX_SUB_FRAMES).into());
+        }
+
+        if !(1..=16).contains(&options.supersample) {
+            return Err("--supersample must be between 1 and 16".into());
+        }
+
+        Ok(options)
+    }
+
+    // The preset with the `--define` overrides
+    pub fn quality(&self) -> Quality {
+        self.quality
+            .quality()
+            .with_overrides(&self.quality_overrides)
+    }
+
+    pub fn timeline(&self) -> Timeline {
+        let mut timeline = match (self.only_scene, self.scene) {
+            (Some(scene), _) => Timeline::only_scene(scene),
+            (None, Some(scene)) => Timeline::from_scene(scene),
+            (None, None) => Timeline::default(),
+        };
+
+        if let Some(start) = self.start {
+            timeline = timeline.starting_at(start);
+        }
+
+        if let Some(end) = self.end {
+            timeline = timeline.ending_at(end);
+        }
+
+        timeline.with_leipae(self.leipae)
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            filter: UpscaleFilter::Linear,
+            target_fps: None,
+            antialiasing: false,
+            motion_blur: 0.0,
+            debug_view: DebugView::Scene,
+            quality: Preset::High,
+            quality_overrides: Vec::new(),
+            export: None,
+            fps: 60.0,
+            sub_frames: 1,
+            supersample: 1,
+            supersample_filter: SampleFilter::Mitchell,
+            scene: None,
+            start: None,
+            end: None,
+            only_scene: None,
+            leipae: LEIPAE_COUNT,
+            loop_gap: None,
+            benchmark: false,
+            profile: None,
+            disabled_sections: Vec::new(),
+            channels: Vec::new(),
+            shadertoy: None,
+            play: None,
+            verify_sdf: false,
+        }
+    }
+}
+
+fn value<T>(name: &str, value: Option<String>) -> Result<T, Box<dyn Error>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = value.ok_or_else(|| format!("missing value for {}", name))?;
+
+    value
+        .parse()
+        .map_err(|err| format!("invalid value for {}: {}", name, err).into())
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, Box<dyn Error>> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_the_timeline() {
+        let options = parse(&["--start", "62.5", "--end", "80", "--loop-gap", "5"]).unwrap();
+
+        assert_eq!(options.start, Some(62.5));
+        assert_eq!(options.end, Some(80.0));
+        assert_eq!(options.loop_gap, Some(5.0));
+    }
+
+    #[test]
+    fn rejects_times_a_duration_cant_hold() {
+        for option in ["--start", "--end", "--loop-gap"] {
+            for time in ["NaN", "inf", "-inf", "1e30", "-1"] {
+                assert!(parse(&[option, time]).is_err(), "{} {}", option, time);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_an_end_before_the_start() {
+        assert!(parse(&["--start", "20", "--end", "10"]).is_err());
+        assert!(parse(&["--scene", "intro", "--end", "30"]).is_err());
+    }
+
+    #[test]
+    fn rejects_frame_rates_without_a_time_step() {
+        for option in ["--fps", "--target-fps"] {
+            for fps in ["0", "-60", "NaN", "inf", "1e30"] {
+                assert!(parse(&[option, fps]).is_err(), "{} {}", option, fps);
+            }
+        }
+
+        assert!(parse(&["--sub-frames", "0"]).is_err());
+        assert!(parse(&["--sub-frames", "4294967295"]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_arguments_and_missing_values() {
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["--fps"]).is_err());
+        assert!(parse(&["--fps", "sixty"]).is_err());
+    }
+}
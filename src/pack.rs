@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+pub const MIN_MATCH: usize = 3;
+
+// Embeds a file under src/, packed in compo builds
+#[cfg(not(feature = "compo"))]
+#[macro_export]
+macro_rules! asset {
+    ($path:literal) => {
+        $crate::pack::Asset::new(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/",
+            $path
+        )))
+    };
+}
+
+#[cfg(feature = "compo")]
+#[macro_export]
+macro_rules! asset {
+    ($path:literal) => {
+        $crate::pack::Asset::new(include_bytes!(concat!(env!("OUT_DIR"), "/", $path)))
+    };
+}
+
+// An embedded file. Compo builds have it packed with a small LZ77 by build.rs
+// and unpack it when used.
+#[derive(Debug, Clone, Copy)]
+pub struct Asset {
+    data: &'static [u8],
+}
+
+impl Asset {
+    pub const fn new(data: &'static [u8]) -> Self {
+        Self { data }
+    }
+
+    #[cfg(not(feature = "compo"))]
+    pub fn bytes(&self) -> Cow<'static, [u8]> {
+        Cow::Borrowed(self.data)
+    }
+
+    #[cfg(feature = "compo")]
+    pub fn bytes(&self) -> Cow<'static, [u8]> {
+        Cow::Owned(unpack(self.data))
+    }
+
+    pub fn text(&self) -> Cow<'static, str> {
+        match self.bytes() {
+            Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+            Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+        }
+    }
+}
+
+// A packed file starts with its unpacked length as a little endian u32. Then
+// a flag byte tells whether each of the next eight tokens is a literal byte
+// (bit clear) or a match (bit set, lowest bit first). A match is the offset
+// back minus one as a little endian u16 and the length minus `MIN_MATCH`.
+pub fn unpack(data: &[u8]) -> Vec<u8> {
+    let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    let mut out = Vec::with_capacity(len);
+    let mut i = 4;
+
+    while out.len() < len {
+        let flags = data[i];
+        i += 1;
+
+        for bit in 0..8 {
+            if out.len() >= len {
+                break;
+            }
+
+            if flags & (1 << bit) == 0 {
+                out.push(data[i]);
+                i += 1;
+            } else {
+                let offset = u16::from_le_bytes([data[i], data[i + 1]]) as usize + 1;
+                let start = out.len() - offset;
+
+                // Byte by byte, the match may overlap what it writes
+                for j in 0..data[i + 2] as usize + MIN_MATCH {
+                    out.push(out[start + j]);
+                }
+                i += 3;
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,645 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl::types::*;
+use glutin::dpi::PhysicalPosition;
+use glutin::event::{ElementState, Event, MouseButton, StartCause, VirtualKeyCode, WindowEvent};
+use glutin::event_loop::{ControlFlow, EventLoop};
+use glutin::platform::run_return::EventLoopExtRunReturn;
+use glutin::window::Window;
+use glutin::{ContextWrapper, PossiblyCurrent};
+
+use crate::clock::{Clock, SystemClock};
+use crate::framebuffer::Framebuffer;
+use crate::pack::Asset;
+use crate::program::ShaderProgram;
+use crate::renderer;
+use crate::shader::{Shader, ShaderError};
+use crate::texture::{TextureCache, TextureSpec, CHANNEL_COUNT};
+
+const VERTEX_SHADER: Asset = crate::asset!("shaders/vertex.glsl");
+
+// Declarations Shadertoy provides for every pass, the pass itself only
+// defines `mainImage`. The output has a name shaders aren't likely to use.
+const PRELUDE: &str = "#version 330 core
+
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform vec4 iDate;
+uniform vec3 iChannelResolution[4];
+uniform sampler2D iChannel0;
+uniform sampler2D iChannel1;
+uniform sampler2D iChannel2;
+uniform sampler2D iChannel3;
+
+out vec4 PlayerColor;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {
+    mainImage(PlayerColor, gl_FragCoord.xy);
+}
+";
+
+// Passes are rendered in this order every frame, the image pass to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PassKind {
+    BufferA,
+    BufferB,
+    BufferC,
+    BufferD,
+    Image,
+}
+
+impl PassKind {
+    // Index of the buffer the pass renders into, none for the image
+    fn buffer(self) -> Option<usize> {
+        match self {
+            PassKind::BufferA => Some(0),
+            PassKind::BufferB => Some(1),
+            PassKind::BufferC => Some(2),
+            PassKind::BufferD => Some(3),
+            PassKind::Image => None,
+        }
+    }
+}
+
+impl FromStr for PassKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buffer_a" => Ok(PassKind::BufferA),
+            "buffer_b" => Ok(PassKind::BufferB),
+            "buffer_c" => Ok(PassKind::BufferC),
+            "buffer_d" => Ok(PassKind::BufferD),
+            "image" => Ok(PassKind::Image),
+            _ => Err(format!(
+                "unknown pass: {}, expected common, buffer_a, buffer_b, buffer_c, buffer_d or image",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelInput {
+    Buffer(PassKind),
+    Texture(TextureSpec),
+}
+
+#[derive(Debug, Clone)]
+pub struct PassSource {
+    pub kind: PassKind,
+    pub path: PathBuf,
+    pub channels: [Option<ChannelInput>; CHANNEL_COUNT],
+}
+
+// Passes of a Shadertoy style shader. A manifest lists one pass per line as
+// the pass name, the shader file relative to the manifest and the buffers or
+// textures bound to `iChannel0` onwards, `-` leaving a channel empty:
+//
+//     # Trails that fade out over time
+//     common    common.glsl
+//     buffer_a  trails.glsl   buffer_a  noise
+//     image     image.glsl    buffer_a  crust.jpg,clamp
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    // Included at the top of every pass
+    pub common: Option<PathBuf>,
+    // In rendering order
+    pub passes: Vec<PassSource>,
+}
+
+impl Manifest {
+    // Loads a manifest, or a single image shader when given a `.glsl` file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if matches!(path.extension(), Some(extension) if extension == "glsl") {
+            return Ok(Self {
+                common: None,
+                passes: vec![PassSource {
+                    kind: PassKind::Image,
+                    path: path.to_path_buf(),
+                    channels: Default::default(),
+                }],
+            });
+        }
+
+        let src = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Self::parse(&src, directory).map_err(|err| format!("{}: {}", path.display(), err).into())
+    }
+
+    fn parse(src: &str, directory: &Path) -> Result<Self, String> {
+        let mut common = None;
+        let mut passes: Vec<PassSource> = Vec::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let path = match words.next() {
+                Some(file) => directory.join(file),
+                None => return Err(format!("line {}: missing the shader file", i + 1)),
+            };
+
+            if name == "common" {
+                common = Some(path);
+                continue;
+            }
+
+            let kind: PassKind = name
+                .parse()
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+            if passes.iter().any(|pass| pass.kind == kind) {
+                return Err(format!("line {}: {} is defined twice", i + 1, name));
+            }
+
+            let mut channels: [Option<ChannelInput>; CHANNEL_COUNT] = Default::default();
+            for (channel, input) in words.enumerate() {
+                if channel == CHANNEL_COUNT {
+                    return Err(format!("line {}: only {} channels", i + 1, CHANNEL_COUNT));
+                }
+
+                if input == "-" {
+                    continue;
+                }
+
+                let input = match input.parse() {
+                    Ok(PassKind::Image) => {
+                        return Err(format!("line {}: the image can't be read from", i + 1))
+                    }
+                    Ok(kind) => ChannelInput::Buffer(kind),
+                    Err(_) => ChannelInput::Texture(
+                        input
+                            .parse::<TextureSpec>()
+                            .map_err(|err| format!("line {}: {}", i + 1, err))?
+                            .relative_to(directory),
+                    ),
+                };
+
+                channels[channel] = Some(input);
+            }
+
+            passes.push(PassSource {
+                kind,
+                path,
+                channels,
+            });
+        }
+
+        passes.sort_by_key(|pass| pass.kind);
+
+        if passes.last().map(|pass| pass.kind) != Some(PassKind::Image) {
+            return Err("no image pass".to_string());
+        }
+
+        for pass in passes.iter() {
+            for input in pass.channels.iter().flatten() {
+                let input = match input {
+                    ChannelInput::Buffer(kind) => *kind,
+                    ChannelInput::Texture(_) => continue,
+                };
+
+                if !passes.iter().any(|other| other.kind == input) {
+                    return Err(format!(
+                        "{:?} reads {:?} which isn't defined",
+                        pass.kind, input
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { common, passes })
+    }
+}
+
+// Two framebuffers so a buffer can read its own previous frame while
+// rendering the next one
+#[derive(Debug)]
+struct Buffer {
+    targets: [Framebuffer; 2],
+    // Index of the target holding the latest frame
+    current: usize,
+}
+
+impl Buffer {
+    unsafe fn new(width: i32, height: i32) -> Result<Self, Box<dyn Error>> {
+        let buffer = Self {
+            targets: [
+                Framebuffer::new(width, height)?,
+                Framebuffer::new(width, height)?,
+            ],
+            current: 0,
+        };
+        buffer.clear();
+
+        Ok(buffer)
+    }
+
+    fn latest(&self) -> &Framebuffer {
+        &self.targets[self.current]
+    }
+
+    unsafe fn resize(&mut self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        for target in self.targets.iter_mut() {
+            target.resize(width, height)?;
+        }
+        self.clear();
+
+        Ok(())
+    }
+
+    // Shadertoy buffers start out black and transparent
+    unsafe fn clear(&self) {
+        for target in self.targets.iter() {
+            target.bind();
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+}
+
+// Shadertoy's `iMouse`: the position while a button is held and where it was
+// pressed, the latter negated once released
+#[derive(Debug, Default)]
+struct Mouse {
+    position: [f32; 2],
+    drag: [f32; 2],
+    click: [f32; 2],
+    is_down: bool,
+    // Only set for the first frame after a press
+    is_clicked: bool,
+}
+
+impl Mouse {
+    fn uniform4_f32(&self) -> [f32; 4] {
+        let z = if self.is_down {
+            self.click[0]
+        } else {
+            -self.click[0]
+        };
+        let w = if self.is_clicked {
+            self.click[1]
+        } else {
+            -self.click[1]
+        };
+
+        [self.drag[0], self.drag[1], z, w]
+    }
+}
+
+// Plays Shadertoy style shaders with up to four buffer passes, for
+// prototyping scenes before they're moved to `fragment.glsl`
+#[derive(Debug)]
+pub struct ShaderPlayer {
+    manifest: Manifest,
+    // One for each pass in the manifest
+    programs: Vec<ShaderProgram>,
+    buffers: [Option<Buffer>; 4],
+    textures: TextureCache,
+
+    width: i32,
+    height: i32,
+
+    clock: SystemClock,
+    time: f32,
+    frame: i32,
+    is_paused: bool,
+    mouse: Mouse,
+
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl ShaderPlayer {
+    pub unsafe fn new(manifest: Manifest, width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        let (width, height) = (width as i32, height as i32);
+
+        let programs = compile(&manifest)?;
+        let textures = load_textures(&manifest)?;
+
+        let mut buffers = [None, None, None, None];
+        for pass in manifest.passes.iter() {
+            if let Some(index) = pass.kind.buffer() {
+                buffers[index] = Some(Buffer::new(width, height)?);
+            }
+        }
+
+        let (vao, vbo) = renderer::fullscreen_quad();
+
+        Ok(Self {
+            manifest,
+            programs,
+            buffers,
+            textures,
+            width,
+            height,
+            clock: SystemClock::new(),
+            time: 0.0,
+            frame: 0,
+            is_paused: false,
+            mouse: Mouse::default(),
+            vao,
+            vbo,
+        })
+    }
+
+    // Reads the shaders and textures from disk again, keeping the old ones if
+    // they fail to load
+    pub unsafe fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let programs = compile(&self.manifest)?;
+        self.textures = load_textures(&self.manifest)?;
+        self.programs = programs;
+
+        Ok(())
+    }
+
+    // Starts over from the first frame with empty buffers
+    pub unsafe fn restart(&mut self) {
+        self.time = 0.0;
+        self.frame = 0;
+
+        for buffer in self.buffers.iter().flatten() {
+            buffer.clear();
+        }
+    }
+
+    pub unsafe fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.width = width as i32;
+        self.height = height as i32;
+
+        for buffer in self.buffers.iter_mut().flatten() {
+            buffer.resize(self.width, self.height)?;
+        }
+
+        Ok(())
+    }
+
+    // Renders every pass once with the time advanced by `dt` seconds. While
+    // paused only the image is drawn again so the buffers keep their state.
+    pub unsafe fn draw(&mut self, dt: f32) {
+        let dt = if self.is_paused { 0.0 } else { dt };
+        let date = date(SystemTime::now());
+        let mouse = self.mouse.uniform4_f32();
+
+        gl::BindVertexArray(self.vao);
+
+        for (pass, program) in self.manifest.passes.iter().zip(self.programs.iter()) {
+            let buffer = pass.kind.buffer();
+            if self.is_paused && buffer.is_some() {
+                continue;
+            }
+
+            program.activate();
+            program.set_uniform3_f32("iResolution", self.width as f32, self.height as f32, 1.0);
+            program.set_uniform_f32("iTime", self.time);
+            program.set_uniform_f32("iTimeDelta", dt);
+            program.set_uniform_i32("iFrame", self.frame);
+            program.set_uniform4_f32("iMouse", mouse[0], mouse[1], mouse[2], mouse[3]);
+            program.set_uniform4_f32("iDate", date[0], date[1], date[2], date[3]);
+
+            // Buffers rendered earlier in the frame give this frame's
+            // contents, the rest including the pass itself the previous frame
+            let mut resolutions = [[0.0; 3]; CHANNEL_COUNT];
+            for (channel, input) in pass.channels.iter().enumerate() {
+                let texture = match input {
+                    Some(ChannelInput::Buffer(kind)) => self.buffer(*kind).map(|framebuffer| {
+                        (
+                            framebuffer.texture(),
+                            framebuffer.width(),
+                            framebuffer.height(),
+                        )
+                    }),
+                    Some(ChannelInput::Texture(spec)) => self
+                        .textures
+                        .get(spec)
+                        .map(|texture| (texture.id(), texture.width(), texture.height())),
+                    None => None,
+                };
+
+                let texture = match texture {
+                    Some((id, width, height)) => {
+                        resolutions[channel] = [width as f32, height as f32, 1.0];
+                        id
+                    }
+                    None => 0,
+                };
+
+                gl::ActiveTexture(gl::TEXTURE0 + channel as GLenum);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                program.set_uniform_i32(&format!("iChannel{}", channel), channel as i32);
+            }
+            program.set_uniform3_f32v("iChannelResolution", &resolutions);
+
+            match buffer.and_then(|index| self.buffers[index].as_ref()) {
+                Some(buffer) => buffer.targets[1 - buffer.current].bind(),
+                None => {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    gl::Viewport(0, 0, self.width, self.height);
+                }
+            }
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+
+            if let Some(buffer) = buffer.and_then(|index| self.buffers[index].as_mut()) {
+                buffer.current = 1 - buffer.current;
+            }
+        }
+
+        for channel in 0..CHANNEL_COUNT {
+            gl::ActiveTexture(gl::TEXTURE0 + channel as GLenum);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindVertexArray(0);
+
+        if !self.is_paused {
+            self.time += dt;
+            self.frame += 1;
+            self.mouse.is_clicked = false;
+        }
+    }
+
+    pub fn run(
+        mut self,
+        mut event_loop: EventLoop<()>,
+        gl_window: ContextWrapper<PossiblyCurrent, Window>,
+    ) -> Result<(), Box<dyn Error>> {
+        let exit_code = event_loop.run_return(move |event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+
+            match event {
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::CursorMoved { position, .. } => self.move_mouse(position),
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state,
+                        ..
+                    } => {
+                        self.press_mouse(state == ElementState::Pressed);
+                    }
+                    WindowEvent::KeyboardInput { input, .. }
+                        if input.state == ElementState::Pressed =>
+                    {
+                        match input.virtual_keycode {
+                            Some(VirtualKeyCode::Escape) => *control_flow = ControlFlow::Exit,
+                            Some(VirtualKeyCode::R) => unsafe {
+                                // A typo while prototyping shouldn't close the window
+                                if let Err(err) = self.reload() {
+                                    eprintln!("{}", err);
+                                }
+                            },
+                            Some(VirtualKeyCode::T) => unsafe {
+                                self.restart();
+                            },
+                            Some(VirtualKeyCode::Space) => {
+                                self.is_paused = !self.is_paused;
+                            }
+                            _ => (),
+                        }
+                    }
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                        gl_window.resize(size);
+                        unsafe {
+                            self.resize(size.width, size.height).unwrap();
+                        }
+                    }
+                    _ => (),
+                },
+                Event::NewEvents(StartCause::Poll) | Event::RedrawRequested(_) => {
+                    let dt = self.clock.tick().as_secs_f32();
+
+                    unsafe {
+                        self.draw(dt);
+                    }
+
+                    gl_window.swap_buffers().unwrap();
+                }
+                _ => (),
+            }
+        });
+
+        if exit_code == 0 {
+            Ok(())
+        } else {
+            Err(format!("Exited with code: {}", exit_code).into())
+        }
+    }
+
+    fn buffer(&self, kind: PassKind) -> Option<&Framebuffer> {
+        self.buffers[kind.buffer()?].as_ref().map(Buffer::latest)
+    }
+
+    // Window coordinates have the origin at the top left, Shadertoy's at the
+    // bottom left
+    fn move_mouse(&mut self, position: PhysicalPosition<f64>) {
+        self.mouse.position = [position.x as f32, self.height as f32 - position.y as f32];
+
+        if self.mouse.is_down {
+            self.mouse.drag = self.mouse.position;
+        }
+    }
+
+    fn press_mouse(&mut self, is_down: bool) {
+        if is_down {
+            self.mouse.drag = self.mouse.position;
+            self.mouse.click = self.mouse.position;
+            self.mouse.is_clicked = true;
+        }
+
+        self.mouse.is_down = is_down;
+    }
+}
+
+impl Drop for ShaderPlayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+unsafe fn compile(manifest: &Manifest) -> Result<Vec<ShaderProgram>, Box<dyn Error>> {
+    let common = match &manifest.common {
+        Some(path) => read(path)?,
+        None => String::new(),
+    };
+
+    let mut programs = Vec::with_capacity(manifest.passes.len());
+
+    for pass in manifest.passes.iter() {
+        // Line numbers in the errors refer to the pass file
+        let src = format!("{}\n{}\n#line 1\n{}", PRELUDE, common, read(&pass.path)?);
+
+        let vs = Shader::new(&VERTEX_SHADER.text(), gl::VERTEX_SHADER)?;
+        let fs = Shader::new(&src, gl::FRAGMENT_SHADER)
+            .map_err(|ShaderError(err)| format!("{}: {}", pass.path.display(), err))?;
+
+        let program = ShaderProgram::new(vs, fs)
+            .map_err(|ShaderError(err)| format!("{}: {}", pass.path.display(), err))?;
+        programs.push(program);
+    }
+
+    Ok(programs)
+}
+
+unsafe fn load_textures(manifest: &Manifest) -> Result<TextureCache, Box<dyn Error>> {
+    let mut textures = TextureCache::default();
+
+    for pass in manifest.passes.iter() {
+        for input in pass.channels.iter().flatten() {
+            if let ChannelInput::Texture(spec) = input {
+                textures.load(spec)?;
+            }
+        }
+    }
+
+    Ok(textures)
+}
+
+fn read(path: &Path) -> Result<String, Box<dyn Error>> {
+    fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err).into())
+}
+
+// Year, month from zero, day of the month and seconds since midnight, in UTC
+// as there's no time zone database to look the local time up from
+fn date(now: SystemTime) -> [f32; 4] {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let days = (since_epoch.as_secs() / 86400) as i64;
+    let seconds = since_epoch.as_secs_f64() - days as f64 * 86400.0;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    [year as f32, (month - 1) as f32, day as f32, seconds as f32]
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+
+use crate::focus::Lens;
+use crate::framebuffer::Framebuffer;
+use crate::gbuffer::GBuffer;
+use crate::pack::Asset;
+use crate::program::ShaderProgram;
+use crate::shader::Shader;
+
+const VERTEX_SHADER: Asset = crate::asset!("shaders/vertex.glsl");
+
+// First texture unit of the G-buffer, after `iChannel0`
+const GBUFFER_UNIT: u32 = 1;
+
+// The order in which the enabled effects are applied
+pub const EFFECT_ORDER: &[Effect] = &[
+    Effect::Occlusion,
+    Effect::Outlines,
+    Effect::DepthOfField,
+    Effect::Grade,
+    Effect::Bloom,
+    Effect::ChromaticAberration,
+    Effect::Scanlines,
+    Effect::FilmGrain,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    // Screen space ambient occlusion from the G-buffer positions and normals
+    Occlusion,
+    // Dark lines at silhouettes and creases of the G-buffer
+    Outlines,
+    // Blur by the distance from the focus, see focus.rs
+    DepthOfField,
+    // Colour grading and the fades at the beginning and the end
+    Grade,
+    Bloom,
+    ChromaticAberration,
+    Scanlines,
+    FilmGrain,
+}
+
+impl Effect {
+    fn source(self) -> Asset {
+        match self {
+            Effect::Occlusion => crate::asset!("shaders/post/occlusion.glsl"),
+            Effect::Outlines => crate::asset!("shaders/post/outlines.glsl"),
+            Effect::DepthOfField => crate::asset!("shaders/post/dof.glsl"),
+            Effect::Grade => crate::asset!("shaders/post/grade.glsl"),
+            Effect::Bloom => crate::asset!("shaders/post/bloom.glsl"),
+            Effect::ChromaticAberration => crate::asset!("shaders/post/chromatic.glsl"),
+            Effect::Scanlines => crate::asset!("shaders/post/scanlines.glsl"),
+            Effect::FilmGrain => crate::asset!("shaders/post/grain.glsl"),
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Effect::Occlusion => "src/shaders/post/occlusion.glsl",
+            Effect::Outlines => "src/shaders/post/outlines.glsl",
+            Effect::DepthOfField => "src/shaders/post/dof.glsl",
+            Effect::Grade => "src/shaders/post/grade.glsl",
+            Effect::Bloom => "src/shaders/post/bloom.glsl",
+            Effect::ChromaticAberration => "src/shaders/post/chromatic.glsl",
+            Effect::Scanlines => "src/shaders/post/scanlines.glsl",
+            Effect::FilmGrain => "src/shaders/post/grain.glsl",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PostPass {
+    effect: Effect,
+    program: ShaderProgram,
+}
+
+// Chain of fullscreen fragment passes, each reading the previous output from
+// `iChannel0` and writing to the other one of two ping-pong framebuffers. The
+// G-buffer of the scene is bound for the passes too.
+#[derive(Debug)]
+pub struct PostProcessor {
+    passes: Vec<PostPass>,
+    // The scene is rendered into the first one
+    targets: [Framebuffer; 2],
+    // Index of the target holding the result of the latest `apply`
+    output: usize,
+}
+
+impl PostProcessor {
+    pub unsafe fn new(width: i32, height: i32) -> Result<Self, Box<dyn Error>> {
+        let mut passes = Vec::with_capacity(EFFECT_ORDER.len());
+
+        for &effect in EFFECT_ORDER {
+            let vs = Shader::new(&VERTEX_SHADER.text(), gl::VERTEX_SHADER)?;
+            let fs = Shader::new(&effect.source().text(), gl::FRAGMENT_SHADER)?;
+
+            passes.push(PostPass {
+                effect,
+                program: ShaderProgram::new(vs, fs)?,
+            });
+        }
+
+        Ok(Self {
+            passes,
+            targets: [
+                Framebuffer::new(width, height)?,
+                Framebuffer::new(width, height)?,
+            ],
+            output: 0,
+        })
+    }
+
+    // Reloads the pass shaders from the source tree, for development purposes
+    pub unsafe fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        for pass in self.passes.iter_mut() {
+            let vs = Shader::from_file("src/shaders/vertex.glsl", gl::VERTEX_SHADER)?;
+            let fs = Shader::from_file(pass.effect.path(), gl::FRAGMENT_SHADER)?;
+
+            pass.program = ShaderProgram::new(vs, fs)?;
+        }
+
+        Ok(())
+    }
+
+    pub unsafe fn resize(&mut self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        for target in self.targets.iter_mut() {
+            target.resize(width, height)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn scene_target(&self) -> &Framebuffer {
+        &self.targets[0]
+    }
+
+    pub fn output(&self) -> &Framebuffer {
+        &self.targets[self.output]
+    }
+
+    // Runs the enabled effects over the scene target, returns the framebuffer
+    // holding the final image. Expects the fullscreen quad `vao` to be bound.
+    pub unsafe fn apply(
+        &mut self,
+        effects: &[Effect],
+        time: f32,
+        gbuffer: &GBuffer,
+        lens: &Lens,
+    ) -> &Framebuffer {
+        let mut current = 0;
+
+        for pass in self.passes.iter() {
+            if !effects.contains(&pass.effect) {
+                continue;
+            }
+
+            let source = &self.targets[current];
+            let target = &self.targets[1 - current];
+
+            target.bind();
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, source.texture());
+
+            pass.program.activate();
+            pass.program.set_uniform_i32("iChannel0", 0);
+            gbuffer.bind_textures(&pass.program, GBUFFER_UNIT);
+            pass.program.set_uniform2_f32(
+                "iResolution",
+                source.width() as f32,
+                source.height() as f32,
+            );
+            pass.program.set_uniform_f32("iTime", time);
+            pass.program
+                .set_uniform_f32("iFocusDistance", lens.focus_distance);
+            pass.program.set_uniform_f32("iAperture", lens.aperture);
+            pass.program
+                .set_uniform_i32("iBokehBlades", lens.bokeh.blades() as i32);
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+
+            current = 1 - current;
+        }
+
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        self.output = current;
+        &self.targets[current]
+    }
+}
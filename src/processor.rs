@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::time::Duration;
 
 use glutin::event::{ElementState, Event, MouseButton, StartCause, VirtualKeyCode, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
@@ -6,17 +7,37 @@ use glutin::platform::run_return::EventLoopExtRunReturn;
 use glutin::window::Window;
 use glutin::{ContextWrapper, PossiblyCurrent};
 
-use crate::demo::Demo;
+use crate::clock::{Clock, SystemClock};
+use crate::demo::{ClickMode, Demo, Timeline};
 use crate::renderer::Renderer;
+use crate::sdf::{self, SceneSdf, Surface, MIN_DIST};
 
 pub struct EventProcessor {
     demo: Demo,
+    // Part of the demo to play, and to start over from when looping
+    timeline: Timeline,
+    clock: SystemClock,
+
+    // Length of the black gap before starting over, none plays the demo once
+    loop_gap: Option<Duration>,
+    // Time left of the gap currently shown
+    gap: Option<Duration>,
+
+    click_mode: ClickMode,
+    // Last cursor position in window pixels from the top left
+    cursor: [f32; 2],
 }
 
 impl EventProcessor {
-    pub fn new() -> Self {
+    pub fn new(timeline: Timeline, loop_gap: Option<Duration>) -> Self {
         Self {
-            demo: Demo::new(),
+            demo: Demo::with_timeline(timeline.clone()),
+            timeline,
+            clock: SystemClock::new(),
+            loop_gap,
+            gap: None,
+            click_mode: ClickMode::Skip,
+            cursor: [0.0, 0.0],
         }
     }
 
@@ -30,17 +51,31 @@ impl EventProcessor {
             *control_flow = ControlFlow::Poll;
 
             match event {
-                Event::LoopDestroyed => return,
+                Event::LoopDestroyed => {
+                    // The context is still current here, but not after the loop
+                    unsafe {
+                        if let Err(err) = renderer.finish_profile() {
+                            eprintln!("Failed to write the GPU profile: {}", err);
+                        }
+                    }
+                    return;
+                }
                 Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::MouseInput { button: MouseButton::Left, state: ElementState::Pressed, .. } => {
-                        self.demo.skip_to_next();
+                    WindowEvent::CursorMoved { position, .. } => {
+                        self.cursor = [position.x as f32, position.y as f32];
+                    }
+                    WindowEvent::MouseInput { button: MouseButton::Left, state: ElementState::Pressed, .. } => match self.click_mode {
+                        // Nobody gets to skip scenes on a looping lobby screen
+                        ClickMode::Skip if self.loop_gap.is_none() => self.demo.skip_to_next(),
+                        ClickMode::Skip => (),
+                        ClickMode::Select | ClickMode::Spawn => self.click(&renderer),
                     },
-                    WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
+                    WindowEvent::KeyboardInput { input, .. } if input.state == ElementState::Pressed => match input.virtual_keycode {
                         Some(VirtualKeyCode::Escape) => *control_flow = ControlFlow::Exit,
                         Some(VirtualKeyCode::R) => {
                             unsafe {
                                 renderer.reload().unwrap();
-                                renderer.draw(&self.demo);
+                                renderer.draw(&self.demo).unwrap();
                                 gl_window.swap_buffers().unwrap();
                             };
                         }
@@ -48,41 +83,80 @@ impl EventProcessor {
                             self.demo.reset();
                         }
                         Some(VirtualKeyCode::Space) => {
-                            self.demo.pause();
+                            self.demo.toggle_pause();
+                        }
+                        Some(VirtualKeyCode::Right) => {
+                            self.demo.step(1);
+                        }
+                        Some(VirtualKeyCode::Left) => {
+                            self.demo.step(-1);
+                        }
+                        Some(VirtualKeyCode::Up) => {
+                            self.demo.faster();
+                        }
+                        Some(VirtualKeyCode::Down) => {
+                            self.demo.slower();
                         }
-                        Some(VirtualKeyCode::B) => {
-                            self.demo.resume();
+                        Some(VirtualKeyCode::Back) => {
+                            self.demo.reverse();
+                        }
+                        Some(VirtualKeyCode::H) => {
+                            renderer.toggle_hud();
+                        }
+                        Some(VirtualKeyCode::Q) => {
+                            if let Err(err) = unsafe { renderer.next_quality() } {
+                                eprintln!("Failed to switch the quality preset: {}", err);
+                            }
+                        }
+                        Some(VirtualKeyCode::V) => {
+                            renderer.next_debug_view();
+                        }
+                        Some(VirtualKeyCode::C) => {
+                            self.click_mode = self.click_mode.next();
+                            renderer.set_click_mode(self.click_mode);
+                            gl_window.window().set_cursor_visible(self.click_mode != ClickMode::Skip);
                         }
                         _ => (),
                     },
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(size) => {
-                        if size.width != 0 && size.height != 0 {
-                            gl_window.resize(size);
-                            unsafe {
-                                renderer.resize(size.width, size.height);
-                                gl::Viewport(0, 0, size.width as i32, size.height as i32);
-                            }
+                    WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                        gl_window.resize(size);
+                        unsafe {
+                            renderer.resize(size.width, size.height).unwrap();
                         }
                     }
                     _ => (),
                 },
                 Event::NewEvents(StartCause::Poll) | Event::RedrawRequested(_) => {
-                    if !self.demo.is_paused() {
-                        self.demo.update();
+                    // Ticking while paused too keeps the time from jumping on resume
+                    let dt = self.clock.tick();
+
+                    if let Some(gap) = self.gap {
+                        self.update_gap(gap, dt);
 
                         unsafe {
-                            renderer.draw(&self.demo);
+                            renderer.clear();
                         }
+                    } else {
+                        self.demo.update(dt);
 
-                        gl_window.swap_buffers().unwrap();
+                        // Paused frames are drawn too, to keep up with resizes,
+                        // reloads and the HUD
+                        unsafe {
+                            renderer.draw(&self.demo).unwrap();
+                        }
                     }
+
+                    gl_window.swap_buffers().unwrap();
                 }
                 _ => (),
             }
 
-            if self.demo.should_exit() {
-                *control_flow = ControlFlow::Exit
+            if self.demo.should_exit() && self.gap.is_none() {
+                match self.loop_gap {
+                    Some(gap) => self.gap = Some(gap),
+                    None => *control_flow = ControlFlow::Exit,
+                }
             }
         });
 
@@ -92,10 +166,56 @@ impl EventProcessor {
             Err(format!("Exited with code: {}", exit_code).into())
         }
     }
+
+    // Casts a ray through the cursor into the scene as the renderer draws it,
+    // and selects what it hits or spawns a leipae there
+    fn click(&mut self, renderer: &Renderer) {
+        let (width, height) = renderer.resolution();
+        let (width, height) = (width as f32, height as f32);
+        let camera = self.demo.camera();
+        // The shader counts pixels from the bottom left
+        let frag_coord = [self.cursor[0], height - self.cursor[1]];
+        let dir = sdf::camera_ray(camera, self.demo.target(), [width, height], frag_coord);
+
+        let mut scene = SceneSdf::from_demo(&self.demo).with_quality(renderer.quality());
+        if let Some(heightmap) = renderer.heightmap() {
+            scene = scene.with_heightmap(heightmap, camera);
+        }
+
+        let hit = match scene.ray_march(camera, dir, MIN_DIST, scene.max_dist()) {
+            Some(hit) => hit,
+            None => {
+                self.demo.select(None);
+                return;
+            }
+        };
+
+        let point = sdf::add(camera, sdf::scale(dir, hit.depth));
+        let surface = scene.surface_at(point);
+
+        match (self.click_mode, surface) {
+            (ClickMode::Spawn, _) => {
+                let index = self.demo.spawn_leipae(point);
+                self.demo.select(Some(index));
+            }
+            (_, Surface::Leipae(index)) => self.demo.select(Some(index)),
+            _ => self.demo.select(None),
+        }
+    }
+
+    // Starts the demo over from a clean state once the gap has passed
+    fn update_gap(&mut self, gap: Duration, dt: Duration) {
+        if gap > dt {
+            self.gap = Some(gap - dt);
+        } else {
+            self.gap = None;
+            self.demo = Demo::with_timeline(self.timeline.clone());
+        }
+    }
 }
 
 impl Default for EventProcessor {
     fn default() -> Self {
-        Self::new()
+        Self::new(Timeline::default(), None)
     }
 }
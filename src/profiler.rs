@@ -0,0 +1,333 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use gl::types::*;
+
+use crate::demo::Scene;
+use crate::stats::Stats;
+
+// GPU work measured separately within a frame, in the order they're rendered
+pub const PASSES: &[Pass] = &[
+    Pass::Scene,
+    Pass::Lighting,
+    Pass::Temporal,
+    Pass::Post,
+    Pass::Present,
+];
+const PASS_COUNT: usize = 5;
+
+// Query sets in flight, results are read back this many frames later so the
+// CPU doesn't have to wait for the GPU to catch up
+const QUERY_SETS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    // Raymarching the scene into the G-buffer
+    Scene,
+    // Shading the G-buffer, with the shadow and reflection rays
+    Lighting,
+    // Temporal antialiasing and motion blur
+    Temporal,
+    // Post processing chain
+    Post,
+    // Upscaling to the window and the HUD
+    Present,
+}
+
+impl Pass {
+    fn name(self) -> &'static str {
+        match self {
+            Pass::Scene => "scene",
+            Pass::Lighting => "lighting",
+            Pass::Temporal => "temporal",
+            Pass::Post => "post",
+            Pass::Present => "present",
+        }
+    }
+}
+
+// Parts of the scene shader that can be compiled out with a `#define` to
+// measure their cost by comparing profiles with and without them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderSection {
+    SoftShadows,
+    Sky,
+    Normals,
+    Leipae,
+    // The baked terrain, compiling it out marches the analytic terrain everywhere
+    Heightmap,
+    // The second ray from the water
+    Reflections,
+}
+
+impl ShaderSection {
+    pub fn define(self) -> &'static str {
+        match self {
+            ShaderSection::SoftShadows => "NO_SOFT_SHADOWS",
+            ShaderSection::Sky => "NO_SKY",
+            ShaderSection::Normals => "NO_NORMALS",
+            ShaderSection::Leipae => "NO_LEIPAE",
+            ShaderSection::Heightmap => "NO_HEIGHTMAP",
+            ShaderSection::Reflections => "NO_REFLECTIONS",
+        }
+    }
+}
+
+impl FromStr for ShaderSection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shadows" => Ok(ShaderSection::SoftShadows),
+            "sky" => Ok(ShaderSection::Sky),
+            "normals" => Ok(ShaderSection::Normals),
+            "leipae" => Ok(ShaderSection::Leipae),
+            "heightmap" => Ok(ShaderSection::Heightmap),
+            "reflections" => Ok(ShaderSection::Reflections),
+            _ => Err(format!(
+                "unknown section \"{}\", expected shadows, sky, normals, leipae, heightmap or reflections",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QuerySet {
+    queries: [GLuint; PASS_COUNT],
+    issued: [bool; PASS_COUNT],
+    // Scene the queries were issued in, none when there's nothing to read back
+    scene: Option<Scene>,
+}
+
+// Measures the GPU time of every pass with `GL_TIME_ELAPSED` queries and
+// aggregates the frame times by scene. Does nothing unless a report path is
+// given, and is compiled out without the `profiler` feature.
+#[derive(Debug)]
+pub struct GpuProfiler {
+    path: Option<PathBuf>,
+
+    sets: Vec<QuerySet>,
+    current: usize,
+    // Pass with a query running
+    active: Option<Pass>,
+
+    // Milliseconds per pass for every measured frame, by scene in the order
+    // they were first seen
+    frames: Vec<(Scene, Vec<[f32; PASS_COUNT]>)>,
+}
+
+impl GpuProfiler {
+    pub unsafe fn new(path: Option<PathBuf>) -> Self {
+        let mut sets = Vec::new();
+
+        if cfg!(feature = "profiler") && path.is_some() {
+            for _ in 0..QUERY_SETS {
+                let mut queries = [0; PASS_COUNT];
+                gl::GenQueries(PASS_COUNT as GLsizei, queries.as_mut_ptr());
+
+                sets.push(QuerySet {
+                    queries,
+                    issued: [false; PASS_COUNT],
+                    scene: None,
+                });
+            }
+        }
+
+        Self {
+            path,
+            sets,
+            current: 0,
+            active: None,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        cfg!(feature = "profiler") && self.path.is_some()
+    }
+
+    // Starts a new frame in the given scene, reading back the results of the
+    // frame that last used the same query set
+    pub unsafe fn begin_frame(&mut self, scene: Scene) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.current = (self.current + 1) % QUERY_SETS;
+        self.collect(self.current);
+
+        let set = &mut self.sets[self.current];
+        set.scene = Some(scene);
+        set.issued = [false; PASS_COUNT];
+    }
+
+    pub unsafe fn begin(&mut self, pass: Pass) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let set = &mut self.sets[self.current];
+        let index = pass_index(pass);
+
+        gl::BeginQuery(gl::TIME_ELAPSED, set.queries[index]);
+        set.issued[index] = true;
+        self.active = Some(pass);
+    }
+
+    pub unsafe fn end(&mut self) {
+        if self.active.take().is_some() {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+    }
+
+    // Reads back the queries still in flight and writes the report
+    pub unsafe fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let path = match self.path.clone() {
+            Some(path) if self.is_enabled() => path,
+            _ => return Ok(()),
+        };
+
+        self.end();
+        for index in 0..self.sets.len() {
+            self.collect(index);
+        }
+
+        let rows = self.summarize();
+        let report = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => to_json(&rows),
+            _ => to_csv(&rows),
+        };
+
+        fs::write(&path, report)?;
+        println!("Wrote GPU profile to {}", path.display());
+
+        Ok(())
+    }
+
+    unsafe fn collect(&mut self, index: usize) {
+        let set = &mut self.sets[index];
+        let scene = match set.scene.take() {
+            Some(scene) => scene,
+            None => return,
+        };
+
+        let mut times = [0.0; PASS_COUNT];
+        for (i, time) in times.iter_mut().enumerate() {
+            if set.issued[i] {
+                // Waits for the result if the GPU is more than a frame behind
+                let mut nanoseconds: GLuint64 = 0;
+                gl::GetQueryObjectui64v(set.queries[i], gl::QUERY_RESULT, &mut nanoseconds);
+                *time = nanoseconds as f32 / 1_000_000.0;
+            }
+        }
+
+        match self.frames.iter_mut().find(|(s, _)| *s == scene) {
+            Some((_, frames)) => frames.push(times),
+            None => self.frames.push((scene, vec![times])),
+        }
+    }
+
+    fn summarize(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut all = Vec::new();
+
+        for (scene, frames) in self.frames.iter() {
+            rows.extend(pass_rows(&format!("{:?}", scene), frames));
+            all.extend_from_slice(frames);
+        }
+
+        if !all.is_empty() {
+            rows.extend(pass_rows("all", &all));
+        }
+
+        rows
+    }
+}
+
+impl Drop for GpuProfiler {
+    fn drop(&mut self) {
+        unsafe {
+            for set in self.sets.iter() {
+                gl::DeleteQueries(PASS_COUNT as GLsizei, set.queries.as_ptr());
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Row {
+    scene: String,
+    pass: &'static str,
+    stats: Stats,
+}
+
+// One row per pass and one for the whole frame
+fn pass_rows(scene: &str, frames: &[[f32; PASS_COUNT]]) -> Vec<Row> {
+    let mut rows = Vec::with_capacity(PASS_COUNT + 1);
+
+    for &pass in PASSES {
+        let times: Vec<f32> = frames.iter().map(|frame| frame[pass_index(pass)]).collect();
+        rows.extend(row(scene, pass.name(), times));
+    }
+
+    let totals = frames.iter().map(|frame| frame.iter().sum()).collect();
+    rows.extend(row(scene, "frame", totals));
+
+    rows
+}
+
+fn row(scene: &str, pass: &'static str, times: Vec<f32>) -> Option<Row> {
+    Some(Row {
+        scene: scene.to_string(),
+        pass,
+        stats: Stats::new(times)?,
+    })
+}
+
+fn to_csv(rows: &[Row]) -> String {
+    let mut csv = String::from("scene,pass,frames,min_ms,avg_ms,p95_ms,max_ms\n");
+
+    for row in rows {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{:.3},{:.3},{:.3},{:.3}",
+            row.scene,
+            row.pass,
+            row.stats.count,
+            row.stats.min,
+            row.stats.avg,
+            row.stats.p95,
+            row.stats.max
+        );
+    }
+
+    csv
+}
+
+fn to_json(rows: &[Row]) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "  {{\"scene\": \"{}\", \"pass\": \"{}\", \"frames\": {}, \"min_ms\": {:.3}, \"avg_ms\": {:.3}, \"p95_ms\": {:.3}, \"max_ms\": {:.3}}}",
+                row.scene,
+                row.pass,
+                row.stats.count,
+                row.stats.min,
+                row.stats.avg,
+                row.stats.p95,
+                row.stats.max
+            )
+        })
+        .collect();
+
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+fn pass_index(pass: Pass) -> usize {
+    PASSES.iter().position(|&p| p == pass).unwrap()
+}
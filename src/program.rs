@@ -1,18 +1,15 @@
 use core::mem::transmute;
 use std::ffi::CString;
-use std::ptr;
-use std::str;
 
 use gl::types::*;
 
-use crate::demo::LEIPAE_COUNT;
-use crate::shader::Shader;
+use crate::shader::{Shader, ShaderError};
 
 #[derive(Debug)]
 pub struct ShaderProgram(GLuint);
 
 impl ShaderProgram {
-    pub fn new(vs: Shader, fs: Shader) -> Self {
+    pub fn new(vs: Shader, fs: Shader) -> Result<Self, ShaderError> {
         let program = unsafe { gl::CreateProgram() };
 
         unsafe {
@@ -25,22 +22,14 @@ impl ShaderProgram {
             gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
 
             if success != gl::TRUE as GLint {
-                let mut log_buffer = Vec::with_capacity(512);
-                gl::GetProgramInfoLog(
-                    program,
-                    512,
-                    ptr::null_mut(),
-                    log_buffer.as_mut_ptr() as *mut GLchar,
-                );
-
-                panic!(
-                    "{}",
-                    str::from_utf8(&log_buffer).expect("ShaderInfoLog not valid utf8")
-                );
+                let error = read_program_error(program);
+                gl::DeleteProgram(program);
+
+                return Err(ShaderError(error));
             }
         }
 
-        Self(program)
+        Ok(Self(program))
     }
 
     pub fn id(&self) -> GLuint {
@@ -51,6 +40,14 @@ impl ShaderProgram {
         gl::UseProgram(self.id());
     }
 
+    pub unsafe fn set_uniform_i32(&self, name: &str, value: i32) {
+        let name_c_str = CString::new(name.as_bytes()).unwrap();
+        gl::Uniform1i(
+            gl::GetUniformLocation(self.id(), name_c_str.as_ptr()),
+            value,
+        );
+    }
+
     pub unsafe fn set_uniform_f32(&self, name: &str, value: f32) {
         let name_c_str = CString::new(name.as_bytes()).unwrap();
         gl::Uniform1f(
@@ -74,12 +71,32 @@ impl ShaderProgram {
         );
     }
 
-    pub unsafe fn set_uniform4_f32v(&self, name: &str, v: [[f32; 4]; LEIPAE_COUNT]) {
+    pub unsafe fn set_uniform4_f32(&self, name: &str, a: f32, b: f32, c: f32, d: f32) {
+        let name_c_str = CString::new(name.as_bytes()).unwrap();
+        gl::Uniform4f(
+            gl::GetUniformLocation(self.id(), name_c_str.as_ptr()),
+            a,
+            b,
+            c,
+            d,
+        );
+    }
+
+    pub unsafe fn set_uniform3_f32v(&self, name: &str, v: &[[f32; 3]]) {
+        let name_c_str = CString::new(name.as_bytes()).unwrap();
+        gl::Uniform3fv(
+            gl::GetUniformLocation(self.id(), name_c_str.as_ptr()),
+            v.len() as i32,
+            v.as_ptr() as *const GLfloat,
+        );
+    }
+
+    pub unsafe fn set_uniform4_f32v(&self, name: &str, v: &[[f32; 4]]) {
         let name_c_str = CString::new(name.as_bytes()).unwrap();
         gl::Uniform4fv(
             gl::GetUniformLocation(self.id(), name_c_str.as_ptr()),
-            LEIPAE_COUNT as i32,
-            transmute::<_, *const GLfloat>(v.as_ptr()),
+            v.len() as i32,
+            transmute::<*const [f32; 4], *const GLfloat>(v.as_ptr()),
         );
     }
 }
@@ -89,3 +106,25 @@ impl Drop for ShaderProgram {
         unsafe { gl::DeleteProgram(self.0) }
     }
 }
+
+fn read_program_error(program: GLuint) -> String {
+    let mut info_log_length: GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut info_log_length);
+    }
+
+    let mut read_length: GLint = 0;
+    let mut log_buffer = Vec::with_capacity(info_log_length as usize);
+    unsafe {
+        gl::GetProgramInfoLog(
+            program,
+            info_log_length,
+            &mut read_length,
+            log_buffer.as_mut_ptr() as *mut GLchar,
+        );
+
+        log_buffer.set_len(read_length as usize);
+    }
+
+    String::from_utf8_lossy(&log_buffer).to_string()
+}
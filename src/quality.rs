@@ -0,0 +1,202 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+// Quality presets of the scene shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Preset {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::Ultra,
+            Self::Ultra => Self::Low,
+        }
+    }
+
+    pub fn quality(self) -> Quality {
+        match self {
+            Self::Low => Quality {
+                max_marching_steps: 150,
+                max_dist: 60.0,
+                shadow_steps: 32,
+                shadow_sharpness: 12.0,
+                cloud_octaves: 4,
+                star_octaves: 2,
+            },
+            Self::Medium => Quality {
+                max_marching_steps: 250,
+                max_dist: 80.0,
+                shadow_steps: 96,
+                shadow_sharpness: 16.0,
+                cloud_octaves: 6,
+                star_octaves: 3,
+            },
+            // What the shader defaults to
+            Self::High => Quality {
+                max_marching_steps: 400,
+                max_dist: 100.0,
+                shadow_steps: 256,
+                shadow_sharpness: 24.0,
+                cloud_octaves: 8,
+                star_octaves: 4,
+            },
+            Self::Ultra => Quality {
+                max_marching_steps: 800,
+                max_dist: 150.0,
+                shadow_steps: 512,
+                shadow_sharpness: 32.0,
+                cloud_octaves: 10,
+                star_octaves: 5,
+            },
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Preset::Low),
+            "medium" => Ok(Preset::Medium),
+            "high" => Ok(Preset::High),
+            "ultra" => Ok(Preset::Ultra),
+            _ => Err(format!(
+                "unknown quality \"{}\", expected low, medium, high or ultra",
+                s
+            )),
+        }
+    }
+}
+
+// The values of the defines. Compiled in, the loops keep constant bounds and
+// a weak machine pays for nothing it doesn't use. The terrain keeps its
+// octaves, the baked heightmap and sdf.rs have to match it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    pub max_marching_steps: u32,
+    // Rays and shadows give up this far
+    pub max_dist: f32,
+    pub shadow_steps: u32,
+    // The `k` of the soft shadows, higher is sharper
+    pub shadow_sharpness: f32,
+    pub cloud_octaves: u32,
+    pub star_octaves: u32,
+}
+
+impl Quality {
+    pub fn with_overrides(mut self, overrides: &[Override]) -> Self {
+        for Override(setting, value) in overrides {
+            match setting {
+                Setting::MaxMarchingSteps => self.max_marching_steps = *value as u32,
+                Setting::MaxDist => self.max_dist = *value,
+                Setting::ShadowSteps => self.shadow_steps = *value as u32,
+                Setting::ShadowSharpness => self.shadow_sharpness = *value,
+                Setting::CloudOctaves => self.cloud_octaves = *value as u32,
+                Setting::StarOctaves => self.star_octaves = *value as u32,
+            }
+        }
+
+        self
+    }
+
+    // `NAME value` for `shader::with_defines`
+    pub fn defines(&self) -> Vec<String> {
+        vec![
+            format!("{} {}", Setting::MaxMarchingSteps, self.max_marching_steps),
+            format!("{} {:?}", Setting::MaxDist, self.max_dist),
+            format!("{} {}", Setting::ShadowSteps, self.shadow_steps),
+            format!("{} {:?}", Setting::ShadowSharpness, self.shadow_sharpness),
+            format!("{} {}", Setting::CloudOctaves, self.cloud_octaves),
+            format!("{} {}", Setting::StarOctaves, self.star_octaves),
+        ]
+    }
+}
+
+// A define of fragment.glsl a preset sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    MaxMarchingSteps,
+    MaxDist,
+    ShadowSteps,
+    ShadowSharpness,
+    CloudOctaves,
+    StarOctaves,
+}
+
+impl Setting {
+    const ALL: [Setting; 6] = [
+        Setting::MaxMarchingSteps,
+        Setting::MaxDist,
+        Setting::ShadowSteps,
+        Setting::ShadowSharpness,
+        Setting::CloudOctaves,
+        Setting::StarOctaves,
+    ];
+
+    // The counts are loop bounds and have to be whole
+    fn is_integer(self) -> bool {
+        !matches!(self, Setting::MaxDist | Setting::ShadowSharpness)
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Setting::MaxMarchingSteps => "MAX_MARCHING_STEPS",
+            Setting::MaxDist => "MAX_DIST",
+            Setting::ShadowSteps => "SHADOW_STEPS",
+            Setting::ShadowSharpness => "SHADOW_SHARPNESS",
+            Setting::CloudOctaves => "CLOUD_OCTAVES",
+            Setting::StarOctaves => "STAR_OCTAVES",
+        };
+
+        f.write_str(name)
+    }
+}
+
+// A `NAME=VALUE` replacing the value of the preset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Override(Setting, f32);
+
+impl FromStr for Override {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got \"{}\"", s))?;
+
+        let setting = Setting::ALL
+            .into_iter()
+            .find(|setting| setting.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<String> = Setting::ALL.iter().map(|s| s.to_string()).collect();
+                format!("unknown define \"{}\", expected {}", name, names.join(", "))
+            })?;
+
+        let value: f32 = value
+            .parse()
+            .map_err(|_| format!("invalid value \"{}\" for {}", value, setting))?;
+        if value <= 0.0 || (setting.is_integer() && value.fract() != 0.0) {
+            return Err(format!(
+                "{} must be a positive {}",
+                setting,
+                if setting.is_integer() {
+                    "integer"
+                } else {
+                    "number"
+                }
+            ));
+        }
+
+        Ok(Override(setting, value))
+    }
+}
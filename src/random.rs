@@ -0,0 +1,55 @@
+use std::ops::Range;
+
+// SplitMix64 in place of the rand crate, enough for placing leipae and
+// filling noise textures
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Seeded differently on every run
+    pub fn from_entropy() -> Self {
+        Self::with_seed(entropy())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, range: Range<f32>) -> f32 {
+        range.start + (range.end - range.start) * self.next_f32()
+    }
+}
+
+// The standard library seeds its hash maps randomly
+#[cfg(not(target_arch = "wasm32"))]
+fn entropy() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    RandomState::new().build_hasher().finish()
+}
+
+// Without a WASI host there is no randomness but the browser's
+#[cfg(target_arch = "wasm32")]
+fn entropy() -> u64 {
+    (js_sys::Math::random() * u64::MAX as f64) as u64
+}
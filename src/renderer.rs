@@ -1,129 +1,555 @@
 use std::error::Error;
+use std::fs;
 use std::mem;
 use std::ptr;
-use std::str;
+use std::str::{self, FromStr};
 
 use gl::types::*;
 
-use crate::demo::Demo;
+use crate::clock::{Clock, RealtimeClock};
+use crate::culling::LeipaeBuffer;
+use crate::demo::{ClickMode, Demo, SCENE_ORDER};
+use crate::framebuffer::Framebuffer;
+use crate::gbuffer::GBuffer;
+use crate::hud::Hud;
+use crate::material::MaterialBuffer;
+use crate::options::Options;
+use crate::pack::Asset;
+use crate::postprocess::PostProcessor;
+use crate::profiler::{GpuProfiler, Pass, ShaderSection};
 use crate::program::ShaderProgram;
-use crate::shader::Shader;
+use crate::quality::{Override, Preset, Quality};
+use crate::scaling::{DynamicResolution, UpscaleFilter};
+use crate::shader::{self, Shader};
+use crate::temporal::{self, TemporalFilter};
+use crate::terrain::Heightmap;
+use crate::texture::{Channels, Texture, CHANNEL_COUNT};
 
 // #[rustfmt::skip]
 static VERTICES: [GLfloat; 12] = [
     -1.0, -1.0, 0.0, 1.0, -1.0, 0.0, -1.0, 1.0, 0.0, 1.0, 1.0, 0.0,
 ];
 
-const VERTEX_SHADER: &str = include_str!("shaders/vertex.glsl");
-const FRAGMENT_SHADER: &str = include_str!("shaders/fragment.min.glsl");
+const VERTEX_SHADER: Asset = crate::asset!("shaders/vertex.glsl");
+const FRAGMENT_SHADER: Asset = crate::asset!("shaders/fragment.min.glsl");
+
+// Texture unit of the heightmap, after the channels
+const HEIGHTMAP_UNIT: u32 = CHANNEL_COUNT as u32;
+// First texture unit of the G-buffer in the lighting pass, after the heightmap
+const GBUFFER_UNIT: u32 = HEIGHTMAP_UNIT + 1;
+
+// Defines choosing which half of the scene shader is compiled
+const RAYMARCH_PASS: &str = "GBUFFER_PASS";
+const LIGHTING_PASS: &str = "LIGHTING_PASS";
+
+// What the scene shader outputs in place of the shaded image, for finding
+// where the raymarcher goes wrong. The values of `iDebugMode`, the DEBUG_
+// constants of fragment.glsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Scene,
+    // Marching steps of the primary ray, black to white
+    Steps,
+    // Brighter is closer
+    Depth,
+    Normals,
+    // Albedo, without the light
+    Material,
+    // The soft shadow from the sun
+    Shadow,
+    // How much the fog covers
+    Fog,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Scene => Self::Steps,
+            Self::Steps => Self::Depth,
+            Self::Depth => Self::Normals,
+            Self::Normals => Self::Material,
+            Self::Material => Self::Shadow,
+            Self::Shadow => Self::Fog,
+            Self::Fog => Self::Scene,
+        }
+    }
+}
+
+impl FromStr for DebugView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scene" => Ok(DebugView::Scene),
+            "steps" => Ok(DebugView::Steps),
+            "depth" => Ok(DebugView::Depth),
+            "normals" => Ok(DebugView::Normals),
+            "material" => Ok(DebugView::Material),
+            "shadow" => Ok(DebugView::Shadow),
+            "fog" => Ok(DebugView::Fog),
+            _ => Err(format!(
+                "unknown view \"{}\", expected scene, steps, depth, normals, material, shadow or fog",
+                s
+            )),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Renderer {
+    // Window size, the scene itself is rendered at `scale` times this
     width: f32,
     height: f32,
 
-    program: ShaderProgram,
+    scale: f32,
+    filter: UpscaleFilter,
+    dynamic_resolution: Option<DynamicResolution>,
+    // Started on the first frame
+    frame_clock: Option<RealtimeClock>,
+    frame: u32,
+    debug_view: DebugView,
+
+    // The scene shader marching the rays into the G-buffer, and shading it
+    raymarch_program: ShaderProgram,
+    lighting_program: ShaderProgram,
+    // Sources of the scene shader, read from the files on reload
+    vertex_source: String,
+    fragment_source: String,
+    // Defines of the sections compiled out of the scene shader
+    defines: Vec<&'static str>,
+    quality: Preset,
+    // Values set on the command line, kept when the preset changes
+    quality_overrides: Vec<Override>,
+    // Textures of the scene shader
+    channels: Channels,
+    materials: MaterialBuffer,
+    leipae: LeipaeBuffer,
+    // Baked terrain, kept on the CPU for the picking, none when compiled out
+    heightmap: Option<(Texture, Heightmap)>,
+    gbuffer: GBuffer,
+    temporal: TemporalFilter,
+    post: PostProcessor,
+    hud: Hud,
+    profiler: GpuProfiler,
 
     vao: GLuint,
     vbo: GLuint,
 }
 
 impl Renderer {
-    pub fn new(width: f32, height: f32) -> Result<Self, Box<dyn Error>> {
-        let vs = Shader::new(VERTEX_SHADER, gl::VERTEX_SHADER)?;
-        let fs = Shader::new(FRAGMENT_SHADER, gl::FRAGMENT_SHADER)?;
-
-        let program = ShaderProgram::new(vs, fs);
+    pub fn new(width: f32, height: f32, options: &Options) -> Result<Self, Box<dyn Error>> {
+        let defines: Vec<&'static str> = options
+            .disabled_sections
+            .iter()
+            .map(|section| section.define())
+            .collect();
 
-        let mut vbo: GLuint = 0;
-        let mut vao: GLuint = 0;
+        let vertex_source = VERTEX_SHADER.text().into_owned();
+        let fragment_source = FRAGMENT_SHADER.text().into_owned();
+        let quality = options.quality();
+        let raymarch_program = scene_program(
+            &vertex_source,
+            &fragment_source,
+            RAYMARCH_PASS,
+            &defines,
+            quality,
+        )?;
+        let lighting_program = scene_program(
+            &vertex_source,
+            &fragment_source,
+            LIGHTING_PASS,
+            &defines,
+            quality,
+        )?;
 
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::GenBuffers(1, &mut vbo);
+        let (internal_width, internal_height) = internal_size(width, height, options.scale);
+        let gbuffer = unsafe { GBuffer::new(internal_width, internal_height)? };
+        let temporal = unsafe {
+            TemporalFilter::new(
+                internal_width,
+                internal_height,
+                options.antialiasing,
+                options.motion_blur,
+            )?
+        };
+        let channels = unsafe { Channels::new(options.channels.clone(), SCENE_ORDER)? };
+        let materials = unsafe { MaterialBuffer::new() };
+        let leipae = unsafe { LeipaeBuffer::new() };
+        let heightmap = if options
+            .disabled_sections
+            .contains(&ShaderSection::Heightmap)
+        {
+            None
+        } else {
+            let heightmap = Heightmap::bake();
+            let resolution = heightmap.resolution();
+            let texture = unsafe { Texture::from_f32(resolution, resolution, heightmap.heights()) };
 
-            // Bind Vertex Array Object first
-            gl::BindVertexArray(vao);
+            Some((texture, heightmap))
+        };
+        let post = unsafe { PostProcessor::new(internal_width, internal_height)? };
+        let hud = unsafe { Hud::new()? };
+        let profiler = unsafe { GpuProfiler::new(options.profile.clone()) };
 
-            // Bind Vertex Buffer Object and copy the vertices to it
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (VERTICES.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-                &VERTICES[0] as *const f32 as *const std::ffi::c_void,
-                gl::STATIC_DRAW,
-            );
+        let (vao, vbo) = unsafe { fullscreen_quad() };
 
-            program.activate();
-            program.set_uniform2_f32("iResolution", width, height);
-
-            // Define vertex data layout, only position
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT as GLenum,
-                gl::FALSE as GLboolean,
-                3 * mem::size_of::<f32>() as GLsizei,
-                ptr::null(),
-            );
-            gl::EnableVertexAttribArray(0);
-
-            // Unbind the VBO and VAO
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
+        unsafe {
+            for program in [&raymarch_program, &lighting_program] {
+                materials.attach(program);
+                leipae.attach(program);
+                program.activate();
+                program.set_uniform2_f32(
+                    "iResolution",
+                    internal_width as f32,
+                    internal_height as f32,
+                );
+            }
         }
 
         Ok(Self {
             width,
             height,
+            scale: options.scale,
+            filter: options.filter,
+            dynamic_resolution: options.target_fps.map(DynamicResolution::new),
+            frame_clock: None,
+            frame: 0,
+            debug_view: options.debug_view,
             vao,
             vbo,
-            program,
+            raymarch_program,
+            lighting_program,
+            vertex_source,
+            fragment_source,
+            defines,
+            quality: options.quality,
+            quality_overrides: options.quality_overrides.clone(),
+            channels,
+            materials,
+            leipae,
+            heightmap,
+            gbuffer,
+            temporal,
+            post,
+            hud,
+            profiler,
         })
     }
 
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Window size
+    pub fn resolution(&self) -> (i32, i32) {
+        (self.width as i32, self.height as i32)
+    }
+
+    // Size the scene is rendered at
+    pub fn internal_resolution(&self) -> (i32, i32) {
+        let target = self.post.scene_target();
+
+        (target.width(), target.height())
+    }
+
+    pub fn toggle_hud(&mut self) {
+        self.hud.toggle();
+    }
+
+    pub fn set_click_mode(&mut self, mode: ClickMode) {
+        self.hud.set_click_mode(mode);
+    }
+
+    pub fn next_debug_view(&mut self) {
+        self.debug_view = self.debug_view.next();
+    }
+
+    // The settings the scene shader is compiled with
+    pub fn quality(&self) -> Quality {
+        self.quality
+            .quality()
+            .with_overrides(&self.quality_overrides)
+    }
+
+    // The terrain the raymarcher reads beyond the detail distance
+    pub fn heightmap(&self) -> Option<&Heightmap> {
+        self.heightmap.as_ref().map(|(_, heightmap)| heightmap)
+    }
+
+    // Recompiles the scene shader with the next quality preset, keeping the
+    // previous preset and program if it fails to compile
+    pub unsafe fn next_quality(&mut self) -> Result<(), Box<dyn Error>> {
+        let previous = self.quality;
+        self.quality = self.quality.next();
+
+        if let Err(err) = self.compile() {
+            self.quality = previous;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     // Reloads the current unminified shaders, for development purposes
     pub unsafe fn reload(&mut self) -> Result<(), Box<dyn Error>> {
-        let vs = Shader::from_file("src/shaders/vertex.glsl", gl::VERTEX_SHADER)?;
-        let fs = Shader::from_file("src/shaders/fragment.glsl", gl::FRAGMENT_SHADER)?;
+        self.vertex_source = fs::read_to_string("src/shaders/vertex.glsl")?;
+        self.fragment_source = fs::read_to_string("src/shaders/fragment.glsl")?;
 
-        self.program = ShaderProgram::new(vs, fs);
-        unsafe {
-            self.program.activate();
-            self.program
-                .set_uniform2_f32("iResolution", self.width, self.height);
+        self.compile()?;
+        self.temporal.reload()?;
+        self.post.reload()?;
+
+        Ok(())
+    }
+
+    unsafe fn compile(&mut self) -> Result<(), Box<dyn Error>> {
+        let quality = self.quality();
+
+        // Both passes compile before either replaces the current program
+        let raymarch_program = scene_program(
+            &self.vertex_source,
+            &self.fragment_source,
+            RAYMARCH_PASS,
+            &self.defines,
+            quality,
+        )?;
+        let lighting_program = scene_program(
+            &self.vertex_source,
+            &self.fragment_source,
+            LIGHTING_PASS,
+            &self.defines,
+            quality,
+        )?;
+        self.raymarch_program = raymarch_program;
+        self.lighting_program = lighting_program;
+
+        for program in [&self.raymarch_program, &self.lighting_program] {
+            self.materials.attach(program);
+            self.leipae.attach(program);
         }
+        self.update_resolution();
 
         Ok(())
     }
 
-    pub unsafe fn resize(&mut self, width: u32, height: u32) {
+    pub unsafe fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
         self.width = width as f32;
         self.height = height as f32;
 
-        self.program.activate();
-        self.program
-            .set_uniform2_f32("iResolution", self.width, self.height);
+        self.resize_framebuffer()
     }
 
-    pub unsafe fn draw(&mut self, demo: &Demo) {
+    pub unsafe fn set_scale(&mut self, scale: f32) -> Result<(), Box<dyn Error>> {
+        self.scale = scale;
+
+        self.resize_framebuffer()
+    }
+
+    pub unsafe fn draw(&mut self, demo: &Demo) -> Result<(), Box<dyn Error>> {
+        match self.frame_clock.as_mut() {
+            Some(clock) => {
+                let frame_time = clock.tick().as_secs_f32();
+
+                self.hud.record_frame_time(frame_time);
+                self.update_dynamic_resolution(frame_time)?;
+            }
+            None => self.frame_clock = Some(RealtimeClock::new()),
+        }
+
+        // Without temporal antialiasing the jitter would only make the image shake
+        let jitter = if self.temporal.antialiasing() && self.debug_view == DebugView::Scene {
+            temporal::jitter(self.frame)
+        } else {
+            [0.0, 0.0]
+        };
+
+        let (width, height) = (self.width as i32, self.height as i32);
+        let filter = self.filter.gl_enum();
+
+        self.render(demo, jitter)?;
+
+        self.profiler.begin(Pass::Present);
+        self.post.output().blit_to_screen(width, height, filter);
+        self.hud.draw(
+            demo,
+            self.debug_view,
+            self.quality,
+            self.scale,
+            width,
+            height,
+        );
+        self.profiler.end();
+
+        Ok(())
+    }
+
+    // Fills the window with black
+    pub unsafe fn clear(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::Viewport(0, 0, self.width as i32, self.height as i32);
         gl::ClearColor(0.0, 0.0, 0.0, 1.0);
         gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
 
-        let camera = demo.camera();
-        let target = demo.target();
+    // Renders a frame at the internal resolution with the scene sampled at the
+    // given subpixel offset, returns the framebuffer holding the final image
+    pub unsafe fn render(
+        &mut self,
+        demo: &Demo,
+        jitter: [f32; 2],
+    ) -> Result<&Framebuffer, Box<dyn Error>> {
+        self.render_scene(demo, jitter)?;
 
-        self.program.activate();
-        self.program.set_uniform_f32("iTime", demo.day_time());
-        self.program
-            .set_uniform3_f32("iCamera", camera[0], camera[1], camera[2]);
-        self.program
-            .set_uniform3_f32("iTarget", target[0], target[1], target[2]);
-        self.program.set_uniform4_f32v("iLeipae", demo.leipae());
+        // The debug views are shown as they are, without blending with the
+        // previous frames, grading or fades
+        self.profiler.begin(Pass::Temporal);
+        match self.debug_view {
+            DebugView::Scene => self.temporal.apply(demo, self.post.scene_target()),
+            _ => self.temporal.reset(),
+        }
+        self.profiler.end();
+
+        Ok(self.post_process(demo))
+    }
+
+    // Ray marches and lights the scene sampled at the given subpixel offset,
+    // returns the scene target holding it before the temporal filter and the
+    // effects
+    pub unsafe fn render_scene(
+        &mut self,
+        demo: &Demo,
+        jitter: [f32; 2],
+    ) -> Result<&Framebuffer, Box<dyn Error>> {
+        self.frame = self.frame.wrapping_add(1);
+        self.profiler.begin_frame(demo.scene());
+
+        self.set_scene_uniforms(demo, jitter)?;
 
         gl::BindVertexArray(self.vao);
+
+        self.profiler.begin(Pass::Scene);
+        self.gbuffer.bind();
+        self.raymarch_program.activate();
         gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        self.profiler.end();
+
+        self.profiler.begin(Pass::Lighting);
+        self.post.scene_target().bind();
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+
+        self.lighting_program.activate();
+        self.gbuffer
+            .bind_textures(&self.lighting_program, GBUFFER_UNIT);
+        gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        self.profiler.end();
+
         gl::BindVertexArray(0);
+
+        Ok(self.post.scene_target())
+    }
+
+    // Where `render_scene` draws and the effects start from
+    pub fn scene_target(&self) -> &Framebuffer {
+        self.post.scene_target()
+    }
+
+    // Runs the effects of the scene over the scene target, returns the
+    // framebuffer holding the final image
+    pub unsafe fn post_process(&mut self, demo: &Demo) -> &Framebuffer {
+        gl::BindVertexArray(self.vao);
+
+        self.profiler.begin(Pass::Post);
+        let effects = match self.debug_view {
+            DebugView::Scene => demo.effects(),
+            _ => &[],
+        };
+        let lens = demo.lens(self.width / self.height, self.quality().max_dist);
+        let output = self
+            .post
+            .apply(effects, demo.day_time(), &self.gbuffer, &lens);
+        self.profiler.end();
+
+        gl::BindVertexArray(0);
+
+        output
+    }
+
+    // The uniforms of both passes of the scene shader, the same in each
+    unsafe fn set_scene_uniforms(
+        &mut self,
+        demo: &Demo,
+        jitter: [f32; 2],
+    ) -> Result<(), Box<dyn Error>> {
+        let camera = demo.camera();
+        let target = demo.target();
+        let leipae = demo.visible_leipae(self.width / self.height, self.quality().max_dist);
+        self.hud.set_drawn_leipae(leipae.len());
+        let ripples = demo.ripples();
+
+        for program in [&self.raymarch_program, &self.lighting_program] {
+            program.activate();
+            program.set_uniform_f32("iTime", demo.day_time());
+            program.set_uniform3_f32("iCamera", camera[0], camera[1], camera[2]);
+            program.set_uniform3_f32("iTarget", target[0], target[1], target[2]);
+            self.leipae.bind(program, &leipae);
+            program.set_uniform4_f32v("iRipples", &ripples);
+            program.set_uniform2_f32("iJitter", jitter[0], jitter[1]);
+            program.set_uniform_i32("iDebugMode", self.debug_view as i32);
+            self.channels.bind(demo.scene(), program)?;
+
+            if let Some((texture, heightmap)) = &self.heightmap {
+                let bounds = heightmap.bounds();
+
+                gl::ActiveTexture(gl::TEXTURE0 + HEIGHTMAP_UNIT);
+                gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                gl::ActiveTexture(gl::TEXTURE0);
+
+                program.set_uniform_i32("iHeightmap", HEIGHTMAP_UNIT as i32);
+                program.set_uniform3_f32("iHeightmapBounds", bounds[0], bounds[1], bounds[2]);
+            }
+        }
+
+        self.materials.bind(&demo.materials());
+
+        Ok(())
+    }
+
+    // Writes the GPU profile if profiling was enabled
+    pub unsafe fn finish_profile(&mut self) -> Result<(), Box<dyn Error>> {
+        self.profiler.finish()
+    }
+
+    unsafe fn update_dynamic_resolution(&mut self, frame_time: f32) -> Result<(), Box<dyn Error>> {
+        if let Some(dynamic_resolution) = self.dynamic_resolution.as_mut() {
+            let scale = dynamic_resolution.update(self.scale, frame_time);
+
+            if scale != self.scale {
+                self.set_scale(scale)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn resize_framebuffer(&mut self) -> Result<(), Box<dyn Error>> {
+        let (width, height) = internal_size(self.width, self.height, self.scale);
+        self.gbuffer.resize(width, height)?;
+        self.temporal.resize(width, height)?;
+        self.post.resize(width, height)?;
+        self.update_resolution();
+
+        Ok(())
+    }
+
+    // The shader sees the internal resolution, not the window size
+    unsafe fn update_resolution(&self) {
+        let target = self.post.scene_target();
+
+        for program in [&self.raymarch_program, &self.lighting_program] {
+            program.activate();
+            program.set_uniform2_f32("iResolution", target.width() as f32, target.height() as f32);
+        }
     }
 }
 
@@ -135,3 +561,71 @@ impl Drop for Renderer {
         }
     }
 }
+
+// One pass of the scene shader with the sections compiled out and the quality
+// settings
+fn scene_program(
+    vertex_source: &str,
+    fragment_source: &str,
+    pass: &str,
+    sections: &[&str],
+    quality: Quality,
+) -> Result<ShaderProgram, Box<dyn Error>> {
+    let mut defines = vec![pass.to_string()];
+    defines.extend(sections.iter().map(|section| section.to_string()));
+    defines.extend(quality.defines());
+
+    let vs = Shader::new(vertex_source, gl::VERTEX_SHADER)?;
+    let fs = Shader::new(
+        &shader::with_defines(fragment_source, &defines),
+        gl::FRAGMENT_SHADER,
+    )?;
+
+    Ok(ShaderProgram::new(vs, fs)?)
+}
+
+fn internal_size(width: f32, height: f32, scale: f32) -> (i32, i32) {
+    (
+        ((width * scale).round() as i32).max(1),
+        ((height * scale).round() as i32).max(1),
+    )
+}
+
+// Vertex array and buffer of a quad covering the whole viewport, drawn as a
+// triangle strip of 4 vertices
+pub unsafe fn fullscreen_quad() -> (GLuint, GLuint) {
+    let mut vbo: GLuint = 0;
+    let mut vao: GLuint = 0;
+
+    gl::GenVertexArrays(1, &mut vao);
+    gl::GenBuffers(1, &mut vbo);
+
+    // Bind Vertex Array Object first
+    gl::BindVertexArray(vao);
+
+    // Bind Vertex Buffer Object and copy the vertices to it
+    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (VERTICES.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+        &VERTICES[0] as *const f32 as *const std::ffi::c_void,
+        gl::STATIC_DRAW,
+    );
+
+    // Define vertex data layout, only position
+    gl::VertexAttribPointer(
+        0,
+        3,
+        gl::FLOAT as GLenum,
+        gl::FALSE as GLboolean,
+        3 * mem::size_of::<f32>() as GLsizei,
+        ptr::null(),
+    );
+    gl::EnableVertexAttribArray(0);
+
+    // Unbind the VBO and VAO
+    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    gl::BindVertexArray(0);
+
+    (vao, vbo)
+}
@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use gl::types::*;
+
+pub const MIN_SCALE: f32 = 0.25;
+pub const MAX_SCALE: f32 = 2.0;
+
+// Dynamic scaling never supersamples, that is left for explicit --scale values
+const MAX_DYNAMIC_SCALE: f32 = 1.0;
+const SCALE_STEP: f32 = 0.05;
+// Frames to wait after a scale change before measuring again
+const COOLDOWN_FRAMES: u32 = 30;
+const SMOOTHING: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    Nearest,
+    Linear,
+}
+
+impl UpscaleFilter {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            UpscaleFilter::Nearest => gl::NEAREST,
+            UpscaleFilter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+impl FromStr for UpscaleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(UpscaleFilter::Nearest),
+            "linear" => Ok(UpscaleFilter::Linear),
+            _ => Err(format!(
+                "unknown filter \"{}\", expected nearest or linear",
+                s
+            )),
+        }
+    }
+}
+
+// Adjusts the internal render scale from measured frame times to hold a target fps
+#[derive(Debug)]
+pub struct DynamicResolution {
+    target_frame_time: f32,
+    average_frame_time: f32,
+    frames_since_change: u32,
+}
+
+impl DynamicResolution {
+    pub fn new(target_fps: f32) -> Self {
+        let target_frame_time = 1.0 / target_fps;
+
+        Self {
+            target_frame_time,
+            average_frame_time: target_frame_time,
+            frames_since_change: 0,
+        }
+    }
+
+    // Feeds the latest frame time in seconds, returns the scale to use for the next frame
+    pub fn update(&mut self, scale: f32, frame_time: f32) -> f32 {
+        self.average_frame_time += (frame_time - self.average_frame_time) * SMOOTHING;
+        self.frames_since_change += 1;
+
+        if self.frames_since_change < COOLDOWN_FRAMES {
+            return scale;
+        }
+
+        let ratio = self.target_frame_time / self.average_frame_time;
+        if (0.95..1.1).contains(&ratio) {
+            return scale;
+        }
+
+        // Fragment cost grows with the pixel count, so with the square of the scale.
+        // Always move at least one step so that small errors don't get stuck on rounding.
+        let steps = ((scale * ratio.sqrt() - scale) / SCALE_STEP).round();
+        let steps = if ratio > 1.0 {
+            steps.max(1.0)
+        } else {
+            steps.min(-1.0)
+        };
+        let scale = (scale + steps * SCALE_STEP).clamp(MIN_SCALE, MAX_DYNAMIC_SCALE);
+
+        self.frames_since_change = 0;
+        self.average_frame_time = self.target_frame_time;
+
+        scale
+    }
+}
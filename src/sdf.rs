@@ -0,0 +1,547 @@
+use crate::culling::LEIPAE_RADIUS;
+use crate::demo::Demo;
+use crate::material::MaterialId;
+use crate::noise::{self, fract, mix, smoothstep};
+use crate::quality::{Preset, Quality};
+use crate::terrain::{self, Heightmap, DETAIL_BLEND, DETAIL_DISTANCE};
+
+pub type Vec3 = [f32; 3];
+// Columns, like GLSL's mat3
+pub type Mat3 = [Vec3; 3];
+
+pub const MIN_DIST: f32 = 0.0;
+pub const FOV: f32 = 60.0;
+pub const EPSILON: f32 = 0.00001;
+pub const WATER_LEVEL: f32 = 0.3;
+
+const TOTAL_DURATION: f32 = 100.0;
+
+// Distance to the closest surface and its material, the vec4 the shader's
+// SDF functions return
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distance {
+    pub material: MaterialId,
+    // Added to the albedo of the material
+    pub detail: f32,
+    pub dist: f32,
+}
+
+impl Distance {
+    fn new(material: MaterialId, detail: f32, dist: f32) -> Self {
+        Self {
+            material,
+            detail,
+            dist,
+        }
+    }
+}
+
+// Where a ray stopped, the shader's `rayMarch` result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub material: MaterialId,
+    pub detail: f32,
+    pub depth: f32,
+}
+
+// The object a point is closest to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    Terrain,
+    Water,
+    // Index in `Demo::leipae`
+    Leipae(usize),
+}
+
+// The scene of fragment.glsl at one moment on the CPU, for picking. The
+// functions follow the shader operation for operation, `--verify-sdf`
+// compares them against the GPU.
+#[derive(Debug, Clone)]
+pub struct SceneSdf<'a> {
+    time: f32,
+    leipae: Vec<[f32; 4]>,
+    // The defines of the quality the shader is compiled with
+    max_marching_steps: u32,
+    max_dist: f32,
+    // The renderer's heightmap and the camera it's read around, none for
+    // the analytic terrain everywhere like NO_HEIGHTMAP
+    heightmap: Option<(&'a Heightmap, Vec3)>,
+}
+
+impl<'a> SceneSdf<'a> {
+    // With the quality the shader defaults to
+    pub fn new(time: f32, leipae: Vec<[f32; 4]>) -> Self {
+        let quality = Preset::High.quality();
+
+        Self {
+            time,
+            leipae,
+            max_marching_steps: quality.max_marching_steps,
+            max_dist: quality.max_dist,
+            heightmap: None,
+        }
+    }
+
+    pub fn with_quality(mut self, quality: Quality) -> Self {
+        self.max_marching_steps = quality.max_marching_steps;
+        self.max_dist = quality.max_dist;
+        self
+    }
+
+    pub fn with_heightmap(mut self, heightmap: &'a Heightmap, camera: Vec3) -> Self {
+        self.heightmap = Some((heightmap, camera));
+        self
+    }
+
+    pub fn from_demo(demo: &Demo) -> Self {
+        Self::new(demo.day_time(), demo.leipae())
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn leipae(&self) -> &[[f32; 4]] {
+        &self.leipae
+    }
+
+    // Where the rays give up, `MAX_DIST`
+    pub fn max_dist(&self) -> f32 {
+        self.max_dist
+    }
+
+    pub fn progress(&self) -> f32 {
+        (TOTAL_DURATION - self.time) / TOTAL_DURATION
+    }
+
+    // Direction towards the sun, `sun` in the shader's main
+    pub fn sun(&self) -> Vec3 {
+        let angle = self.progress() - 0.2;
+
+        normalize([-97.0 + 100.0 * angle.cos(), 100.0 * angle.sin(), -100.0])
+    }
+
+    // `sdScene`
+    pub fn distance(&self, p: Vec3) -> Distance {
+        self.closest(p).0
+    }
+
+    // Which object `distance` takes the distance to
+    pub fn surface_at(&self, p: Vec3) -> Surface {
+        self.closest(p).1
+    }
+
+    // The unions of `sdScene`, keeping track of the object as well
+    fn closest(&self, p: Vec3) -> (Distance, Surface) {
+        let terrain = (self.terrain(p), Surface::Terrain);
+        let water = (sd_water(p, WATER_LEVEL), Surface::Water);
+
+        let mut leipae = (
+            Distance::new(MaterialId::Crust, 0.0, self.max_dist),
+            Surface::Leipae(0),
+        );
+
+        for (i, offset) in self.leipae.iter().enumerate() {
+            // Never closer than its bounding sphere, which is cheaper to
+            // check than rotating into the leipae
+            let center = [offset[0], offset[1], offset[2]];
+            if length(sub(p, center)) - LEIPAE_RADIUS / offset[3] >= leipae.0.dist {
+                continue;
+            }
+
+            let rotation = mul_mat(
+                rotate_z(self.time - offset[2]),
+                rotate_x(self.time - offset[0]),
+            );
+            let local = scale(mul(rotation, sub(p, center)), offset[3]);
+
+            let mut dist = sd_leipae_round(local);
+            dist.dist /= offset[3];
+            leipae = union(leipae, (dist, Surface::Leipae(i)));
+
+            if leipae.0.dist < EPSILON {
+                break;
+            }
+        }
+
+        union(union(terrain, water), leipae)
+    }
+
+    // `sdTerrain`
+    fn terrain(&self, p: Vec3) -> Distance {
+        if p[1] - WATER_LEVEL < 0.0 {
+            return Distance::new(MaterialId::Terrain, 0.0, self.max_dist);
+        }
+
+        if let Some((heightmap, camera)) = self.heightmap {
+            let baked = smoothstep(
+                DETAIL_DISTANCE,
+                DETAIL_DISTANCE + DETAIL_BLEND,
+                length([p[0] - camera[0], 0.0, p[2] - camera[2]]),
+            );
+
+            // Outside of the baked area the terrain is computed like up close
+            if let Some(height) = heightmap.height(p[0], p[2]).filter(|_| baked > 0.0) {
+                let height = if baked < 1.0 {
+                    mix(terrain::height(p[0], p[2]), height, baked)
+                } else {
+                    height
+                };
+
+                return Distance::new(MaterialId::Terrain, 0.0, p[1] - height);
+            }
+        }
+
+        Distance::new(MaterialId::Terrain, 0.0, p[1] - terrain::height(p[0], p[2]))
+    }
+
+    // `estimateNormal`
+    pub fn normal(&self, p: Vec3) -> Vec3 {
+        let d =
+            |offset: Vec3| self.distance(add(p, offset)).dist - self.distance(sub(p, offset)).dist;
+
+        normalize([
+            d([EPSILON, 0.0, 0.0]),
+            d([0.0, EPSILON, 0.0]),
+            d([0.0, 0.0, EPSILON]),
+        ])
+    }
+
+    // `rayMarch`, none when the ray reaches `end` without hitting anything
+    pub fn ray_march(&self, camera: Vec3, dir: Vec3, start: f32, end: f32) -> Option<Hit> {
+        // Check if we would hit the bounding plane before reaching "end"
+        let mut end = end;
+        let steps_to_bounding_plane = (100.0 - camera[1]) / dir[1];
+        if steps_to_bounding_plane > 0.0 {
+            end = end.min(steps_to_bounding_plane);
+        }
+
+        let mut depth = start;
+        let mut material = (MaterialId::Terrain, 0.0);
+
+        for _ in 0..self.max_marching_steps {
+            let step_dist = 0.001 * depth;
+
+            let res = self.distance(add(camera, scale(dir, depth)));
+
+            if res.dist < step_dist {
+                material = (res.material, res.detail);
+                break;
+            }
+
+            depth += res.dist * 0.5;
+
+            if depth >= end {
+                break;
+            }
+        }
+
+        if depth >= end {
+            return None;
+        }
+
+        Some(Hit {
+            material: material.0,
+            detail: material.1,
+            depth,
+        })
+    }
+}
+
+// World space direction through a pixel, `rayDirection` turned with `lookAt`
+// like in the shader's main. `frag_coord` is in pixels from the bottom left.
+pub fn camera_ray(camera: Vec3, target: Vec3, resolution: [f32; 2], frag_coord: [f32; 2]) -> Vec3 {
+    let view = ray_direction(FOV, resolution, frag_coord);
+
+    let f = normalize(sub(target, camera));
+    let s = normalize(cross([0.0, 1.0, 0.0], f));
+    let u = cross(f, s);
+
+    mul([s, u, scale(f, -1.0)], view)
+}
+
+pub fn ray_direction(fov: f32, dimensions: [f32; 2], frag_coord: [f32; 2]) -> Vec3 {
+    let xy = [
+        frag_coord[0] - dimensions[0] / 2.0,
+        frag_coord[1] - dimensions[1] / 2.0,
+    ];
+    let z = dimensions[1] / (fov.to_radians() / 2.0).tan();
+
+    normalize([xy[0], xy[1], -z])
+}
+
+// Rotations
+pub fn rotate_x(theta: f32) -> Mat3 {
+    let (s, c) = theta.sin_cos();
+
+    [[1.0, 0.0, 0.0], [0.0, c, -s], [0.0, s, c]]
+}
+
+pub fn rotate_y(theta: f32) -> Mat3 {
+    let (s, c) = theta.sin_cos();
+
+    [[c, 0.0, s], [0.0, 1.0, 0.0], [-s, 0.0, c]]
+}
+
+pub fn rotate_z(theta: f32) -> Mat3 {
+    let (s, c) = theta.sin_cos();
+
+    [[c, -s, 0.0], [s, c, 0.0], [0.0, 0.0, 1.0]]
+}
+
+// SDF primitives
+pub fn sd_sphere(p: Vec3, s: f32) -> f32 {
+    length(p) - s
+}
+
+pub fn sd_ellipsoid(p: Vec3, r: Vec3) -> f32 {
+    let k0 = length([p[0] / r[0], p[1] / r[1], p[2] / r[2]]);
+    let k1 = length([
+        p[0] / (r[0] * r[0]),
+        p[1] / (r[1] * r[1]),
+        p[2] / (r[2] * r[2]),
+    ]);
+
+    k0 * (k0 - 1.0) / k1
+}
+
+pub fn sd_tri_prism(p: Vec3, h: [f32; 2]) -> f32 {
+    let q = [p[0].abs(), p[1].abs(), p[2].abs()];
+
+    (q[2] - h[1]).max((q[0] * 0.866025 + p[1] * 0.5).max(-p[1]) - h[0] * 0.5)
+}
+
+pub fn op_union(a: Distance, b: Distance) -> Distance {
+    if a.dist < b.dist {
+        return a;
+    }
+
+    b
+}
+
+pub fn op_bend(p: Vec3, k: f32) -> Vec3 {
+    let c = (k * p[0]).cos();
+    let s = (k * p[0]).sin();
+
+    [c * p[0] + s * p[1], -s * p[0] + c * p[1], p[2]]
+}
+
+// Scene objects
+pub fn sd_water(p: Vec3, y: f32) -> Distance {
+    let mut material = MaterialId::Water;
+
+    if fract(p[0]) < 0.03 {
+        material = MaterialId::NeonPink;
+    } else if fract(p[2]) <= 0.03 {
+        material = MaterialId::NeonCyan;
+    }
+
+    Distance::new(material, 0.0, p[1] - y)
+}
+
+pub fn sd_leipae_round(p: Vec3) -> Distance {
+    if sd_sphere(p, 5.0) > 0.0 {
+        // Get a better estimate and use that
+        return Distance::new(MaterialId::Crust, 0.0, sd_sphere(p, 2.5));
+    }
+
+    let xz = |k: f32| [p[0] * k, p[2] * k];
+
+    let noise5 = noise::noise(xz(5.0));
+    let noise30 = noise::noise(xz(30.0));
+    let noise50 = noise::noise(xz(50.0));
+
+    let ellipsoid_dist = sd_ellipsoid(op_bend(p, -0.08), [2.2, 0.8, 2.1]) - 0.25
+        + (0.05 * noise5)
+        + (0.01 * noise30)
+        + (0.005 * noise50);
+
+    let mut dist = ellipsoid_dist;
+    for (z, y) in [(0.9, 0.3), (1.1, -0.8)] {
+        let rotation = mul_mat(rotate_z(z), rotate_y(y));
+
+        for i in -1..=1 {
+            let offset_y = 1.55;
+
+            let wedge = sd_tri_prism(
+                mul(rotation, [p[0] + i as f32 * 1.1, p[1] - offset_y, p[2]]),
+                [1.0, 6.0],
+            ) + (0.03 * noise::noise(xz(10.0)));
+
+            dist = dist.max(-wedge);
+        }
+    }
+
+    Distance::new(MaterialId::Crust, noise5 * 0.5 + noise30 * 0.5, dist)
+}
+
+// `op_union` carrying along what the distance is to
+fn union(a: (Distance, Surface), b: (Distance, Surface)) -> (Distance, Surface) {
+    if a.0.dist < b.0.dist {
+        return a;
+    }
+
+    b
+}
+
+// Vector math the shader gets from GLSL
+pub fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(v: Vec3, s: f32) -> Vec3 {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length(v: Vec3) -> f32 {
+    dot(v, v).sqrt()
+}
+
+pub fn normalize(v: Vec3) -> Vec3 {
+    scale(v, 1.0 / length(v))
+}
+
+// Matrix times column vector
+pub fn mul(m: Mat3, v: Vec3) -> Vec3 {
+    add(add(scale(m[0], v[0]), scale(m[1], v[1])), scale(m[2], v[2]))
+}
+
+pub fn mul_mat(a: Mat3, b: Mat3) -> Mat3 {
+    [mul(a, b[0]), mul(a, b[1]), mul(a, b[2])]
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    const DOWN: Vec3 = [0.0, -1.0, 0.0];
+
+    fn scene(leipae: &[[f32; 4]]) -> SceneSdf<'static> {
+        SceneSdf::new(0.0, leipae.to_vec())
+    }
+
+    #[test]
+    fn leipae_far_away() {
+        // Outside the bounding sphere only the cheap estimate is used
+        assert_eq!(sd_leipae_round([10.0, 0.0, 0.0]).dist, 7.5);
+    }
+
+    #[test]
+    fn leipae_inside_and_out() {
+        assert!(sd_leipae_round([0.0, 0.0, 0.0]).dist < 0.0);
+        assert!(sd_leipae_round([0.0, 2.0, 0.0]).dist > 0.0);
+        assert!(sd_leipae_round([3.0, 0.0, 0.0]).dist > 0.0);
+    }
+
+    #[test]
+    fn ray_march_hits_the_terrain() {
+        let scene = scene(&[]);
+
+        // Somewhere the terrain rises above the water
+        let (x, z) = (0..100)
+            .map(|i| (i as f32 * 0.7, i as f32 * -0.3))
+            .find(|&(x, z)| terrain::height(x, z) > WATER_LEVEL + 0.5)
+            .expect("no land above the water");
+
+        let camera = [x, 50.0, z];
+        let hit = scene
+            .ray_march(camera, DOWN, MIN_DIST, scene.max_dist())
+            .expect("missed the terrain");
+
+        assert_eq!(hit.material, MaterialId::Terrain);
+        assert!((hit.depth - (camera[1] - terrain::height(x, z))).abs() < 0.1);
+        assert_eq!(
+            scene.surface_at(add(camera, scale(DOWN, hit.depth))),
+            Surface::Terrain
+        );
+    }
+
+    #[test]
+    fn ray_march_misses_the_sky() {
+        let scene = scene(&[]);
+
+        assert_eq!(
+            scene.ray_march(
+                [0.0, 50.0, 0.0],
+                [0.0, 1.0, 0.0],
+                MIN_DIST,
+                scene.max_dist()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_march_hits_a_leipae() {
+        let scene = scene(&[[0.0, 60.0, 0.0, 1.0]]);
+
+        let camera = [0.0, 60.0, 20.0];
+        let hit = scene
+            .ray_march(camera, [0.0, 0.0, -1.0], MIN_DIST, scene.max_dist())
+            .expect("missed the leipae");
+
+        // Within the bounding sphere of the leipae
+        assert_eq!(hit.material, MaterialId::Crust);
+        assert!(hit.depth > 20.0 - LEIPAE_RADIUS && hit.depth < 20.0);
+        assert_eq!(
+            scene.surface_at([0.0, 60.0, 20.0 - hit.depth]),
+            Surface::Leipae(0)
+        );
+    }
+
+    #[test]
+    fn ray_march_gives_up_at_the_max_dist() {
+        let scene = scene(&[[0.0, 60.0, -70.0, 1.0]]);
+        let camera = [0.0, 60.0, 0.0];
+        let forward = [0.0, 0.0, -1.0];
+
+        let high = scene.clone().with_quality(Preset::High.quality());
+        let low = scene.with_quality(Preset::Low.quality());
+
+        assert!(high
+            .ray_march(camera, forward, MIN_DIST, high.max_dist())
+            .is_some());
+        assert_eq!(
+            low.ray_march(camera, forward, MIN_DIST, low.max_dist()),
+            None
+        );
+    }
+
+    #[test]
+    fn terrain_reads_the_heightmap_at_range() {
+        let heightmap = Heightmap::bake();
+        let scene = scene(&[]).with_heightmap(&heightmap, [0.0, 5.0, 0.0]);
+
+        // Up close the exact terrain, past the blend the baked heights
+        let near = [2.0, 5.0, 3.0];
+        let far = [30.0, 5.0, -40.0];
+
+        assert_eq!(
+            scene.terrain(near).dist,
+            near[1] - terrain::height(near[0], near[2])
+        );
+        assert_eq!(
+            scene.terrain(far).dist,
+            far[1] - heightmap.height(far[0], far[2]).unwrap()
+        );
+    }
+}
@@ -1,16 +1,21 @@
-use gl::types::*;
+use std::error::Error;
 use std::ffi::CString;
+use std::fmt;
+use std::fs;
 use std::ptr;
 use std::str;
-use std::fs;
-use std::error::Error;
-use std::fmt;
+
+use gl::types::*;
 
 #[derive(Debug)]
 pub struct Shader(GLuint);
 
 impl Shader {
     pub fn new(shader_src: &str, shader_type: GLenum) -> Result<Self, ShaderError> {
+        // WebGL2 only compiles GLSL ES
+        #[cfg(target_arch = "wasm32")]
+        let shader_src = &to_es(shader_src);
+
         unsafe {
             let shader = gl::CreateShader(shader_type);
             let c_str = CString::new(shader_src.as_bytes()).unwrap();
@@ -32,7 +37,12 @@ impl Shader {
     pub fn from_file(file_path: &str, shader_type: GLenum) -> Result<Self, ShaderError> {
         let shader_src = match fs::read_to_string(file_path) {
             Ok(src) => src,
-            Err(err) => return Err(ShaderError(format!("failed to read shader source file: {}", err)))
+            Err(err) => {
+                return Err(ShaderError(format!(
+                    "failed to read shader source file: {}",
+                    err
+                )))
+            }
         };
 
         Shader::new(&shader_src, shader_type)
@@ -49,6 +59,41 @@ impl Drop for Shader {
     }
 }
 
+// Inserts `#define` lines after the `#version` directive, which has to stay first
+pub fn with_defines<S: AsRef<str>>(shader_src: &str, defines: &[S]) -> String {
+    let (version, rest) = match shader_src.split_once('\n') {
+        Some((first, rest)) if first.starts_with("#version") => (first, rest),
+        _ => ("", shader_src),
+    };
+
+    let mut src = String::with_capacity(shader_src.len() + defines.len() * 32);
+    src.push_str(version);
+    src.push('\n');
+    for define in defines {
+        src.push_str("#define ");
+        src.push_str(define.as_ref());
+        src.push('\n');
+    }
+    src.push_str(rest);
+
+    src
+}
+
+// The GLSL ES 3.00 of WebGL2 from a GLSL 3.30 shader. The shaders are written
+// to be valid in both besides the version, and ES has no default precision
+// for floats in fragment shaders.
+pub fn to_es(shader_src: &str) -> String {
+    let rest = match shader_src.split_once('\n') {
+        Some((first, rest)) if first.starts_with("#version") => rest,
+        _ => shader_src,
+    };
+
+    format!(
+        "#version 300 es\nprecision highp float;\nprecision highp int;\nprecision highp sampler2D;\n{}",
+        rest
+    )
+}
+
 #[derive(Debug)]
 pub struct ShaderError(pub String);
 
@@ -73,7 +118,7 @@ fn read_shader_error(shader: GLuint) -> String {
             shader,
             info_log_length,
             &mut read_length,
-            log_buffer.as_mut_ptr() as *mut GLchar
+            log_buffer.as_mut_ptr() as *mut GLchar,
         );
 
         log_buffer.set_len(read_length as usize);
@@ -81,3 +126,33 @@ fn read_shader_error(shader: GLuint) -> String {
 
     String::from_utf8_lossy(&log_buffer).to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    use super::*;
+
+    #[test]
+    fn defines_follow_the_version() {
+        let src = with_defines(
+            "#version 330 core\nvoid main() {}\n",
+            &["SHADOWS", "STEPS 64"],
+        );
+
+        assert_eq!(
+            src,
+            "#version 330 core\n#define SHADOWS\n#define STEPS 64\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn es_replaces_the_version() {
+        let src = to_es("#version 330 core\nout vec4 fragColor;\n");
+
+        assert!(src.starts_with("#version 300 es\nprecision highp float;\n"));
+        assert!(src.ends_with("precision highp sampler2D;\nout vec4 fragColor;\n"));
+        assert!(!src.contains("330"));
+    }
+}
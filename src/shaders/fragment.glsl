@@ -1,31 +1,130 @@
 #version 330 core
 
-in vec4 gl_FragCoord;
+#ifdef GBUFFER_PASS
+// What the rays hit, see gbuffer.rs
+layout(location = 0) out vec4 gPosition;
+layout(location = 1) out vec4 gNormal;
+layout(location = 2) out vec4 gMaterial;
+#else
 out vec4 FragColor;
+#endif
 
-const int LEIPAE_COUNT = 20;
+const int MAX_LEIPAE = 256;
+const int RIPPLE_COUNT = 8;
 const float TOTAL_DURATION = 100.0;
 
 uniform float iTime;
 uniform vec2 iResolution;
 uniform vec3 iCamera;
 uniform vec3 iTarget;
-uniform vec4 iLeipae[LEIPAE_COUNT];
-
-float PROGRESS = (TOTAL_DURATION - iTime) / TOTAL_DURATION;
-
-const int MAX_MARCHING_STEPS = 400;
+// The leipae in view, nearest first, see culling.rs. Position and scale.
+layout(std140) uniform Leipae {
+    vec4 iLeipae[MAX_LEIPAE];
+};
+uniform int iLeipaeCount;
+// Rings left on the water by falling leipae, see `Demo::ripples`. The centre
+// x and z, the start time and the strength, 0 for the unused ones.
+uniform vec4 iRipples[RIPPLE_COUNT];
+// Subpixel offset for temporal antialiasing and supersampling
+uniform vec2 iJitter;
+// What to output instead of the shaded scene, one of the DEBUG_ constants
+uniform int iDebugMode;
+// Textures chosen per scene in the timeline, see `Scene::channels`
+uniform sampler2D iChannel0;
+uniform sampler2D iChannel1;
+uniform sampler2D iChannel2;
+uniform sampler2D iChannel3;
+uniform vec3 iChannelResolution[4];
+// Terrain heights baked on the CPU, see terrain.rs. The bounds are the x and z
+// of the first sample and the distance between samples.
+uniform sampler2D iHeightmap;
+uniform vec3 iHeightmapBounds;
+#ifdef LIGHTING_PASS
+// The G-buffer written by the raymarching pass. The hit position and depth,
+// the normal and marching steps and the material.
+uniform sampler2D iGPosition;
+uniform sampler2D iGNormal;
+uniform sampler2D iGMaterial;
+#endif
+
+// Material IDs, the indices of iMaterials. The SDFs return the ID in x and a
+// variation added to the albedo in y.
+const int MATERIAL_TERRAIN = 0;
+const int MATERIAL_WATER = 1;
+const int MATERIAL_NEON_PINK = 2;
+const int MATERIAL_NEON_CYAN = 3;
+const int MATERIAL_CRUST = 4;
+const int MATERIAL_COUNT = 5;
+
+struct Material {
+    vec3 albedo;
+    // 0 is a mirror, 1 a diffuse surface
+    float roughness;
+    vec3 emissive;
+    float specular;
+};
+
+// Chosen per scene in the timeline, see material.rs
+layout(std140) uniform Materials {
+    Material iMaterials[MATERIAL_COUNT];
+};
+
+// The soft shadows, sky, normals, leipae, baked terrain and reflections can be
+// compiled out for profiling with the NO_SOFT_SHADOWS, NO_SKY, NO_NORMALS,
+// NO_LEIPAE, NO_HEIGHTMAP and NO_REFLECTIONS defines
+
+// The renderer compiles the shader twice, with GBUFFER_PASS for the pass that
+// marches the rays into the G-buffer and with LIGHTING_PASS for the one that
+// shades it. Without either it does both at once, like in the Shadertoy export.
+
+#define PROGRESS ((TOTAL_DURATION - iTime) / TOTAL_DURATION)
+
+// Quality settings, the renderer defines these from the preset, see
+// quality.rs. The defaults are the high preset.
+#ifndef MAX_MARCHING_STEPS
+#define MAX_MARCHING_STEPS 400
+#endif
+#ifndef MAX_DIST
+#define MAX_DIST 100.0
+#endif
+#ifndef SHADOW_STEPS
+#define SHADOW_STEPS 256
+#endif
+#ifndef SHADOW_SHARPNESS
+#define SHADOW_SHARPNESS 24.0
+#endif
+#ifndef CLOUD_OCTAVES
+#define CLOUD_OCTAVES 8
+#endif
+#ifndef STAR_OCTAVES
+#define STAR_OCTAVES 4
+#endif
+
+// Debug views, see `DebugView` in renderer.rs
+const int DEBUG_STEPS = 1;
+const int DEBUG_DEPTH = 2;
+const int DEBUG_NORMALS = 3;
+const int DEBUG_MATERIAL = 4;
+const int DEBUG_SHADOW = 5;
+const int DEBUG_FOG = 6;
 const float MIN_DIST = 0.0;
-const float MAX_DIST = 100.0;
 const float FOV = 60.0;
 const float EPSILON = 0.00001;
 const float PI = 3.14159265;
 const float WATER_LEVEL = 0.3;
+// sdLeipaeRound is never below the distance to a sphere this big
+const float LEIPAE_RADIUS = 2.5;
+// The terrain is computed closer than this to the camera, blending into the
+// baked heights over the next DETAIL_BLEND units
+const float DETAIL_DISTANCE = 10.0;
+const float DETAIL_BLEND = 5.0;
 
 const vec3 SUN_COLOR = vec3(0.8, 1.0, 1.0);
 const vec3 SKY_COLOR = vec3(0.06, 0.03, 0.69);
 const vec3 FOG_COLOR = vec3(0.94, 0.12, 0.58);
 const vec3 COLOR_SHIFT = vec3(2.5, 1.5, 1.0);
+const float RIPPLE_SPEED = 1.5;
+const float RIPPLE_LIFETIME = 6.0;
 
 // Translations
 // http://en.wikipedia.org/wiki/Rotation_matrix#Basic_rotations
@@ -113,7 +212,7 @@ float hash(vec2 x) {
     vec2 fractional = fract(x);
 
     vec2 u =
-        3 * fractional * fractional - 2 * fractional * fractional * fractional;
+        3.0 * fractional * fractional - 2.0 * fractional * fractional * fractional;
 
     vec2 ua = 50.0 * fract(x / PI);
     return 2.0 * fract(ua.x * ua.y * (ua.x + ua.y)) - 1.0;
@@ -168,7 +267,7 @@ float noise(vec2 p) {
     return res * res;
 }
 
-// Union of SDF distances, modified to carry material color in "xyz" properties
+// Union of SDF distances, modified to carry the material in "xyz" properties
 vec4 opUnion(vec4 distA, vec4 distB) {
     if (distA.a < distB.a) {
         return distA;
@@ -186,31 +285,31 @@ vec3 opBend(vec3 p, float k) {
 
 // Scene object SDF functions
 
-// Dark water with pink and cyan texture
+// Dark water with a pink and cyan grid
 vec4 sdWater(vec3 p, float y) {
-    vec3 material = vec3(0.0);
+    int material = MATERIAL_WATER;
 
     vec3 fractional = fract(p);
     if (fractional.x < 0.03) {
-        material = vec3(1, 0, 1);        
+        material = MATERIAL_NEON_PINK;
     } else if (fractional.z <= 0.03) {
-        material = vec3(0, 1, 1);
+        material = MATERIAL_NEON_CYAN;
     }
 
     float dist = p.y - y;
-    return vec4(material, dist);
+    return vec4(float(material), 0.0, 0.0, dist);
 }
 
 // Unused leipae, decided to not use this after all and just stick to the round one
 vec4 sdLeipae(in vec3 p) {
-    if (sdSphere(p, 10) > 0) {
+    if (sdSphere(p, 10.0) > 0.0) {
         // Get a better estimate and use that
-        return vec4(0.0, 0.0, 0.0, sdSphere(p, 6));
+        return vec4(float(MATERIAL_CRUST), 0.0, 0.0, sdSphere(p, 6.0));
     }
 
-    float noise5 = noise(p.xz * 5);
-    float noise30 = noise(p.xz * 30);
-    float noise50 = noise(p.xz * 50);
+    float noise5 = noise(p.xz * 5.0);
+    float noise30 = noise(p.xz * 30.0);
+    float noise50 = noise(p.xz * 50.0);
 
     float ellipsoidDist = sdEllipsoid(opBend(p, -0.03), vec3(5.0, 1.0, 1.5)) -
                           0.25 + (0.05 * noise5) + (0.01 * noise30) +
@@ -226,28 +325,27 @@ vec4 sdLeipae(in vec3 p) {
         }
 
         float wedge = sdTriPrism(tRotateZ(1.0) * tRotateY(0.3) *
-                                     vec3(p.x + i * 1.1, p.y - offsetY, p.z),
+                                     vec3(p.x + float(i) * 1.1, p.y - offsetY, p.z),
                                  vec2(1.0, 2.0)) +
-                      (0.03 * noise(p.xz * 10));
+                      (0.03 * noise(p.xz * 10.0));
 
         dist = max(dist, -wedge);
     }
 
-    vec3 material =
-        vec3(0.88, 0.52, 0.07) + (noise5 * vec3(0.5) + noise30 * vec3(0.5));
-    return vec4(material, dist);
+    float crust = noise5 * 0.5 + noise30 * 0.5;
+    return vec4(float(MATERIAL_CRUST), crust, 0.0, dist);
 }
 
 // The main leipae, round with wedges cut to it
 vec4 sdLeipaeRound(in vec3 p) {
-    if (sdSphere(p, 5) > 0) {
+    if (sdSphere(p, 5.0) > 0.0) {
         // Get a better estimate and use that
-        return vec4(0.0, 0.0, 0.0, sdSphere(p, 2.5));
+        return vec4(float(MATERIAL_CRUST), 0.0, 0.0, sdSphere(p, 2.5));
     }
 
-    float noise5 = noise(p.xz * 5);
-    float noise30 = noise(p.xz * 30);
-    float noise50 = noise(p.xz * 50);
+    float noise5 = noise(p.xz * 5.0);
+    float noise30 = noise(p.xz * 30.0);
+    float noise50 = noise(p.xz * 50.0);
 
     float ellipsoidDist = sdEllipsoid(opBend(p, -0.08), vec3(2.2, 0.8, 2.1)) -
                           0.25 + (0.05 * noise5) + (0.01 * noise30) +
@@ -258,9 +356,9 @@ vec4 sdLeipaeRound(in vec3 p) {
         float offsetY = 1.55;
 
         float wedge = sdTriPrism(tRotateZ(0.9) * tRotateY(0.3) *
-                                     vec3(p.x + i * 1.1, p.y - offsetY, p.z),
+                                     vec3(p.x + float(i) * 1.1, p.y - offsetY, p.z),
                                  vec2(1.0, 6.0)) +
-                      (0.03 * noise(p.xz * 10));
+                      (0.03 * noise(p.xz * 10.0));
 
         dist = max(dist, -wedge);
     }
@@ -269,49 +367,96 @@ vec4 sdLeipaeRound(in vec3 p) {
         float offsetY = 1.55;
 
         float wedge = sdTriPrism(tRotateZ(1.1) * tRotateY(-0.8) *
-                                     vec3(p.x + i * 1.1, p.y - offsetY, p.z),
+                                     vec3(p.x + float(i) * 1.1, p.y - offsetY, p.z),
                                  vec2(1.0, 6.0)) +
-                      (0.03 * noise(p.xz * 10));
+                      (0.03 * noise(p.xz * 10.0));
 
         dist = max(dist, -wedge);
     }
 
-    vec3 material =
-        vec3(0.88, 0.52, 0.07) + (noise5 * vec3(0.5) + noise30 * vec3(0.5));
-    return vec4(material, dist);
+    float crust = noise5 * 0.5 + noise30 * 0.5;
+    return vec4(float(MATERIAL_CRUST), crust, 0.0, dist);
+}
+
+float terrainHeight(vec2 p) {
+    return abs(fbm((p + vec2(50.0, -30.0)) / 2.0, 1.1, 4)) * 2.0;
+}
+
+#ifndef NO_HEIGHTMAP
+// Bilinear filtering by hand, the texture units interpolate with too little
+// precision for the normals
+float bakedHeight(vec2 texel) {
+    ivec2 i = ivec2(floor(texel));
+    vec2 f = fract(texel);
+
+    float a = texelFetch(iHeightmap, i, 0).r;
+    float b = texelFetch(iHeightmap, i + ivec2(1, 0), 0).r;
+    float c = texelFetch(iHeightmap, i + ivec2(0, 1), 0).r;
+    float d = texelFetch(iHeightmap, i + ivec2(1, 1), 0).r;
+
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
 }
+#endif
 
 // Terrain with hills
 vec4 sdTerrain(in vec3 p) {
-    vec3 material = vec3(0.6 * PROGRESS);
-    if (p.y - WATER_LEVEL < 0) {
+    vec3 material = vec3(float(MATERIAL_TERRAIN), 0.0, 0.0);
+    if (p.y - WATER_LEVEL < 0.0) {
         return vec4(material, MAX_DIST);
     }
 
-    return vec4(material,
-                p.y - abs(fbm((p.xz + vec2(50.0, -30.0)) / 2, 1.1, 4)) * 2);
+#ifndef NO_HEIGHTMAP
+    float baked = smoothstep(DETAIL_DISTANCE, DETAIL_DISTANCE + DETAIL_BLEND,
+                             distance(p.xz, iCamera.xz));
+    vec2 texel = (p.xz - iHeightmapBounds.xy) / iHeightmapBounds.z;
+    vec2 size = vec2(textureSize(iHeightmap, 0) - 1);
+
+    // Outside of the baked area the terrain is computed like up close
+    if (baked > 0.0 && all(greaterThanEqual(texel, vec2(0.0))) &&
+        all(lessThan(texel, size))) {
+        float height = bakedHeight(texel);
+        if (baked < 1.0) {
+            height = mix(terrainHeight(p.xz), height, baked);
+        }
+
+        return vec4(material, p.y - height);
+    }
+#endif
+
+    return vec4(material, p.y - terrainHeight(p.xz));
 }
 
 vec4 sdScene(in vec3 p) {
     vec4 terrain = sdTerrain(p);
     vec4 water = sdWater(p, WATER_LEVEL);
 
-    vec4 leipae = vec4(0.0, 0.0, 0.0, MAX_DIST);
+    vec4 leipae = vec4(float(MATERIAL_CRUST), 0.0, 0.0, MAX_DIST);
 
-    for (int i = 0; i < LEIPAE_COUNT; i++) {
+#ifndef NO_LEIPAE
+    for (int i = 0; i < iLeipaeCount; i++) {
         vec4 offset = iLeipae[i];
-        vec4 dist = sdLeipaeRound(tRotateZ(iTime - offset.z) * tRotateX(iTime - offset.x) * (p - offset.xyz) * offset.w) / offset.w;
+        // Cheaper than rotating into the leipae
+        if (distance(p, offset.xyz) - LEIPAE_RADIUS / offset.w >= leipae.w) {
+            continue;
+        }
+
+        vec4 dist = sdLeipaeRound(tRotateZ(iTime - offset.z) * tRotateX(iTime - offset.x) * (p - offset.xyz) * offset.w);
+        dist.w /= offset.w;
         leipae = opUnion(leipae, dist);
 
         if (leipae.w < EPSILON) {
             break;
         }
     }
+#endif
 
     return opUnion(opUnion(terrain, water), leipae);
 }
 
 vec3 estimateNormal(vec3 p) {
+#ifdef NO_NORMALS
+    return vec3(0.0, 1.0, 0.0);
+#endif
     float dx = sdScene(vec3(p.x + EPSILON, p.y, p.z)).a -
                sdScene(vec3(p.x - EPSILON, p.y, p.z)).a;
     float dy = sdScene(vec3(p.x, p.y + EPSILON, p.z)).a -
@@ -321,6 +466,32 @@ vec3 estimateNormal(vec3 p) {
     return normalize(vec3(dx, dy, dz));
 }
 
+// Height of the rings spreading from where the leipae fell in
+float ripples(vec2 p) {
+    float height = 0.0;
+    for (int i = 0; i < RIPPLE_COUNT; i++) {
+        vec4 ripple = iRipples[i];
+        float age = iTime - ripple.z;
+        if (ripple.w <= 0.0 || age < 0.0 || age > RIPPLE_LIFETIME) {
+            continue;
+        }
+
+        // Distance from the wave front, fading as it spreads
+        float d = distance(p, ripple.xy) - age * RIPPLE_SPEED;
+        height += sin(d * 8.0) * exp(-d * d * 2.0) * (1.0 - age / RIPPLE_LIFETIME) *
+                  0.03 * ripple.w;
+    }
+    return height;
+}
+
+// The water is flat in the SDF, the ripples only bend its normal
+vec3 waterNormal(vec2 p) {
+    float h = 0.01;
+    float dx = ripples(p + vec2(h, 0.0)) - ripples(p - vec2(h, 0.0));
+    float dz = ripples(p + vec2(0.0, h)) - ripples(p - vec2(0.0, h));
+    return normalize(vec3(-dx, 2.0 * h, -dz));
+}
+
 // Create a homogeneous transformation matrix that will cause a vector to
 // point at `target`, using `up` for orientation.
 mat4 lookAt(vec3 camera, vec3 target, vec3 up) {
@@ -338,6 +509,9 @@ vec3 rayDirection(float fov, vec2 dimensions, vec2 fragCoord) {
     return normalize(vec3(xy, -z));
 }
 
+// Steps the last rayMarch took, for the step count view
+int marchSteps = 0;
+
 vec4 rayMarch(in vec3 camera, in vec3 rayDir, float start, float end) {
     // Check if we would hit the bounding plane before reaching "end"
     float stepsToBoundingPlane = (100.0 - camera.y) / rayDir.y;
@@ -352,6 +526,7 @@ vec4 rayMarch(in vec3 camera, in vec3 rayDir, float start, float end) {
     vec3 material = vec3(1.0);
 
     for (int i = 0; i < MAX_MARCHING_STEPS; i++) {
+        marchSteps = i + 1;
         stepDist = 0.001 * depth;
 
         vec3 pos = camera + depth * rayDir;
@@ -380,8 +555,12 @@ vec4 rayMarch(in vec3 camera, in vec3 rayDir, float start, float end) {
 
 // Derived from: https://iquilezles.org/articles/rmshadows/
 float softShadows(in vec3 sunDir, in vec3 p, float k) {
+#ifdef NO_SOFT_SHADOWS
+    return 1.0;
+#endif
     float opacity = 1.0;
-    for (float depth = 1.0; depth < MAX_DIST;) {
+    float depth = 1.0;
+    for (int i = 0; i < SHADOW_STEPS && depth < MAX_DIST; i++) {
         float dist = sdScene(p + depth * sunDir).a;
         if (dist < EPSILON) {
             return 0.0;
@@ -392,14 +571,37 @@ float softShadows(in vec3 sunDir, in vec3 p, float k) {
     return opacity;
 }
 
-vec3 lightning(in vec3 sun, in vec3 p, in vec3 camera, in vec3 material) {
-    vec3 n = estimateNormal(p);
+vec3 surfaceAlbedo(in vec3 material) {
+    int id = int(material.x);
+    vec3 albedo = iMaterials[id].albedo + material.y;
+
+    // The land darkens as the day goes on
+    if (id == MATERIAL_TERRAIN) {
+        albedo *= PROGRESS;
+    }
+    return albedo;
+}
+
+vec3 surfaceNormal(in vec3 p, in vec3 material) {
+    return int(material.x) == MATERIAL_WATER ? waterNormal(p.xz) : estimateNormal(p);
+}
+
+vec3 lightning(in vec3 sun, in vec3 p, in vec3 n, in vec3 camera, in vec3 material) {
+    Material m = iMaterials[int(material.x)];
+
+    vec3 albedo = surfaceAlbedo(material);
 
     float dotNS = dot(n, sun);
     vec3 sunLight = vec3(0.0);
-    if (dotNS > 0) {
-        sunLight =
-            clamp(SUN_COLOR * dotNS * softShadows(sun, p, 24.0), 0.0, 1.0);
+    vec3 highlight = vec3(0.0);
+    if (dotNS > 0.0) {
+        float shadow = softShadows(sun, p, SHADOW_SHARPNESS);
+        sunLight = clamp(SUN_COLOR * dotNS * shadow, 0.0, 1.0);
+
+        // Blinn-Phong, the smoother the surface the smaller the highlight
+        vec3 h = normalize(sun + normalize(camera - p));
+        float shininess = exp2(10.0 * (1.0 - m.roughness));
+        highlight = SUN_COLOR * m.specular * pow(max(dot(n, h), 0.0), shininess) * shadow;
     }
 
     vec3 skyLight =
@@ -407,29 +609,38 @@ vec3 lightning(in vec3 sun, in vec3 p, in vec3 camera, in vec3 material) {
 
     float dotNB = dot(n, -sun);
     vec3 bounceLight = vec3(0.0);
-    if (dotNB > 0) {
+    if (dotNB > 0.0) {
         bounceLight = clamp(SUN_COLOR * dotNB * (0.4 * SUN_COLOR), 0.0, 1.0);
     }
 
-    return clamp(material * (sunLight + skyLight + bounceLight), 0.0, 1.0);
+    return clamp(albedo * (sunLight + skyLight + bounceLight) + highlight, 0.0, 1.0) +
+           m.emissive;
+}
+
+// How much of the colour the fog covers
+vec3 fogAmount(float dist) {
+    return 1.0 - exp2(-dist * 0.010 * COLOR_SHIFT);
 }
 
 vec3 fog(in vec3 color, float dist) {
-    vec3 e = exp2(-dist * 0.010 * COLOR_SHIFT);
-    return color * e + (1.0 - e) * FOG_COLOR;
+    vec3 e = fogAmount(dist);
+    return color * (1.0 - e) + e * FOG_COLOR;
 }
 
 vec3 sky(in vec3 camera, in vec3 dir, in vec3 sun) {
+#ifdef NO_SKY
+    return SKY_COLOR;
+#endif
     // Deeper blue when looking up
     vec3 color = SKY_COLOR - 0.5 * dir.y;
 
     // Draw stars behind the clouds that at 5000 height at the end of the demo.
     if (iTime > 75.0) {
-        float dist = 5000 / dir.y;
-        if (dist > 0.0 && dist < 100000) {
+        float dist = 5000.0 / dir.y;
+        if (dist > 0.0 && dist < 100000.0) {
             vec3 p = (camera + dist * dir);
             float stars =
-                smoothstep(0.7, 1.0, fbm(vec2(-6.0 + p.x * 0.015, 2.0 + p.z * 0.003), 0.6, 4));
+                smoothstep(0.7, 1.0, fbm(vec2(-6.0 + p.x * 0.015, 2.0 + p.z * 0.003), 0.6, STAR_OCTAVES));
             color = mix(color, vec3(1.0), stars);
         }
     }
@@ -438,11 +649,11 @@ vec3 sky(in vec3 camera, in vec3 dir, in vec3 sun) {
     // "dir" is the normalized vector towards the plane with length of 1.
     // To get the point on the plane figure out how many steps of "dir"s are
     // needed for the y axel delta, and then multiply the whole dir by that.
-    float dist = (2500 - camera.y) / dir.y;
-    if (dist > 0.0 && dist < 100000) {
+    float dist = (2500.0 - camera.y) / dir.y;
+    if (dist > 0.0 && dist < 100000.0) {
         vec3 p = (camera + dist * dir);
         float clouds =
-            smoothstep(-0.1, 0.8, fbm(0.0002 * p.xz + vec2(-3.0, 2.0), 0.9, 8));
+            smoothstep(-0.1, 0.8, fbm(0.0002 * p.xz + vec2(-3.0, 2.0), 0.9, CLOUD_OCTAVES));
         color = mix(color, vec3(1.0), 0.4 * clouds);
     }
 
@@ -476,51 +687,113 @@ vec3 sky(in vec3 camera, in vec3 dir, in vec3 sun) {
 
     // Sun glare
     if (dotSun > 0.995) {
-        color = mix(color, vec3(1.0, 1.0, 0.5), (dotSun - 0.995) / (1 - 0.995));
+        color = mix(color, vec3(1.0, 1.0, 0.5), (dotSun - 0.995) / (1.0 - 0.995));
     }
 
     return color;
 }
 
-void main() {
-    vec3 viewDir = rayDirection(FOV, iResolution, gl_FragCoord.xy);
+// What the water mirrors, marched from the surface along the reflected ray
+vec3 reflection(in vec3 sun, in vec3 p, in vec3 dir, in vec3 n) {
+    vec3 r = reflect(dir, n);
+    // The ripples can tilt the ray under the surface
+    r.y = abs(r.y);
 
-    vec3 camera = iCamera;
-    vec3 target = iTarget;
+    vec3 origin = p + 0.01 * n;
+    vec4 hit = rayMarch(origin, r, MIN_DIST, MAX_DIST);
+    if (hit.a < 0.0) {
+        return sky(origin, r, sun);
+    }
 
-    mat4 viewToWorld = lookAt(camera, target, vec3(0.0, 1.0, 0.0));
-    vec3 worldDir = (viewToWorld * vec4(viewDir, 0.0)).xyz;
+    vec3 q = origin + hit.a * r;
+    return fog(lightning(sun, q, surfaceNormal(q, hit.xyz), origin, hit.xyz), hit.a);
+}
 
-    vec4 r = rayMarch(camera, worldDir, MIN_DIST, MAX_DIST);
-    float dist = r.a;
+// Black through red and yellow to white
+vec3 heatmap(float t) {
+    return clamp(vec3(3.0 * t, 3.0 * t - 1.0, 3.0 * t - 2.0), 0.0, 1.0);
+}
 
-    vec3 sun = normalize(vec3(-97.0 + 100.0 * cos(PROGRESS - 0.2), 100.0 * sin(PROGRESS - 0.2), -100.0));
+// One term of the shading instead of the image, black for the sky except
+// for the step count
+vec3 debugView(int mode, in vec3 sun, in vec3 p, in vec3 n, in vec4 hit, int steps) {
+    if (mode == DEBUG_STEPS) {
+        return heatmap(float(steps) / float(MAX_MARCHING_STEPS));
+    }
 
+    float dist = hit.a;
     if (dist < 0.0) {
-        FragColor = vec4(sky(camera, worldDir, sun), 1.0);
-    } else {
-        vec3 material = r.xyz;
-
-        // The closest point on the surface to the eyepoint along the view ray
-        vec3 p = camera + dist * worldDir;
+        return vec3(0.0);
+    }
 
-        vec3 color = lightning(sun, p, camera, material);
-        color = fog(color, dist);
+    if (mode == DEBUG_DEPTH) {
+        return vec3(1.0 - dist / MAX_DIST);
+    } else if (mode == DEBUG_NORMALS) {
+        return 0.5 + 0.5 * n;
+    } else if (mode == DEBUG_MATERIAL) {
+        return surfaceAlbedo(hit.xyz);
+    } else if (mode == DEBUG_SHADOW) {
+        return vec3(dot(n, sun) > 0.0 ? softShadows(sun, p, SHADOW_SHARPNESS) : 0.0);
+    }
+    return fogAmount(dist);
+}
 
-        color = pow(color, vec3(1.0, 0.92, 1.0));
-        color *= vec3(1.02, 0.99, 0.9);
-        color.z = color.z + 0.1;
+// The colour seen along `dir` from the camera given what the ray hit at `p`.
+// Alpha carries the depth for the later passes, negative for the sky. Colour
+// grading and fades are done in the grade pass.
+vec4 shade(in vec3 dir, in vec3 p, in vec3 n, in vec4 hit, int steps) {
+    vec3 sun = normalize(vec3(-97.0 + 100.0 * cos(PROGRESS - 0.2), 100.0 * sin(PROGRESS - 0.2), -100.0));
+    float dist = hit.a;
 
-        color = smoothstep(0.0, 1.0, color);
+    if (iDebugMode != 0) {
+        return vec4(debugView(iDebugMode, sun, p, n, hit, steps), dist);
+    }
 
-        FragColor = vec4(color, 1.0);
+    if (dist < 0.0) {
+        return vec4(sky(iCamera, dir, sun), -1.0);
     }
 
-    if (iTime > TOTAL_DURATION) {
-        // Fade to black at the end
-        FragColor = mix(FragColor, vec4(0.0), (iTime - TOTAL_DURATION) / 5.0);
-    } else if (iTime < 2.0) {
-        // Fade in at the beginning
-        FragColor = mix(FragColor, vec4(0.0), (2.0 - iTime) / 2.0);
+    vec3 material = hit.xyz;
+    vec3 color = lightning(sun, p, n, iCamera, material);
+
+#ifndef NO_REFLECTIONS
+    // Schlick's Fresnel, the water mirrors more at grazing angles
+    if (int(material.x) == MATERIAL_WATER) {
+        float fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(n, -dir), 0.0), 5.0);
+        float mirror = 1.0 - iMaterials[MATERIAL_WATER].roughness;
+        color = mix(color, reflection(sun, p, dir, n), fresnel * mirror);
     }
-}
\ No newline at end of file
+#endif
+
+    return vec4(fog(color, dist), dist);
+}
+
+void main() {
+    vec3 viewDir = rayDirection(FOV, iResolution, gl_FragCoord.xy + iJitter);
+
+    mat4 viewToWorld = lookAt(iCamera, iTarget, vec3(0.0, 1.0, 0.0));
+    vec3 worldDir = (viewToWorld * vec4(viewDir, 0.0)).xyz;
+
+#ifdef LIGHTING_PASS
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec4 position = texelFetch(iGPosition, texel, 0);
+    vec4 normal = texelFetch(iGNormal, texel, 0);
+    vec4 hit = vec4(texelFetch(iGMaterial, texel, 0).xyz, position.w);
+
+    FragColor = shade(worldDir, position.xyz, normal.xyz, hit, int(normal.w));
+#else
+    vec4 hit = rayMarch(iCamera, worldDir, MIN_DIST, MAX_DIST);
+
+    // The closest point on the surface to the eyepoint along the view ray
+    vec3 p = iCamera + hit.a * worldDir;
+    vec3 n = hit.a < 0.0 ? vec3(0.0) : surfaceNormal(p, hit.xyz);
+
+#ifdef GBUFFER_PASS
+    gPosition = vec4(p, hit.a);
+    gNormal = vec4(n, float(marchSteps));
+    gMaterial = vec4(hit.xyz, 0.0);
+#else
+    FragColor = shade(worldDir, p, n, hit, marchSteps);
+#endif
+#endif
+}
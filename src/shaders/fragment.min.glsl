@@ -1,2 +1,52 @@
 #version 330 core
-in vec4 gl_FragCoord;out vec4 FragColor;const int v=20;const float i=100.;uniform float iTime;uniform vec2 iResolution;uniform vec3 iCamera,iTarget;uniform vec4 iLeipae[v];float y=(i-iTime)/i;const int m=400;const float f=0.,z=100.,r=60.,x=1e-5,e=3.14159265,w=.3;const vec3 c=vec3(.8,1.,1.),s=vec3(.06,.03,.69),k=vec3(.94,.12,.58),g=vec3(2.5,1.5,1.);mat3 n(float v){float i=sin(v),x=cos(v);return mat3(vec3(1,0,0),vec3(0,x,-i),vec3(0,i,x));}mat3 t(float v){float i=sin(v),x=cos(v);return mat3(vec3(x,0,i),vec3(0,1,0),vec3(-i,0,x));}mat3 p(float v){float i=sin(v),x=cos(v);return mat3(vec3(x,-i,0),vec3(i,x,0),vec3(0,0,1));}float n(vec3 v,vec3 i){vec3 x=abs(v)-i;return length(max(x,0.))+min(max(x.x,max(x.y,x.z)),0.);}float p(vec3 v,float i){return length(v)-i;}float t(vec3 v,vec3 i){float x=length(v/i),f=length(v/(i*i));return x*(x-1.)/f;}float h(vec3 v,vec2 i){vec3 x=abs(v);return max(x.z-i.y,max(x.x*.866025+v.y*.5,-v.y)-i.x*.5);}float h(vec2 v,float i,float x,float z){v.x=abs(v.x);float y=sin(i),f=cos(i);return(f*v.x>y*v.y?length(v-vec2(y,f)*x):abs(length(v)-x))-z;}float h(vec2 v){vec2 i=50.*fract(v/e);return 2.*fract(i.x*i.y*(i.x+i.y))-1.;}float l(vec2 v){vec2 i=floor(v),x=fract(v),f=x*x*(3.-2.*x);float y=h(i+vec2(0,0)),m=h(i+vec2(1,0)),z=h(i+vec2(0,1)),w=h(i+vec2(1,1)),r=y,c=m-y,g=z-y,e=y-m-z+w;return r+c*f.x+g*f.y+e*f.x*f.y;}float h(vec2 v,float i,int x){float m=exp2(-i),f=1.,z=.5,r=0.;for(int e=0;e<x;e++)r+=z*l(f*v),f*=1.9,z*=m;return r;}float F(vec2 v){return fract(sin(dot(v,vec2(12.9898,78.233)))*43758.5453);}float a(vec2 v){vec2 i=floor(v),f=fract(v);f=f*f*(3.-2.*f);float x=mix(mix(F(i),F(i+vec2(1.,0.)),f.x),mix(F(i+vec2(0.,1.)),F(i+vec2(1.,1.)),f.x),f.y);return x*x;}vec4 F(vec4 v,vec4 i){if(v.w<i.w)return v;return i;}vec3 a(vec3 v,float i){float x=cos(i*v.x),f=sin(i*v.x);mat2 z=mat2(x,-f,f,x);return vec3(z*v.xy,v.z);}vec4 l(vec3 v,float i){vec3 x=vec3(0.),f=fract(v);if(f.x<.03)x=vec3(1,0,1);else if(f.z<=.03)x=vec3(0,1,1);float z=v.y-i;return vec4(x,z);}vec4 o(vec3 v){if(p(v,10)>0)return vec4(0.,0.,0.,p(v,6));float i=a(v.xz*5),f=a(v.xz*30),x=a(v.xz*50),m=t(a(v,-.03),vec3(5.,1.,1.5))-.25+.05*i+.01*f+.005*x,r=m;for(int z=-3;z<=3;z++){float y=1.9;if(z==-2||z==2)y=1.7;else if(z==-3||z==3)y=1.4;float e=h(p(1.)*t(.3)*vec3(v.x+z*1.1,v.y-y,v.z),vec2(1.,2.))+.03*a(v.xz*10);r=max(r,-e);}vec3 y=i*vec3(.5)+f*vec3(.5)+vec3(.88,.52,.07);return vec4(y,r);}vec4 d(vec3 v){if(p(v,5)>0)return vec4(0.,0.,0.,p(v,2.5));float i=a(v.xz*5),f=a(v.xz*30),x=a(v.xz*50),m=t(a(v,-.08),vec3(2.2,.8,2.1))-.25+.05*i+.01*f+.005*x,r=m;for(int z=-1;z<=1;z++){float y=h(p(.9)*t(.3)*vec3(v.x+z*1.1,v.y-1.55,v.z),vec2(1.,6.))+.03*a(v.xz*10);r=max(r,-y);}for(int z=-1;z<=1;z++){float y=h(p(1.1)*t(-.8)*vec3(v.x+z*1.1,v.y-1.55,v.z),vec2(1.,6.))+.03*a(v.xz*10);r=max(r,-y);}vec3 y=i*vec3(.5)+f*vec3(.5)+vec3(.88,.52,.07);return vec4(y,r);}vec4 u(vec3 v){vec3 i=vec3(.6*y);if(v.y-w<0)return vec4(i,z);return vec4(i,v.y-abs(h((v.xz+vec2(50.,-30.))/2,1.1,4))*2);}vec4 T(vec3 i){vec4 y=u(i),f=l(i,w),r=vec4(0.,0.,0.,z);for(int e=0;e<v;e++){vec4 m=iLeipae[e],g=d(p(iTime-m.z)*n(iTime-m.x)*(i-m.xyz)*m.w)/m.w;r=F(r,g);if(r.w<x)break;}return F(F(y,f),r);}vec3 C(vec3 v){float i=T(vec3(v.x+x,v.y,v.z)).w-T(vec3(v.x-x,v.y,v.z)).w,f=T(vec3(v.x,v.y+x,v.z)).w-T(vec3(v.x,v.y-x,v.z)).w,z=T(vec3(v.x,v.y,v.z+x)).w-T(vec3(v.x,v.y,v.z-x)).w;return normalize(vec3(i,f,z));}mat4 C(vec3 v,vec3 i,vec3 x){vec3 f=normalize(i-v),z=normalize(cross(x,f)),r=cross(f,z);return mat4(vec4(z,0.),vec4(r,0.),vec4(-f,0.),vec4(0.,0.,0.,1.));}vec3 F(float v,vec2 i,vec2 y){vec2 x=y-i/2.;float z=i.y/tan(radians(v)/2.);return normalize(vec3(x,-z));}vec4 C(vec3 v,vec3 i,float x,float y){float f=(100.-v.y)/i.y;if(f>0.)y=min(y,f);float z=0.,r=0.,e=x;vec3 c=vec3(1.);for(int w=0;w<m;w++){z=.001*e;vec3 g=v+e*i;vec4 s=T(g);float a=s.w;if(a<z){c=s.xyz;break;}e+=a*.5;if(e>=y)break;}if(e>=y)return vec4(c,-1.);return vec4(c,e);}float T(vec3 v,vec3 i,float f){float r=1.;for(float y=1.;y<z;){float m=T(i+y*v).w;if(m<x)return 0.;r=min(r,f*m/y);y+=m;}return r;}vec3 F(vec3 v,vec3 i,vec3 x,vec3 z){vec3 f=C(i);float y=dot(f,v);vec3 r=vec3(0.);if(y>0)r=clamp(c*y*T(v,i,24.),0.,1.);vec3 m=clamp(c*(.5+.5*f.y)*(.1*s),0.,1.);float e=dot(f,-v);vec3 g=vec3(0.);if(e>0)g=clamp(c*e*(.4*c),0.,1.);return clamp(z*(r+m+g),0.,1.);}vec3 C(vec3 v,float i){vec3 x=exp2(-i*.01*g);return v*x+(1.-x)*k;}vec3 a(vec3 v,vec3 i,vec3 x){vec3 f=s-.5*i.y;if(iTime>75.){float z=5000/i.y;if(z>0.&&z<100000){vec3 y=v+z*i;float r=smoothstep(.7,1.,h(vec2(-6.+y.x*.015,2.+y.z*.003),.6,4));f=mix(f,vec3(1.),r);}}float z=(2500-v.y)/i.y;if(z>0.&&z<100000){vec3 y=v+z*i;float r=smoothstep(-.1,.8,h(2e-4*y.xz+vec2(-3.,2.),.9,8));f=mix(f,vec3(1.),.4*r);}vec3 y=exp2(-abs(z)*1e-5*g);f=f*y+(1.-y)*k;float r=dot(x,i);if(r>.996){float m=i.y-x.y;if(m>-.02)f=vec3(1,1,.5);else if(m<-.025&&m>-.03)f=vec3(1,.75,.6);else if(m<-.035&&m>-.04)f=vec3(1,.65,.7);else if(m<-.045&&m>-.05)f=vec3(1,.5,.82);else if(m<-.055&&m>-.06)f=vec3(1,.5,.82);else if(m<-.065&&m>-.07)f=vec3(1,.5,.92);else if(m<-.075&&m>-.08)f=vec3(1,.5,1.);else if(m<-.085&&m>-.09)f=vec3(1,.5,1.);}if(r>.995)f=mix(f,vec3(1.,1.,.5),(r-.995)/(1-.995));return f;}void main(){vec3 v=F(r,iResolution,gl_FragCoord.xy),x=iCamera,m=iTarget;mat4 c=C(x,m,vec3(0.,1.,0.));vec3 e=(c*vec4(v,0.)).xyz;vec4 s=C(x,e,f,z);float g=s.w;vec3 w=normalize(vec3(-97.+100.*cos(y-.2),100.*sin(y-.2),-100.));if(g<0.)FragColor=vec4(a(x,e,w),1.);else{vec3 n=s.xyz,k=x+g*e,d=F(w,k,x,n);d=C(d,g);d=pow(d,vec3(1.,.92,1.));d*=vec3(1.02,.99,.9);d.z=d.z+.1;d=smoothstep(0.,1.,d);FragColor=vec4(d,1.);}if(iTime>i)FragColor=mix(FragColor,vec4(0.),(iTime-i)/5.);else if(iTime<2.)FragColor=mix(FragColor,vec4(0.),(2.-iTime)/2.);}
\ No newline at end of file
+#ifdef GBUFFER_PASS
+layout(location=0)out vec4 gPosition;layout(location=1)out vec4 gNormal;layout(location=2)out vec4 gMaterial;
+#else
+out vec4 FragColor;
+#endif
+const float i=100.;uniform float iTime;uniform vec2 iResolution;uniform vec3 iCamera,iTarget;layout(std140)uniform Leipae{vec4 iLeipae[256];};uniform int iLeipaeCount;uniform vec4 iRipples[8];uniform vec2 iJitter;uniform int iDebugMode;uniform sampler2D iChannel0,iChannel1,iChannel2,iChannel3;uniform vec3 iChannelResolution[4];uniform sampler2D iHeightmap;uniform vec3 iHeightmapBounds;
+#ifdef LIGHTING_PASS
+uniform sampler2D iGPosition,iGNormal,iGMaterial;
+#endif
+struct U{vec3 v;float i;vec3 x;float f;};layout(std140)uniform Materials{U iMaterials[5];};
+#define S ((i-iTime)/i)
+const int m=MAX_MARCHING_STEPS;const float f=0.,z=MAX_DIST,r=60.,x=1e-5,e=3.14159265,w=.3;const vec3 c=vec3(.8,1.,1.),s=vec3(.06,.03,.69),k=vec3(.94,.12,.58),g=vec3(2.5,1.5,1.);mat3 n(float v){float i=sin(v),x=cos(v);return mat3(vec3(1,0,0),vec3(0,x,-i),vec3(0,i,x));}mat3 t(float v){float i=sin(v),x=cos(v);return mat3(vec3(x,0,i),vec3(0,1,0),vec3(-i,0,x));}mat3 p(float v){float i=sin(v),x=cos(v);return mat3(vec3(x,-i,0),vec3(i,x,0),vec3(0,0,1));}float n(vec3 v,vec3 i){vec3 x=abs(v)-i;return length(max(x,0.))+min(max(x.x,max(x.y,x.z)),0.);}float p(vec3 v,float i){return length(v)-i;}float t(vec3 v,vec3 i){float x=length(v/i),f=length(v/(i*i));return x*(x-1.)/f;}float h(vec3 v,vec2 i){vec3 x=abs(v);return max(x.z-i.y,max(x.x*.866025+v.y*.5,-v.y)-i.x*.5);}float h(vec2 v,float i,float x,float z){v.x=abs(v.x);float y=sin(i),f=cos(i);return(f*v.x>y*v.y?length(v-vec2(y,f)*x):abs(length(v)-x))-z;}float h(vec2 v){vec2 i=50.*fract(v/e);return 2.*fract(i.x*i.y*(i.x+i.y))-1.;}float l(vec2 v){vec2 i=floor(v),x=fract(v),f=x*x*(3.-2.*x);float y=h(i+vec2(0,0)),m=h(i+vec2(1,0)),z=h(i+vec2(0,1)),w=h(i+vec2(1,1)),r=y,c=m-y,g=z-y,e=y-m-z+w;return r+c*f.x+g*f.y+e*f.x*f.y;}float h(vec2 v,float i,int x){float m=exp2(-i),f=1.,z=.5,r=0.;for(int e=0;e<x;e++)r+=z*l(f*v),f*=1.9,z*=m;return r;}float F(vec2 v){return fract(sin(dot(v,vec2(12.9898,78.233)))*43758.5453);}float a(vec2 v){vec2 i=floor(v),f=fract(v);f=f*f*(3.-2.*f);float x=mix(mix(F(i),F(i+vec2(1.,0.)),f.x),mix(F(i+vec2(0.,1.)),F(i+vec2(1.,1.)),f.x),f.y);return x*x;}vec4 F(vec4 v,vec4 i){if(v.w<i.w)return v;return i;}vec3 a(vec3 v,float i){float x=cos(i*v.x),f=sin(i*v.x);mat2 z=mat2(x,-f,f,x);return vec3(z*v.xy,v.z);}vec4 l(vec3 v,float i){float x=1.;vec3 f=fract(v);if(f.x<.03)x=2.;else if(f.z<=.03)x=3.;return vec4(x,0.,0.,v.y-i);}vec4 o(vec3 v){if(p(v,10.)>0.)return vec4(4.,0.,0.,p(v,6.));float i=a(v.xz*5.),f=a(v.xz*30.),x=a(v.xz*50.),m=t(a(v,-.03),vec3(5.,1.,1.5))-.25+.05*i+.01*f+.005*x,r=m;for(int z=-3;z<=3;z++){float y=1.9;if(z==-2||z==2)y=1.7;else if(z==-3||z==3)y=1.4;float e=h(p(1.)*t(.3)*vec3(v.x+float(z)*1.1,v.y-y,v.z),vec2(1.,2.))+.03*a(v.xz*10.);r=max(r,-e);}return vec4(4.,i*.5+f*.5,0.,r);}vec4 d(vec3 v){if(p(v,5.)>0.)return vec4(4.,0.,0.,p(v,2.5));float i=a(v.xz*5.),f=a(v.xz*30.),x=a(v.xz*50.),m=t(a(v,-.08),vec3(2.2,.8,2.1))-.25+.05*i+.01*f+.005*x,r=m;for(int z=-1;z<=1;z++){float y=h(p(.9)*t(.3)*vec3(v.x+float(z)*1.1,v.y-1.55,v.z),vec2(1.,6.))+.03*a(v.xz*10.);r=max(r,-y);}for(int z=-1;z<=1;z++){float y=h(p(1.1)*t(-.8)*vec3(v.x+float(z)*1.1,v.y-1.55,v.z),vec2(1.,6.))+.03*a(v.xz*10.);r=max(r,-y);}return vec4(4.,i*.5+f*.5,0.,r);}float Q(vec2 v){return abs(h((v+vec2(50.,-30.))/2.,1.1,4))*2.;}
+#ifndef NO_HEIGHTMAP
+float R(vec2 v){ivec2 i=ivec2(floor(v));vec2 f=fract(v);return mix(mix(texelFetch(iHeightmap,i,0).r,texelFetch(iHeightmap,i+ivec2(1,0),0).r,f.x),mix(texelFetch(iHeightmap,i+ivec2(0,1),0).r,texelFetch(iHeightmap,i+ivec2(1,1),0).r,f.x),f.y);}
+#endif
+vec4 u(vec3 v){vec3 i=vec3(0.);if(v.y-w<0.)return vec4(i,z);
+#ifndef NO_HEIGHTMAP
+float f=smoothstep(10.,15.,distance(v.xz,iCamera.xz));vec2 x=(v.xz-iHeightmapBounds.xy)/iHeightmapBounds.z;if(f>0.&&all(greaterThanEqual(x,vec2(0)))&&all(lessThan(x,vec2(textureSize(iHeightmap,0)-1)))){float m=R(x);if(f<1.)m=mix(Q(v.xz),m,f);return vec4(i,v.y-m);}
+#endif
+return vec4(i,v.y-Q(v.xz));}vec4 T(vec3 i){vec4 y=u(i),f=l(i,w),r=vec4(4.,0.,0.,z);
+#ifndef NO_LEIPAE
+for(int e=0;e<iLeipaeCount;e++){vec4 m=iLeipae[e];if(distance(i,m.xyz)-2.5/m.w>=r.w)continue;vec4 g=d(p(iTime-m.z)*n(iTime-m.x)*(i-m.xyz)*m.w);g.w/=m.w;r=F(r,g);if(r.w<x)break;}
+#endif
+return F(F(y,f),r);}vec3 C(vec3 v){
+#ifdef NO_NORMALS
+return vec3(0.,1.,0.);
+#endif
+float i=T(vec3(v.x+x,v.y,v.z)).w-T(vec3(v.x-x,v.y,v.z)).w,f=T(vec3(v.x,v.y+x,v.z)).w-T(vec3(v.x,v.y-x,v.z)).w,z=T(vec3(v.x,v.y,v.z+x)).w-T(vec3(v.x,v.y,v.z-x)).w;return normalize(vec3(i,f,z));}float G(vec2 v){float i=0.;for(int x=0;x<8;x++){vec4 f=iRipples[x];float z=iTime-f.z;if(f.w<=0.||z<0.||z>6.)continue;float r=distance(v,f.xy)-z*1.5;i+=sin(r*8.)*exp(-r*r*2.)*(1.-z/6.)*.03*f.w;}return i;}vec3 H(vec2 v){vec2 i=vec2(.01,0.);return normalize(vec3(G(v-i)-G(v+i),.02,G(v-i.yx)-G(v+i.yx)));}mat4 C(vec3 v,vec3 i,vec3 x){vec3 f=normalize(i-v),z=normalize(cross(x,f)),r=cross(f,z);return mat4(vec4(z,0.),vec4(r,0.),vec4(-f,0.),vec4(0.,0.,0.,1.));}vec3 F(float v,vec2 i,vec2 y){vec2 x=y-i/2.;float z=i.y/tan(radians(v)/2.);return normalize(vec3(x,-z));}int j=0;vec4 C(vec3 v,vec3 i,float x,float y){float f=(100.-v.y)/i.y;if(f>0.)y=min(y,f);float z=0.,r=0.,e=x;vec3 c=vec3(1.);for(int w=0;w<m;w++){j=w+1;z=.001*e;vec3 g=v+e*i;vec4 s=T(g);float a=s.w;if(a<z){c=s.xyz;break;}e+=a*.5;if(e>=y)break;}if(e>=y)return vec4(c,-1.);return vec4(c,e);}float T(vec3 v,vec3 i,float f){
+#ifdef NO_SOFT_SHADOWS
+return 1.;
+#endif
+float r=1.,y=1.;for(int e=0;e<SHADOW_STEPS&&y<z;e++){float m=T(i+y*v).w;if(m<x)return 0.;r=min(r,f*m/y);y+=m;}return r;}vec3 B(vec3 v){int x=int(v.x);vec3 f=iMaterials[x].v+v.y;if(x==0)f*=S;return f;}vec3 E(vec3 v,vec3 x){return int(x.x)==1?H(v.xz):C(v);}vec3 F(vec3 v,vec3 P,vec3 f,vec3 x,vec3 z){U W=iMaterials[int(z.x)];vec3 X=B(z);float y=dot(f,v);vec3 r=vec3(0.),Y=vec3(0.);if(y>0.){float Z=T(v,P,SHADOW_SHARPNESS);r=clamp(c*y*Z,0.,1.);Y=c*W.f*pow(max(dot(f,normalize(v+normalize(x-P))),0.),exp2(10.*(1.-W.i)))*Z;}vec3 m=clamp(c*(.5+.5*f.y)*(.1*s),0.,1.);float e=dot(f,-v);vec3 g=vec3(0.);if(e>0.)g=clamp(c*e*(.4*c),0.,1.);return clamp(X*(r+m+g)+Y,0.,1.)+W.x;}vec3 C(vec3 v,float i){vec3 x=exp2(-i*.01*g);return v*x+(1.-x)*k;}vec3 a(vec3 v,vec3 i,vec3 x){
+#ifdef NO_SKY
+return s;
+#endif
+vec3 f=s-.5*i.y;if(iTime>75.){float z=5e3/i.y;if(z>0.&&z<1e5){vec3 y=v+z*i;float r=smoothstep(.7,1.,h(vec2(-6.+y.x*.015,2.+y.z*.003),.6,STAR_OCTAVES));f=mix(f,vec3(1.),r);}}float z=(2500.-v.y)/i.y;if(z>0.&&z<1e5){vec3 y=v+z*i;float r=smoothstep(-.1,.8,h(2e-4*y.xz+vec2(-3.,2.),.9,CLOUD_OCTAVES));f=mix(f,vec3(1.),.4*r);}vec3 y=exp2(-abs(z)*1e-5*g);f=f*y+(1.-y)*k;float r=dot(x,i);if(r>.996){float m=i.y-x.y;if(m>-.02)f=vec3(1,1,.5);else if(m<-.025&&m>-.03)f=vec3(1,.75,.6);else if(m<-.035&&m>-.04)f=vec3(1,.65,.7);else if(m<-.045&&m>-.05)f=vec3(1,.5,.82);else if(m<-.055&&m>-.06)f=vec3(1,.5,.82);else if(m<-.065&&m>-.07)f=vec3(1,.5,.92);else if(m<-.075&&m>-.08)f=vec3(1,.5,1.);else if(m<-.085&&m>-.09)f=vec3(1,.5,1.);}if(r>.995)f=mix(f,vec3(1.,1.,.5),(r-.995)/(1.-.995));return f;}vec3 D(vec3 v,vec3 i,vec3 x,vec3 f){x=reflect(x,f);x.y=abs(x.y);i+=.01*f;vec4 z=C(i,x,0.,MAX_DIST);if(z.w<0.)return a(i,x,v);vec3 P=i+z.w*x;return C(F(v,P,E(P,z.xyz),i,z.xyz),z.w);}vec3 A(int v,vec3 i,vec3 P,vec3 b,vec4 z,int y){if(v==1)return clamp(3.*float(y)/float(m)-vec3(0,1,2),0.,1.);float r=z.w;if(r<0.)return vec3(0.);if(v==2)return vec3(1.-r/MAX_DIST);if(v==3)return .5+.5*b;if(v==4)return B(z.xyz);if(v==5)return vec3(dot(b,i)>0.?T(i,P,SHADOW_SHARPNESS):0.);return 1.-exp2(-r*.01*g);}vec4 I(vec3 e,vec3 k,vec3 b,vec4 s,int y){vec3 w=normalize(vec3(-97.+100.*cos(S-.2),100.*sin(S-.2),-100.));float g=s.w;if(iDebugMode!=0)return vec4(A(iDebugMode,w,k,b,s,y),g);if(g<0.)return vec4(a(iCamera,e,w),-1.);vec3 n=s.xyz,d=F(w,k,b,iCamera,n);
+#ifndef NO_REFLECTIONS
+if(int(n.x)==1)d=mix(d,D(w,k,e,b),(.02+.98*pow(1.-max(dot(b,-e),0.),5.))*(1.-iMaterials[1].i));
+#endif
+return vec4(C(d,g),g);}void main(){vec3 v=F(r,iResolution,gl_FragCoord.xy+iJitter),e=(C(iCamera,iTarget,vec3(0.,1.,0.))*vec4(v,0.)).xyz;
+#ifdef LIGHTING_PASS
+ivec2 x=ivec2(gl_FragCoord.xy);vec4 k=texelFetch(iGPosition,x,0),b=texelFetch(iGNormal,x,0);FragColor=I(e,k.xyz,b.xyz,vec4(texelFetch(iGMaterial,x,0).xyz,k.w),int(b.w));
+#else
+vec4 s=C(iCamera,e,f,z);vec3 k=iCamera+s.w*e,b=s.w<0.?vec3(0.):E(k,s.xyz);
+#ifdef GBUFFER_PASS
+gPosition=vec4(k,s.w);gNormal=vec4(b,float(j));gMaterial=vec4(s.xyz,0.);
+#else
+FragColor=I(e,k,b,s,j);
+#endif
+#endif
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float PI = 3.14159265;
+const float THRESHOLD = 0.75;
+const float INTENSITY = 0.5;
+const int RINGS = 4;
+const int SAMPLES = 12;
+
+uniform sampler2D iChannel0;
+uniform vec2 iResolution;
+uniform float iTime;
+
+vec3 bright(vec2 uv) {
+    vec3 color = texture(iChannel0, uv).rgb;
+    return color * smoothstep(THRESHOLD, 1.0, max(color.r, max(color.g, color.b)));
+}
+
+// Cheap single pass bloom, gathers the bright parts of the image from rings
+// around the pixel. Radius is relative to the height so that the glow looks
+// the same at every internal resolution.
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution;
+    vec4 scene = texture(iChannel0, uv);
+
+    vec2 texel = vec2(iResolution.y / iResolution.x, 1.0) / 1080.0;
+
+    vec3 bloom = vec3(0.0);
+    float total = 0.0;
+    for (int ring = 1; ring <= RINGS; ring++) {
+        float radius = 8.0 * float(ring);
+        float weight = 1.0 / float(ring);
+
+        for (int i = 0; i < SAMPLES; i++) {
+            float angle = 2.0 * PI * (float(i) + 0.5 * float(ring)) / float(SAMPLES);
+            vec2 offset = vec2(cos(angle), sin(angle)) * radius * texel;
+
+            bloom += bright(uv + offset) * weight;
+            total += weight;
+        }
+    }
+
+    FragColor = vec4(scene.rgb + INTENSITY * bloom / total, scene.a);
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float STRENGTH = 0.006;
+
+uniform sampler2D iChannel0;
+uniform vec2 iResolution;
+uniform float iTime;
+
+// Splits red and blue channels radially, stronger towards the edges
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution;
+    vec2 offset = (uv - 0.5) * STRENGTH;
+
+    vec4 scene = texture(iChannel0, uv);
+    float r = texture(iChannel0, uv + offset).r;
+    float b = texture(iChannel0, uv - offset).b;
+
+    FragColor = vec4(r, scene.g, b, scene.a);
+}
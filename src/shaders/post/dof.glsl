@@ -0,0 +1,84 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float PI = 3.14159265;
+const float GOLDEN_ANGLE = 2.39996323;
+const int SAMPLES = 64;
+// Limit for the blur radius relative to the height, beyond it the samples
+// spread too thin
+const float MAX_BLUR = 0.04;
+// Depth of the sky, blurred like anything at infinity
+const float FAR = 10000.0;
+
+uniform sampler2D iChannel0;
+// Hit position and depth, negative for the sky, see gbuffer.rs
+uniform sampler2D iGPosition;
+uniform vec2 iResolution;
+uniform float iTime;
+// See `Lens` in focus.rs
+uniform float iFocusDistance;
+uniform float iAperture;
+// Straight edges of the aperture, 0 for a round one
+uniform int iBokehBlades;
+
+float depthAt(vec2 fragCoord) {
+    ivec2 texel = clamp(ivec2(fragCoord), ivec2(0), ivec2(iResolution) - 1);
+    float depth = texelFetch(iGPosition, texel, 0).w;
+    return depth < 0.0 ? FAR : depth;
+}
+
+// Radius of the circle of confusion in pixels, at most `limit`
+float blurRadius(float depth, float limit) {
+    return min(iAperture * iResolution.y * abs(depth - iFocusDistance) / depth, limit);
+}
+
+// Distance from the centre to the edge of the aperture in the direction of
+// `angle`, relative to the corners
+float apertureEdge(float angle) {
+    if (iBokehBlades < 3) {
+        return 1.0;
+    }
+
+    float blade = 2.0 * PI / float(iBokehBlades);
+    return cos(blade / 2.0) / cos(mod(angle, blade) - blade / 2.0);
+}
+
+// Gathers the samples whose blur reaches this pixel from a disc shaped like
+// the aperture, so out of focus highlights take its shape. Sharp surfaces in
+// front of a blurred background stay sharp, blurred ones in front of a sharp
+// background spill over it.
+void main() {
+    vec4 scene = texture(iChannel0, gl_FragCoord.xy / iResolution);
+
+    if (iAperture <= 0.0) {
+        FragColor = scene;
+        return;
+    }
+
+    // Twice the blur at infinity covers everything further than a third of
+    // the focus distance
+    float gather = min(2.0 * iAperture, MAX_BLUR) * iResolution.y;
+    float depth = depthAt(gl_FragCoord.xy);
+    float radius = blurRadius(depth, gather);
+
+    vec3 color = scene.rgb;
+    float total = 1.0;
+    for (int i = 0; i < SAMPLES; i++) {
+        float angle = float(i) * GOLDEN_ANGLE;
+        float r = sqrt((float(i) + 0.5) / float(SAMPLES)) * gather;
+        vec2 p = gl_FragCoord.xy + vec2(cos(angle), sin(angle)) * r * apertureEdge(angle);
+
+        float sampleDepth = depthAt(p);
+        float sampleRadius = blurRadius(sampleDepth, gather);
+        if (sampleDepth > depth) {
+            sampleRadius = min(sampleRadius, radius);
+        }
+
+        float weight = clamp(sampleRadius - r + 1.0, 0.0, 1.0);
+        color += texture(iChannel0, p / iResolution).rgb * weight;
+        total += weight;
+    }
+
+    FragColor = vec4(color / total, scene.a);
+}
@@ -0,0 +1,33 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float TOTAL_DURATION = 100.0;
+
+uniform sampler2D iChannel0;
+uniform vec2 iResolution;
+uniform float iTime;
+
+void main() {
+    vec4 scene = texture(iChannel0, gl_FragCoord.xy / iResolution);
+    vec3 color = scene.rgb;
+
+    // Alpha holds the depth and is negative for the sky, which is left ungraded
+    if (scene.a >= 0.0) {
+        color = pow(color, vec3(1.0, 0.92, 1.0));
+        color *= vec3(1.02, 0.99, 0.9);
+        color.z = color.z + 0.1;
+
+        color = smoothstep(0.0, 1.0, color);
+    }
+
+    if (iTime > TOTAL_DURATION) {
+        // Fade to black at the end
+        color = mix(color, vec3(0.0), (iTime - TOTAL_DURATION) / 5.0);
+    } else if (iTime < 2.0) {
+        // Fade in at the beginning
+        color = mix(color, vec3(0.0), (2.0 - iTime) / 2.0);
+    }
+
+    FragColor = vec4(color, scene.a);
+}
@@ -0,0 +1,23 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float STRENGTH = 0.05;
+
+uniform sampler2D iChannel0;
+uniform vec2 iResolution;
+uniform float iTime;
+
+// https://stackoverflow.com/a/4275343
+float rand(vec2 co) {
+    return fract(sin(dot(co, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution;
+    vec4 scene = texture(iChannel0, uv);
+
+    float grain = rand(uv + fract(iTime * 0.37)) - 0.5;
+
+    FragColor = vec4(scene.rgb + grain * STRENGTH, scene.a);
+}
@@ -0,0 +1,66 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float FOV = 60.0;
+const float GOLDEN_ANGLE = 2.39996323;
+const int SAMPLES = 16;
+// Surfaces closer than this in world units occlude, further ones fade out
+const float RADIUS = 0.5;
+const float STRENGTH = 1.5;
+// Keeps a flat surface from occluding itself
+const float BIAS = 0.05;
+
+uniform sampler2D iChannel0;
+// Hit position and depth, negative for the sky, and the normal, see gbuffer.rs
+uniform sampler2D iGPosition;
+uniform sampler2D iGNormal;
+uniform vec2 iResolution;
+uniform float iTime;
+
+// Per pixel rotation of the samples, the noise is averaged out by the
+// temporal filter and the film grain
+float interleavedGradientNoise(vec2 p) {
+    return fract(52.9829189 * fract(dot(p, vec2(0.06711056, 0.00583715))));
+}
+
+// Darkens the creases and where the leipae meet the ground by how much of
+// the hemisphere above each surface the nearby G-buffer positions cover
+void main() {
+    vec4 scene = texture(iChannel0, gl_FragCoord.xy / iResolution);
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec4 position = texelFetch(iGPosition, texel, 0);
+
+    if (position.w < 0.0) {
+        FragColor = scene;
+        return;
+    }
+
+    vec3 n = texelFetch(iGNormal, texel, 0).xyz;
+
+    // The radius projected to pixels, see rayDirection in fragment.glsl
+    float focal = iResolution.y / tan(radians(FOV) / 2.0);
+    float pixels = min(RADIUS * focal / position.w, 0.1 * iResolution.y);
+    float rotation = 2.0 * 3.14159265 * interleavedGradientNoise(gl_FragCoord.xy);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < SAMPLES; i++) {
+        // A spiral filling the disc evenly
+        float angle = rotation + float(i) * GOLDEN_ANGLE;
+        float r = sqrt((float(i) + 0.5) / float(SAMPLES)) * pixels;
+        ivec2 offset = ivec2(vec2(cos(angle), sin(angle)) * r);
+        ivec2 sampleTexel = clamp(texel + offset, ivec2(0), ivec2(iResolution) - 1);
+
+        vec4 q = texelFetch(iGPosition, sampleTexel, 0);
+        if (q.w < 0.0) {
+            continue;
+        }
+
+        vec3 v = q.xyz - position.xyz;
+        float d = max(length(v), 0.0001);
+        occlusion += max(dot(n, v) / d - BIAS, 0.0) * (1.0 - smoothstep(RADIUS, 2.0 * RADIUS, d));
+    }
+
+    float ambient = clamp(1.0 - STRENGTH * occlusion / float(SAMPLES), 0.0, 1.0);
+    FragColor = vec4(scene.rgb * ambient, scene.a);
+}
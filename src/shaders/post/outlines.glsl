@@ -0,0 +1,55 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const vec3 OUTLINE_COLOR = vec3(0.02, 0.0, 0.08);
+// Depth of the sky, for the silhouettes against it
+const float FAR = 10000.0;
+// Depth difference relative to the depth where a silhouette starts
+const float DEPTH_THRESHOLD = 0.04;
+// Cosine of the angle between neighbouring normals where a crease starts
+const float NORMAL_THRESHOLD = 0.6;
+// Creases fade out by this depth, further away the normals of the terrain
+// change from pixel to pixel
+const float CREASE_DISTANCE = 15.0;
+
+uniform sampler2D iChannel0;
+// Hit position and depth, negative for the sky, and the normal, see gbuffer.rs
+uniform sampler2D iGPosition;
+uniform sampler2D iGNormal;
+uniform vec2 iResolution;
+uniform float iTime;
+
+float depthAt(ivec2 texel) {
+    float depth = texelFetch(iGPosition, texel, 0).w;
+    return depth < 0.0 ? FAR : depth;
+}
+
+// Cartoon lines where the depth jumps at the silhouettes or the normal turns
+// sharply at the creases, compared with the four neighbouring pixels
+void main() {
+    vec4 scene = texture(iChannel0, gl_FragCoord.xy / iResolution);
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    ivec2 last = ivec2(iResolution) - 1;
+
+    float depth = depthAt(texel);
+    vec3 n = texelFetch(iGNormal, texel, 0).xyz;
+    float crease = depth < FAR ? 1.0 - smoothstep(0.5 * CREASE_DISTANCE, CREASE_DISTANCE, depth) : 0.0;
+
+    float edge = 0.0;
+    ivec2 offsets[4] = ivec2[](ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1));
+    for (int i = 0; i < 4; i++) {
+        ivec2 neighbour = clamp(texel + offsets[i], ivec2(0), last);
+        float neighbourDepth = depthAt(neighbour);
+
+        // Only the nearer side of a silhouette, the lines stay a pixel wide
+        edge = max(edge, smoothstep(DEPTH_THRESHOLD, 2.0 * DEPTH_THRESHOLD, (neighbourDepth - depth) / depth));
+
+        if (neighbourDepth < FAR) {
+            vec3 m = texelFetch(iGNormal, neighbour, 0).xyz;
+            edge = max(edge, crease * (1.0 - smoothstep(NORMAL_THRESHOLD - 0.1, NORMAL_THRESHOLD, dot(n, m))));
+        }
+    }
+
+    FragColor = vec4(mix(scene.rgb, OUTLINE_COLOR, edge), scene.a);
+}
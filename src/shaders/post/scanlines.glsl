@@ -0,0 +1,28 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float PI = 3.14159265;
+const float LINES = 270.0;
+
+uniform sampler2D iChannel0;
+uniform vec2 iResolution;
+uniform float iTime;
+
+// CRT style scanlines with a slowly rolling brighter band and vignette.
+// The line count is fixed so that it doesn't depend on the resolution.
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution;
+    vec4 scene = texture(iChannel0, uv);
+
+    float scanline = 0.5 + 0.5 * sin(uv.y * LINES * 2.0 * PI);
+    float band = smoothstep(0.9, 1.0, sin((uv.y + iTime * 0.1) * 2.0 * PI));
+
+    vec2 centered = uv - 0.5;
+    float vignette = 1.0 - dot(centered, centered) * 0.8;
+
+    vec3 color = scene.rgb * mix(1.0, scanline, 0.3) * vignette;
+    color += 0.04 * band;
+
+    FragColor = vec4(color, scene.a);
+}
@@ -0,0 +1,130 @@
+#version 330 core
+
+out vec4 FragColor;
+
+const float FOV = 60.0;
+const vec3 UP = vec3(0.0, 1.0, 0.0);
+const int MOTION_BLUR_SAMPLES = 12;
+// Limit for the blur length, relative to the height
+const float MAX_VELOCITY = 0.05;
+
+// Current frame, depth in alpha
+uniform sampler2D iChannel0;
+// Resolved previous frame
+uniform sampler2D iChannel1;
+
+uniform vec2 iResolution;
+uniform vec3 iCamera;
+uniform vec3 iTarget;
+uniform vec3 iPreviousCamera;
+uniform vec3 iPreviousTarget;
+
+// Weight of the history in the resolve, zero when the history isn't valid
+uniform float iHistoryWeight;
+// Fraction of the frame time the shutter is open for
+uniform float iMotionBlur;
+// 0 for the temporal resolve, 1 for the motion blur
+uniform int iPass;
+
+// Same camera as in the scene shader, without the homogeneous part
+mat3 lookAt(vec3 camera, vec3 target, vec3 up) {
+    vec3 f = normalize(target - camera);
+    vec3 s = normalize(cross(up, f));
+    vec3 u = cross(f, s);
+
+    return mat3(s, u, -f);
+}
+
+vec3 rayDirection(float fov, vec2 dimensions, vec2 fragCoord) {
+    vec2 xy = fragCoord - dimensions / 2.0;
+    float z = dimensions.y / tan(radians(fov) / 2.0);
+    return normalize(vec3(xy, -z));
+}
+
+// Inverse of rayDirection, from a view space direction back to pixel coordinates
+vec2 projectDirection(float fov, vec2 dimensions, vec3 dir) {
+    float z = dimensions.y / tan(radians(fov) / 2.0);
+    return dir.xy * (z / -dir.z) + dimensions / 2.0;
+}
+
+// Finds where the surface seen at `fragCoord` was on the screen on the
+// previous frame. Returns a negative position if it was behind the camera.
+vec2 reproject(vec2 fragCoord, float depth) {
+    vec3 worldDir = lookAt(iCamera, iTarget, UP) * rayDirection(FOV, iResolution, fragCoord);
+
+    // The sky is infinitely far away, so only the rotation of the camera matters
+    vec3 previousDir = worldDir;
+    if (depth >= 0.0) {
+        previousDir = normalize(iCamera + depth * worldDir - iPreviousCamera);
+    }
+
+    vec3 viewDir = transpose(lookAt(iPreviousCamera, iPreviousTarget, UP)) * previousDir;
+    if (viewDir.z >= 0.0) {
+        return vec2(-1.0);
+    }
+
+    return projectDirection(FOV, iResolution, viewDir);
+}
+
+bool onScreen(vec2 uv) {
+    return all(greaterThanEqual(uv, vec2(0.0))) && all(lessThanEqual(uv, vec2(1.0)));
+}
+
+vec4 resolve(vec2 uv) {
+    vec4 current = texture(iChannel0, uv);
+
+    // Clamp the history to the colours around the pixel on the current frame
+    // to reject stale history from disocclusions and moving leipae
+    vec3 minColor = current.rgb;
+    vec3 maxColor = current.rgb;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec3 color = texture(iChannel0, uv + vec2(x, y) / iResolution).rgb;
+            minColor = min(minColor, color);
+            maxColor = max(maxColor, color);
+        }
+    }
+
+    vec2 previousUv = reproject(gl_FragCoord.xy, current.a) / iResolution;
+    if (!onScreen(previousUv)) {
+        return current;
+    }
+
+    vec3 history = clamp(texture(iChannel1, previousUv).rgb, minColor, maxColor);
+
+    return vec4(mix(current.rgb, history, iHistoryWeight), current.a);
+}
+
+vec4 motionBlur(vec2 uv) {
+    vec4 current = texture(iChannel0, uv);
+
+    vec2 previous = reproject(gl_FragCoord.xy, current.a);
+    if (previous.x < 0.0) {
+        return current;
+    }
+
+    // Screen space movement since the previous frame in pixels
+    vec2 velocity = gl_FragCoord.xy - previous;
+    float maxLength = MAX_VELOCITY * iResolution.y;
+    if (length(velocity) > maxLength) {
+        velocity = normalize(velocity) * maxLength;
+    }
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < MOTION_BLUR_SAMPLES; i++) {
+        float t = float(i) / float(MOTION_BLUR_SAMPLES - 1) - 0.5;
+        color += texture(iChannel0, uv + velocity * t * iMotionBlur / iResolution).rgb;
+    }
+
+    return vec4(color / float(MOTION_BLUR_SAMPLES), current.a);
+}
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution;
+
+    if (iPass == 0) {
+        FragColor = resolve(uv);
+    } else {
+        FragColor = motionBlur(uv);
+    }
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 uv;
+in vec4 color;
+
+out vec4 FragColor;
+
+// Font atlas, coverage in the red channel
+uniform sampler2D iChannel0;
+
+void main() {
+    // Rectangles have no glyph to sample
+    float coverage = uv.x < 0.0 ? 1.0 : texture(iChannel0, uv).r;
+
+    FragColor = vec4(color.rgb, color.a * coverage);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec2 TexCoord;
+layout (location = 2) in vec4 Color;
+
+// Size of the target in pixels, positions are in pixels from the top left
+uniform vec2 iResolution;
+
+out vec2 uv;
+out vec4 color;
+
+void main()
+{
+    vec2 ndc = Position / iResolution * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+
+    uv = TexCoord;
+    color = Color;
+}
@@ -0,0 +1,389 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::demo::{self, Scene, GRAVITY, LEIPAE_BOTTOM, LEIPAE_COUNT, LEIPAE_TOP, SCENE_ORDER};
+use crate::material::Material;
+use crate::pack::Asset;
+use crate::texture::CHANNEL_COUNT;
+
+const FRAGMENT_SHADER: Asset = crate::asset!("shaders/fragment.glsl");
+const GRADE_SHADER: Asset = crate::asset!("shaders/post/grade.glsl");
+
+// The published version always places the leipae the same way
+pub const SHADERTOY_SEED: u64 = 2022;
+
+// Camera keyframes per second, linear interpolation between them is
+// indistinguishable from the closed form at the speeds the camera moves
+const KEYFRAME_RATE: f32 = 4.0;
+
+// Writes the whole intro as a single Shadertoy image shader. The scene is
+// the readable fragment shader with the uniforms the demo sets every frame
+// replaced by baked keyframes and leipae, followed by the grade pass.
+pub fn export(path: &Path) -> Result<(), Box<dyn Error>> {
+    fs::write(path, generate()?)?;
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+pub fn generate() -> Result<String, Box<dyn Error>> {
+    let mut src = String::new();
+
+    writeln!(
+        src,
+        "// Leipae - by Cadiac\n// Generated with `--shadertoy` from src/shaders/fragment.glsl and\n// src/shaders/post/grade.glsl, edit those instead.\n"
+    )?;
+
+    src.push_str("#define NO_HEIGHTMAP\n");
+    src.push_str(&scene_shader()?);
+    src.push_str(&timeline()?);
+    src.push_str(&materials()?);
+    src.push_str(&leipae()?);
+    src.push_str(&grade_shader()?);
+
+    src.push_str(
+        "
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    demoTime = mod(iTime, DEMO_DURATION);
+
+    cameraAt(demoTime, iCamera, iTarget);
+    materialsAt(demoTime);
+    for (int i = 0; i < LEIPAE_COUNT; i++) {
+        iLeipae[i] = leipaeAt(LEIPAE[i], demoTime);
+        iRipples[i] = rippleAt(LEIPAE[i], demoTime);
+    }
+
+    renderScene();
+    fragColor = vec4(grade(FragColor).rgb, 1.0);
+}
+",
+    );
+
+    Ok(src)
+}
+
+// Shadertoy declares `iTime` and a three component `iResolution` itself and
+// calls `mainImage` instead of `main`. Time is renamed so it can loop.
+fn scene_shader() -> Result<String, Box<dyn Error>> {
+    let mut src = strip_version(&FRAGMENT_SHADER.text())?;
+
+    src = replace(&src, "out vec4 FragColor;\n", "vec4 FragColor;\n")?;
+    src = replace(&src, "uniform float iTime;\n", "float iTime;\n")?;
+    src = replace(&src, "uniform vec2 iResolution;\n", "")?;
+    src = replace(&src, "uniform vec3 iCamera;\n", "vec3 iCamera;\n")?;
+    src = replace(&src, "uniform vec3 iTarget;\n", "vec3 iTarget;\n")?;
+    // A fixed number of leipae, every one drawn
+    src = replace(
+        &src,
+        "const int MAX_LEIPAE = 256;\n",
+        &format!("const int LEIPAE_COUNT = {};\n", LEIPAE_COUNT),
+    )?;
+    src = replace(
+        &src,
+        "layout(std140) uniform Leipae {\n    vec4 iLeipae[MAX_LEIPAE];\n};\nuniform int iLeipaeCount;\n",
+        "vec4 iLeipae[LEIPAE_COUNT];\nconst int iLeipaeCount = LEIPAE_COUNT;\n",
+    )?;
+    // Every leipae falls, so every one gets a ripple
+    src = replace(
+        &src,
+        "const int RIPPLE_COUNT = 8;\n",
+        "const int RIPPLE_COUNT = LEIPAE_COUNT;\n",
+    )?;
+    src = replace(
+        &src,
+        "uniform vec4 iRipples[RIPPLE_COUNT];\n",
+        "vec4 iRipples[RIPPLE_COUNT];\n",
+    )?;
+    src = replace(
+        &src,
+        "uniform vec2 iJitter;\n",
+        "const vec2 iJitter = vec2(0.0);\n",
+    )?;
+    src = replace(
+        &src,
+        "uniform int iDebugMode;\n",
+        "const int iDebugMode = 0;\n",
+    )?;
+    // Shadertoy declares the channels too
+    for channel in 0..CHANNEL_COUNT {
+        src = replace(
+            &src,
+            &format!("uniform sampler2D iChannel{};\n", channel),
+            "",
+        )?;
+    }
+    src = replace(&src, "uniform vec3 iChannelResolution[4];\n", "")?;
+    // There are no float textures to upload the baked terrain to
+    src = replace(&src, "uniform sampler2D iHeightmap;\n", "")?;
+    src = replace(&src, "uniform vec3 iHeightmapBounds;\n", "")?;
+    // Nor uniform blocks, the tables of every scene are baked
+    src = replace(
+        &src,
+        "layout(std140) uniform Materials {\n    Material iMaterials[MATERIAL_COUNT];\n};\n",
+        "Material iMaterials[MATERIAL_COUNT];\n",
+    )?;
+    src = replace(&src, "iResolution,", "iResolution.xy,")?;
+    src = replace(&src, "void main() {", "void renderScene() {")?;
+
+    Ok(src.replace("iTime", "demoTime"))
+}
+
+fn grade_shader() -> Result<String, Box<dyn Error>> {
+    let mut src = strip_version(&GRADE_SHADER.text())?;
+
+    src = replace(&src, "out vec4 FragColor;\n", "")?;
+    src = replace(&src, "const float TOTAL_DURATION = 100.0;\n", "")?;
+    src = replace(&src, "uniform sampler2D iChannel0;\n", "")?;
+    src = replace(&src, "uniform vec2 iResolution;\n", "")?;
+    src = replace(&src, "uniform float iTime;\n", "")?;
+    src = replace(
+        &src,
+        "void main() {\n    vec4 scene = texture(iChannel0, gl_FragCoord.xy / iResolution);\n",
+        "vec4 grade(vec4 scene) {\n",
+    )?;
+    src = replace(
+        &src,
+        "FragColor = vec4(color, scene.a);",
+        "return vec4(color, scene.a);",
+    )?;
+
+    Ok(src.trim_start().replace("iTime", "demoTime"))
+}
+
+// Camera and target keyframes of every scene, and a lookup by the time since
+// the beginning of the demo
+fn timeline() -> Result<String, Box<dyn Error>> {
+    let scenes: Vec<Scene> = SCENE_ORDER
+        .iter()
+        .copied()
+        .filter(|scene| scene.duration() > 0.0)
+        .collect();
+
+    let mut starts = Vec::with_capacity(scenes.len());
+    let mut first_keys = Vec::with_capacity(scenes.len() + 1);
+    let mut cameras = Vec::new();
+    let mut targets = Vec::new();
+    let mut start = 0.0;
+
+    for scene in scenes.iter() {
+        starts.push(float(start));
+        first_keys.push(cameras.len().to_string());
+
+        let duration = scene.duration();
+        let count = (duration * KEYFRAME_RATE).ceil() as usize + 1;
+        for i in 0..count {
+            let t = (i as f32 / KEYFRAME_RATE).min(duration);
+            cameras.push(vec3(scene.camera(t)));
+            targets.push(vec3(scene.target(t)));
+        }
+
+        start += duration;
+    }
+    first_keys.push(cameras.len().to_string());
+
+    let mut src = String::new();
+
+    writeln!(src, "\nconst float DEMO_DURATION = {};", float(start))?;
+    writeln!(src, "const float KEYFRAME_RATE = {};", float(KEYFRAME_RATE))?;
+    writeln!(src, "const int SCENE_COUNT = {};", scenes.len())?;
+    writeln!(
+        src,
+        "const float SCENE_STARTS[SCENE_COUNT] = float[]({});",
+        starts.join(", ")
+    )?;
+    writeln!(
+        src,
+        "// Index of the first keyframe of each scene, and the end"
+    )?;
+    writeln!(
+        src,
+        "const int SCENE_KEYS[SCENE_COUNT + 1] = int[]({});",
+        first_keys.join(", ")
+    )?;
+    writeln!(src, "const int KEYFRAME_COUNT = {};", cameras.len())?;
+    writeln!(
+        src,
+        "const vec3 CAMERA_KEYS[KEYFRAME_COUNT] = vec3[](\n    {});",
+        cameras.join(",\n    ")
+    )?;
+    writeln!(
+        src,
+        "const vec3 TARGET_KEYS[KEYFRAME_COUNT] = vec3[](\n    {});",
+        targets.join(",\n    ")
+    )?;
+
+    src.push_str(
+        "
+int sceneAt(float time) {
+    int scene = 0;
+    for (int i = 1; i < SCENE_COUNT; i++) {
+        if (time >= SCENE_STARTS[i]) {
+            scene = i;
+        }
+    }
+
+    return scene;
+}
+
+void cameraAt(float time, out vec3 camera, out vec3 target) {
+    int scene = sceneAt(time);
+
+    float keyframe = (time - SCENE_STARTS[scene]) * KEYFRAME_RATE;
+    int last = SCENE_KEYS[scene + 1] - SCENE_KEYS[scene] - 2;
+    int index = clamp(int(keyframe), 0, last);
+    float t = clamp(keyframe - float(index), 0.0, 1.0);
+
+    int key = SCENE_KEYS[scene] + index;
+    camera = mix(CAMERA_KEYS[key], CAMERA_KEYS[key + 1], t);
+    target = mix(TARGET_KEYS[key], TARGET_KEYS[key + 1], t);
+}
+",
+    );
+
+    Ok(src)
+}
+
+// Material tables of every scene, in the order of `timeline`
+fn materials() -> Result<String, Box<dyn Error>> {
+    let tables: Vec<String> = SCENE_ORDER
+        .iter()
+        .filter(|scene| scene.duration() > 0.0)
+        .flat_map(|scene| scene.materials())
+        .map(material)
+        .collect();
+
+    let mut src = String::new();
+
+    writeln!(
+        src,
+        "\nconst Material SCENE_MATERIALS[SCENE_COUNT * MATERIAL_COUNT] = Material[](\n    {});",
+        tables.join(",\n    ")
+    )?;
+
+    src.push_str(
+        "
+void materialsAt(float time) {
+    int first = sceneAt(time) * MATERIAL_COUNT;
+    for (int i = 0; i < MATERIAL_COUNT; i++) {
+        iMaterials[i] = SCENE_MATERIALS[first + i];
+    }
+}
+",
+    );
+
+    Ok(src)
+}
+
+// Starting positions of the leipae and the same falling motion as in demo.rs
+fn leipae() -> Result<String, Box<dyn Error>> {
+    let origins: Vec<String> = demo::leipae_origins(SHADERTOY_SEED)
+        .into_iter()
+        .map(vec4)
+        .collect();
+
+    let mut src = String::new();
+
+    writeln!(src, "\nconst float GRAVITY = {};", float(GRAVITY))?;
+    writeln!(src, "const float LEIPAE_TOP = {};", float(LEIPAE_TOP))?;
+    writeln!(src, "const float LEIPAE_BOTTOM = {};", float(LEIPAE_BOTTOM))?;
+    writeln!(src, "// Position at the start of the demo and scale")?;
+    writeln!(
+        src,
+        "const vec4 LEIPAE[LEIPAE_COUNT] = vec4[](\n    {});",
+        origins.join(",\n    ")
+    )?;
+
+    src.push_str(
+        "
+float fallTime(float height) {
+    return sqrt(2.0 * height / -GRAVITY);
+}
+
+vec4 leipaeAt(vec4 leipae, float time) {
+    float firstFall = fallTime(leipae.y - LEIPAE_BOTTOM);
+
+    float y;
+    if (time < firstFall) {
+        y = leipae.y + 0.5 * GRAVITY * time * time;
+    } else {
+        float t = mod(time - firstFall, fallTime(LEIPAE_TOP - LEIPAE_BOTTOM));
+        y = LEIPAE_TOP + 0.5 * GRAVITY * t * t;
+    }
+
+    return vec4(leipae.x, y, leipae.z, leipae.w);
+}
+
+// The last time the leipae fell through the water, no strength if it hasn't
+vec4 rippleAt(vec4 leipae, float time) {
+    float firstImpact = fallTime(leipae.y - WATER_LEVEL);
+    if (time < firstImpact) {
+        return vec4(0.0);
+    }
+
+    float firstFall = fallTime(leipae.y - LEIPAE_BOTTOM);
+    float impact = fallTime(LEIPAE_TOP - WATER_LEVEL);
+    float start = firstImpact;
+    if (time >= firstFall + impact) {
+        float period = fallTime(LEIPAE_TOP - LEIPAE_BOTTOM);
+        start = firstFall + floor((time - firstFall - impact) / period) * period + impact;
+    }
+
+    return vec4(leipae.x, leipae.z, start, 1.0 / leipae.w);
+}
+
+",
+    );
+
+    Ok(src)
+}
+
+fn strip_version(src: &str) -> Result<String, Box<dyn Error>> {
+    match src.split_once('\n') {
+        Some((first, rest)) if first.starts_with("#version") => Ok(rest.to_string()),
+        _ => Err("shader doesn't start with #version".into()),
+    }
+}
+
+// Fails instead of silently producing a broken shader when the sources change
+fn replace(src: &str, from: &str, to: &str) -> Result<String, Box<dyn Error>> {
+    if !src.contains(from) {
+        return Err(format!(
+            "shader source no longer contains {:?}, the Shadertoy exporter needs updating",
+            from
+        )
+        .into());
+    }
+
+    Ok(src.replace(from, to))
+}
+
+// GLSL float literals need the decimal point
+fn float(value: f32) -> String {
+    format!("{:?}", value)
+}
+
+fn vec3(v: [f32; 3]) -> String {
+    format!("vec3({}, {}, {})", float(v[0]), float(v[1]), float(v[2]))
+}
+
+fn material(m: Material) -> String {
+    format!(
+        "Material({}, {}, {}, {})",
+        vec3(m.albedo),
+        float(m.roughness),
+        vec3(m.emissive),
+        float(m.specular)
+    )
+}
+
+fn vec4(v: [f32; 4]) -> String {
+    format!(
+        "vec4({}, {}, {}, {})",
+        float(v[0]),
+        float(v[1]),
+        float(v[2]),
+        float(v[3])
+    )
+}
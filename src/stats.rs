@@ -0,0 +1,31 @@
+// Summary of a series of timings
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f32,
+    pub avg: f32,
+    pub p95: f32,
+    pub max: f32,
+}
+
+impl Stats {
+    // Returns none for an empty series
+    pub fn new(mut values: Vec<f32>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_by(f32::total_cmp);
+
+        let count = values.len();
+        let p95 = ((count as f32 * 0.95).ceil() as usize).clamp(1, count) - 1;
+
+        Some(Self {
+            count,
+            min: values[0],
+            avg: values.iter().sum::<f32>() / count as f32,
+            p95: values[p95],
+            max: values[count - 1],
+        })
+    }
+}
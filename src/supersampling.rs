@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+// Reconstruction filter used to combine the supersamples of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFilter {
+    Box,
+    Tent,
+    Gaussian,
+    // Mitchell-Netravali with B = C = 1/3
+    Mitchell,
+    // Lanczos with a = 2
+    Lanczos,
+}
+
+impl SampleFilter {
+    // Half width of the filter in pixels
+    fn radius(self) -> f32 {
+        match self {
+            SampleFilter::Box => 0.5,
+            SampleFilter::Tent => 1.0,
+            SampleFilter::Gaussian => 1.5,
+            SampleFilter::Mitchell => 2.0,
+            SampleFilter::Lanczos => 2.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+
+        match self {
+            SampleFilter::Box => 1.0,
+            SampleFilter::Tent => (1.0 - x).max(0.0),
+            SampleFilter::Gaussian => f32::exp(-2.0 * x * x),
+            SampleFilter::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+            SampleFilter::Lanczos => {
+                if x < 1e-5 {
+                    1.0
+                } else if x < 2.0 {
+                    2.0 * f32::sin(PI * x) * f32::sin(PI * x / 2.0) / (PI * PI * x * x)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for SampleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(SampleFilter::Box),
+            "tent" => Ok(SampleFilter::Tent),
+            "gaussian" => Ok(SampleFilter::Gaussian),
+            "mitchell" => Ok(SampleFilter::Mitchell),
+            "lanczos" => Ok(SampleFilter::Lanczos),
+            _ => Err(format!(
+                "unknown filter \"{}\", expected box, tent, gaussian, mitchell or lanczos",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    // Subpixel offset for the jitter uniform, in pixels
+    pub offset: [f32; 2],
+    pub weight: f32,
+}
+
+// An N×N grid of subpixel offsets covering the filter support. Rendering the
+// image once per offset and summing with the weights filters every pixel with
+// the kernel sampled at the grid positions. Weights sum up to one.
+pub fn sample_pattern(size: u32, filter: SampleFilter) -> Vec<Sample> {
+    let radius = filter.radius();
+    let positions: Vec<f32> = (0..size)
+        .map(|i| ((i as f32 + 0.5) / size as f32 * 2.0 - 1.0) * radius)
+        .collect();
+
+    let mut samples = Vec::with_capacity((size * size) as usize);
+    for &y in positions.iter() {
+        for &x in positions.iter() {
+            samples.push(Sample {
+                offset: [x, y],
+                weight: filter.weight(x) * filter.weight(y),
+            });
+        }
+    }
+
+    // Coarse grids can land on the zero crossings of Lanczos, average instead
+    let total: f32 = samples.iter().map(|sample| sample.weight).sum();
+    for sample in samples.iter_mut() {
+        sample.weight = if total > 0.0 {
+            sample.weight / total
+        } else {
+            1.0 / (size * size) as f32
+        };
+    }
+
+    samples
+}
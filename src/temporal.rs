@@ -0,0 +1,195 @@
+use std::error::Error;
+
+use crate::demo::Demo;
+use crate::framebuffer::Framebuffer;
+use crate::pack::Asset;
+use crate::program::ShaderProgram;
+use crate::shader::Shader;
+
+const VERTEX_SHADER: Asset = crate::asset!("shaders/vertex.glsl");
+const TEMPORAL_SHADER: Asset = crate::asset!("shaders/temporal.glsl");
+
+const JITTER_SEQUENCE_LENGTH: u32 = 8;
+const HISTORY_WEIGHT: f32 = 0.9;
+
+const RESOLVE_PASS: i32 = 0;
+const MOTION_BLUR_PASS: i32 = 1;
+
+// Subpixel offset in pixels for the given frame, from the Halton (2, 3) sequence
+pub fn jitter(frame: u32) -> [f32; 2] {
+    let index = frame % JITTER_SEQUENCE_LENGTH + 1;
+
+    [halton(index, 2) - 0.5, halton(index, 3) - 0.5]
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
+// Temporal antialiasing by reprojecting the previous frames with the camera
+// movement, and camera motion blur. Both run on the raymarched scene before
+// the post processing chain.
+#[derive(Debug)]
+pub struct TemporalFilter {
+    program: ShaderProgram,
+
+    history: [Framebuffer; 2],
+    current: usize,
+    is_history_valid: bool,
+
+    antialiasing: bool,
+    motion_blur: f32,
+}
+
+impl TemporalFilter {
+    pub unsafe fn new(
+        width: i32,
+        height: i32,
+        antialiasing: bool,
+        motion_blur: f32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let vs = Shader::new(&VERTEX_SHADER.text(), gl::VERTEX_SHADER)?;
+        let fs = Shader::new(&TEMPORAL_SHADER.text(), gl::FRAGMENT_SHADER)?;
+
+        Ok(Self {
+            program: ShaderProgram::new(vs, fs)?,
+            history: [
+                Framebuffer::new(width, height)?,
+                Framebuffer::new(width, height)?,
+            ],
+            current: 0,
+            is_history_valid: false,
+            antialiasing,
+            motion_blur,
+        })
+    }
+
+    pub fn antialiasing(&self) -> bool {
+        self.antialiasing
+    }
+
+    // Reloads the shader from the source tree, for development purposes
+    pub unsafe fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let vs = Shader::from_file("src/shaders/vertex.glsl", gl::VERTEX_SHADER)?;
+        let fs = Shader::from_file("src/shaders/temporal.glsl", gl::FRAGMENT_SHADER)?;
+
+        self.program = ShaderProgram::new(vs, fs)?;
+        self.is_history_valid = false;
+
+        Ok(())
+    }
+
+    pub unsafe fn resize(&mut self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        for history in self.history.iter_mut() {
+            history.resize(width, height)?;
+        }
+
+        self.is_history_valid = false;
+
+        Ok(())
+    }
+
+    // Forgets the previous frames, for when the scene skips the filter and
+    // they fall out of date
+    pub fn reset(&mut self) {
+        self.is_history_valid = false;
+    }
+
+    // Filters the scene in place. Expects the fullscreen quad `vao` to be bound.
+    pub unsafe fn apply(&mut self, demo: &Demo, scene: &Framebuffer) {
+        if !self.antialiasing && self.motion_blur <= 0.0 {
+            return;
+        }
+
+        // History from before a cut would only smear the previous shot over the new one
+        if demo.is_cut() {
+            self.is_history_valid = false;
+        }
+
+        let previous = &self.history[1 - self.current];
+        let resolved = &self.history[self.current];
+
+        let camera = demo.camera();
+        let target = demo.target();
+        let previous_camera = demo.previous_camera();
+        let previous_target = demo.previous_target();
+
+        self.program.activate();
+        self.program.set_uniform_i32("iChannel0", 0);
+        self.program.set_uniform_i32("iChannel1", 1);
+        self.program
+            .set_uniform2_f32("iResolution", scene.width() as f32, scene.height() as f32);
+        self.program
+            .set_uniform3_f32("iCamera", camera[0], camera[1], camera[2]);
+        self.program
+            .set_uniform3_f32("iTarget", target[0], target[1], target[2]);
+        self.program.set_uniform3_f32(
+            "iPreviousCamera",
+            previous_camera[0],
+            previous_camera[1],
+            previous_camera[2],
+        );
+        self.program.set_uniform3_f32(
+            "iPreviousTarget",
+            previous_target[0],
+            previous_target[1],
+            previous_target[2],
+        );
+
+        if self.antialiasing {
+            let history_weight = if self.is_history_valid {
+                HISTORY_WEIGHT
+            } else {
+                0.0
+            };
+
+            resolved.bind();
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, scene.texture());
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, previous.texture());
+
+            self.program.set_uniform_i32("iPass", RESOLVE_PASS);
+            self.program
+                .set_uniform_f32("iHistoryWeight", history_weight);
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+
+            self.current = 1 - self.current;
+            self.is_history_valid = true;
+        } else {
+            scene.blit_to(resolved);
+        }
+
+        // The history has to stay unblurred, so the blur goes back to the scene
+        if self.motion_blur > 0.0 {
+            scene.bind();
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, resolved.texture());
+
+            self.program.set_uniform_i32("iPass", MOTION_BLUR_PASS);
+            self.program
+                .set_uniform_f32("iMotionBlur", self.motion_blur);
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        } else {
+            resolved.blit_to(scene);
+        }
+
+        gl::ActiveTexture(gl::TEXTURE1);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+}
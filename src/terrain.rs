@@ -0,0 +1,115 @@
+use std::thread;
+
+use crate::noise::{self, fract, mix};
+
+// The baked area covers everything the cameras see before the fog takes over.
+// The shader falls back to the analytic terrain outside of it.
+const HEIGHTMAP_MIN: [f32; 2] = [-128.0, -128.0];
+const HEIGHTMAP_EXTENT: f32 = 256.0;
+// Only read beyond `DETAIL_DISTANCE` where a quarter unit between samples
+// doesn't show, and small enough to bake on every startup, even on the
+// single thread of the web
+const HEIGHTMAP_RESOLUTION: usize = 1024;
+
+// The shader reads the heightmap beyond `DETAIL_DISTANCE` from the camera,
+// blending from the exact terrain over `DETAIL_BLEND`
+pub const DETAIL_DISTANCE: f32 = 10.0;
+pub const DETAIL_BLEND: f32 = 5.0;
+
+// Height of the terrain surface at the given point, `sdTerrain` in
+// fragment.glsl
+pub fn height(x: f32, z: f32) -> f32 {
+    noise::fbm([(x + 50.0) / 2.0, (z - 30.0) / 2.0], 1.1, 4).abs() * 2.0
+}
+
+// Terrain heights sampled on a regular grid
+#[derive(Debug)]
+pub struct Heightmap {
+    // World x and z of the first sample
+    min: [f32; 2],
+    // Distance between samples
+    spacing: f32,
+    resolution: usize,
+    // Rows along z, first row at the smallest z
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    // Bakes the area the demo sees, split across all cores
+    pub fn bake() -> Self {
+        let resolution = HEIGHTMAP_RESOLUTION;
+        let spacing = HEIGHTMAP_EXTENT / (resolution - 1) as f32;
+        let min = HEIGHTMAP_MIN;
+
+        let mut heights = vec![0.0; resolution * resolution];
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let rows_per_thread = resolution.div_ceil(threads);
+
+        let bake_rows = move |chunk_index: usize, chunk: &mut [f32]| {
+            for (i, row) in chunk.chunks_mut(resolution).enumerate() {
+                let z = min[1] + (chunk_index * rows_per_thread + i) as f32 * spacing;
+
+                for (j, sample) in row.iter_mut().enumerate() {
+                    *sample = height(min[0] + j as f32 * spacing, z);
+                }
+            }
+        };
+
+        // The web has no threads to spawn
+        if threads == 1 {
+            bake_rows(0, &mut heights);
+        } else {
+            thread::scope(|scope| {
+                for (chunk_index, chunk) in
+                    heights.chunks_mut(rows_per_thread * resolution).enumerate()
+                {
+                    scope.spawn(move || bake_rows(chunk_index, chunk));
+                }
+            });
+        }
+
+        Self {
+            min,
+            spacing,
+            resolution,
+            heights,
+        }
+    }
+
+    // Height at the given point filtered like `bakedHeight` in fragment.glsl,
+    // none outside of the baked area
+    pub fn height(&self, x: f32, z: f32) -> Option<f32> {
+        let texel = [
+            (x - self.min[0]) / self.spacing,
+            (z - self.min[1]) / self.spacing,
+        ];
+        let size = (self.resolution - 1) as f32;
+        if !texel.iter().all(|&t| (0.0..size).contains(&t)) {
+            return None;
+        }
+
+        let i = [texel[0].floor() as usize, texel[1].floor() as usize];
+        let f = [fract(texel[0]), fract(texel[1])];
+        let sample = |x: usize, z: usize| self.heights[z * self.resolution + x];
+
+        let a = sample(i[0], i[1]);
+        let b = sample(i[0] + 1, i[1]);
+        let c = sample(i[0], i[1] + 1);
+        let d = sample(i[0] + 1, i[1] + 1);
+
+        Some(mix(mix(a, b, f[0]), mix(c, d, f[0]), f[1]))
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    // The shader's `iHeightmapBounds`: the first sample and the spacing
+    pub fn bounds(&self) -> [f32; 3] {
+        [self.min[0], self.min[1], self.spacing]
+    }
+}
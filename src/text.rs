@@ -0,0 +1,252 @@
+use std::error::Error;
+use std::mem;
+
+use gl::types::*;
+
+use crate::pack::Asset;
+use crate::program::ShaderProgram;
+use crate::shader::Shader;
+
+const VERTEX_SHADER: Asset = crate::asset!("shaders/text.vert.glsl");
+const FRAGMENT_SHADER: Asset = crate::asset!("shaders/text.glsl");
+
+// The 6x10 font of the X11 misc-fixed family (public domain) as a 1 bit per
+// pixel image, 16 glyphs per row starting from the space character
+const FONT: Asset = crate::asset!("fonts/6x10.raw");
+const FONT_COLUMNS: u32 = 16;
+const FONT_FIRST_CHAR: u32 = ' ' as u32;
+const FONT_LAST_CHAR: u32 = '~' as u32;
+
+pub const GLYPH_WIDTH: f32 = 6.0;
+pub const GLYPH_HEIGHT: f32 = 10.0;
+
+const ATLAS_WIDTH: i32 = 96;
+const ATLAS_HEIGHT: i32 = 60;
+
+// Position, texture coordinate and colour
+const VERTEX_FLOATS: usize = 8;
+
+// Batches coloured text and rectangles in pixel coordinates from the top left
+// corner and draws them in one call on top of whatever is bound. Glyphs are
+// scaled by an integer factor to keep them sharp.
+#[derive(Debug)]
+pub struct TextRenderer {
+    program: ShaderProgram,
+    texture: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+
+    scale: f32,
+    vertices: Vec<f32>,
+}
+
+impl TextRenderer {
+    pub unsafe fn new(scale: u32) -> Result<Self, Box<dyn Error>> {
+        let vs = Shader::new(&VERTEX_SHADER.text(), gl::VERTEX_SHADER)?;
+        let fs = Shader::new(&FRAGMENT_SHADER.text(), gl::FRAGMENT_SHADER)?;
+        let program = ShaderProgram::new(vs, fs)?;
+
+        let mut texture: GLuint = 0;
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+
+        gl::GenTextures(1, &mut texture);
+        gl::GenVertexArrays(1, &mut vao);
+        gl::GenBuffers(1, &mut vbo);
+
+        let atlas = font_atlas();
+
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::R8 as GLint,
+            ATLAS_WIDTH,
+            ATLAS_HEIGHT,
+            0,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            atlas.as_ptr() as *const std::ffi::c_void,
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_EDGE as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_EDGE as GLint,
+        );
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+        let stride = (VERTEX_FLOATS * mem::size_of::<f32>()) as GLsizei;
+        let attributes = [(0, 2, 0), (1, 2, 2), (2, 4, 4)];
+        for (index, size, offset) in attributes {
+            gl::VertexAttribPointer(
+                index,
+                size,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                stride,
+                (offset * mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+            gl::EnableVertexAttribArray(index);
+        }
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        gl::BindVertexArray(0);
+
+        Ok(Self {
+            program,
+            texture,
+            vao,
+            vbo,
+            scale: scale.max(1) as f32,
+            vertices: Vec::new(),
+        })
+    }
+
+    pub fn line_height(&self) -> f32 {
+        GLYPH_HEIGHT * self.scale
+    }
+
+    // Width of the text in pixels
+    pub fn width(&self, text: &str) -> f32 {
+        text.chars().count() as f32 * GLYPH_WIDTH * self.scale
+    }
+
+    // Queues a single line of text, characters outside printable ASCII are
+    // drawn as question marks
+    pub fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let (width, height) = (GLYPH_WIDTH * self.scale, GLYPH_HEIGHT * self.scale);
+
+        for (i, c) in text.chars().enumerate() {
+            let code = match c as u32 {
+                code @ FONT_FIRST_CHAR..=FONT_LAST_CHAR => code,
+                _ => '?' as u32,
+            };
+            if code == FONT_FIRST_CHAR {
+                continue;
+            }
+
+            let index = code - FONT_FIRST_CHAR;
+            let u = (index % FONT_COLUMNS) as f32 * GLYPH_WIDTH / ATLAS_WIDTH as f32;
+            let v = (index / FONT_COLUMNS) as f32 * GLYPH_HEIGHT / ATLAS_HEIGHT as f32;
+
+            self.quad(
+                [x + i as f32 * width, y, width, height],
+                [
+                    u,
+                    v,
+                    u + GLYPH_WIDTH / ATLAS_WIDTH as f32,
+                    v + GLYPH_HEIGHT / ATLAS_HEIGHT as f32,
+                ],
+                color,
+            );
+        }
+    }
+
+    // Queues a filled rectangle
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.quad([x, y, width, height], [-1.0; 4], color);
+    }
+
+    // Draws everything queued since the previous call with alpha blending into
+    // the bound framebuffer of the given size
+    pub unsafe fn draw(&mut self, width: i32, height: i32) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        gl::Viewport(0, 0, width, height);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+        self.program.activate();
+        self.program.set_uniform_i32("iChannel0", 0);
+        self.program
+            .set_uniform2_f32("iResolution", width as f32, height as f32);
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+
+        gl::BindVertexArray(self.vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (self.vertices.len() * mem::size_of::<f32>()) as GLsizeiptr,
+            self.vertices.as_ptr() as *const std::ffi::c_void,
+            gl::STREAM_DRAW,
+        );
+        gl::DrawArrays(
+            gl::TRIANGLES,
+            0,
+            (self.vertices.len() / VERTEX_FLOATS) as GLsizei,
+        );
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        gl::BindVertexArray(0);
+
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        gl::Disable(gl::BLEND);
+
+        self.vertices.clear();
+    }
+
+    // Two triangles from the `[x, y, width, height]` rectangle and
+    // `[u0, v0, u1, v1]` texture coordinates
+    fn quad(&mut self, rect: [f32; 4], uv: [f32; 4], color: [f32; 4]) {
+        let [x, y, width, height] = rect;
+        let [u0, v0, u1, v1] = uv;
+
+        let corners = [
+            (x, y, u0, v0),
+            (x + width, y, u1, v0),
+            (x, y + height, u0, v1),
+            (x + width, y, u1, v0),
+            (x + width, y + height, u1, v1),
+            (x, y + height, u0, v1),
+        ];
+
+        for (x, y, u, v) in corners {
+            self.vertices.extend_from_slice(&[x, y, u, v]);
+            self.vertices.extend_from_slice(&color);
+        }
+    }
+}
+
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// Unpacks the font into one byte per pixel, the first row is the top of the
+// glyphs so the texture is upside down as far as OpenGL is concerned
+fn font_atlas() -> Vec<u8> {
+    let font = FONT.bytes();
+    let row_bytes = (ATLAS_WIDTH / 8) as usize;
+    let mut atlas = vec![0; (ATLAS_WIDTH * ATLAS_HEIGHT) as usize];
+
+    for (i, pixel) in atlas.iter_mut().enumerate() {
+        let (x, y) = (i % ATLAS_WIDTH as usize, i / ATLAS_WIDTH as usize);
+        let byte = font[y * row_bytes + x / 8];
+
+        if byte & (0x80 >> (x % 8)) != 0 {
+            *pixel = 255;
+        }
+    }
+
+    atlas
+}
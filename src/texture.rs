@@ -0,0 +1,516 @@
+use std::error::Error;
+#[cfg(feature = "images")]
+use std::fs::File;
+#[cfg(feature = "images")]
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use gl::types::*;
+
+use crate::demo::Scene;
+use crate::program::ShaderProgram;
+use crate::random::Random;
+
+pub const CHANNEL_COUNT: usize = 4;
+
+// The noise texture is the same on every run
+const NOISE_SEED: u64 = 1337;
+const NOISE_SIZE: u32 = 256;
+// Offset of the green channel from the red one in texels, for looking up 3D
+// noise from a single sample of two layers
+const NOISE_OFFSET: [u32; 2] = [37, 17];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureSource {
+    // Generated RGBA white noise of the given size, needs no files
+    Noise(u32),
+    // PNG or JPEG image
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl Wrap {
+    fn gl_enum(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::Clamp => gl::CLAMP_TO_EDGE,
+            Wrap::Mirror => gl::MIRRORED_REPEAT,
+        }
+    }
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "repeat" => Ok(Wrap::Repeat),
+            "clamp" => Ok(Wrap::Clamp),
+            "mirror" => Ok(Wrap::Mirror),
+            _ => Err(format!("unknown wrap mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+    // Trilinear between generated mipmap levels
+    Mipmap,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Filter::Nearest),
+            "linear" => Ok(Filter::Linear),
+            "mipmap" => Ok(Filter::Mipmap),
+            _ => Err(format!("unknown texture filter: {}", s)),
+        }
+    }
+}
+
+// A texture and how it's sampled, written as `<source>[,<wrap>][,<filter>]`
+// where the source is `noise`, `noise:<size>` or a path to an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureSpec {
+    pub source: TextureSource,
+    pub wrap: Wrap,
+    pub filter: Filter,
+}
+
+impl TextureSpec {
+    pub fn noise() -> Self {
+        Self {
+            source: TextureSource::Noise(NOISE_SIZE),
+            wrap: Wrap::Repeat,
+            filter: Filter::Mipmap,
+        }
+    }
+
+    // Relative image paths are resolved from the given directory
+    pub fn relative_to(mut self, directory: &Path) -> Self {
+        if let TextureSource::File(path) = &self.source {
+            self.source = TextureSource::File(directory.join(path));
+        }
+
+        self
+    }
+}
+
+impl FromStr for TextureSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+
+        let source = match parts.next().unwrap_or("") {
+            "" => return Err("missing the texture source".to_string()),
+            "noise" => TextureSource::Noise(NOISE_SIZE),
+            source => match source.strip_prefix("noise:") {
+                Some(size) => match size.parse() {
+                    Ok(size) if size > 0 => TextureSource::Noise(size),
+                    _ => return Err(format!("invalid noise texture size: {}", size)),
+                },
+                None => TextureSource::File(PathBuf::from(source)),
+            },
+        };
+
+        // Shadertoy's defaults
+        let mut spec = Self {
+            source,
+            wrap: Wrap::Repeat,
+            filter: Filter::Mipmap,
+        };
+
+        for part in parts {
+            if let Ok(wrap) = part.parse() {
+                spec.wrap = wrap;
+            } else {
+                spec.filter = part.parse()?;
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+// Texture bound to a channel of the scene shader, in every scene or in one,
+// written as `[<scene>:]<channel>=<texture>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelBinding {
+    pub scene: Option<Scene>,
+    pub channel: usize,
+    pub texture: TextureSpec,
+}
+
+impl FromStr for ChannelBinding {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, texture) = s
+            .split_once('=')
+            .ok_or("expected [<scene>:]<channel>=<texture>")?;
+
+        let (scene, channel) = match target.split_once(':') {
+            Some((scene, channel)) => (Some(scene.parse::<Scene>()?), channel),
+            None => (None, target),
+        };
+
+        let channel = match channel.parse() {
+            Ok(channel) if channel < CHANNEL_COUNT => channel,
+            _ => return Err(format!("channel must be between 0 and {}", CHANNEL_COUNT - 1).into()),
+        };
+
+        Ok(Self {
+            scene,
+            channel,
+            texture: texture.parse()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Texture {
+    id: GLuint,
+    width: i32,
+    height: i32,
+}
+
+impl Texture {
+    pub unsafe fn load(spec: &TextureSpec) -> Result<Self, Box<dyn Error>> {
+        let (width, height, pixels) = match &spec.source {
+            TextureSource::Noise(size) => noise(*size),
+            TextureSource::File(path) => {
+                decode(path).map_err(|err| format!("failed to load {}: {}", path.display(), err))?
+            }
+        };
+
+        let mut id: GLuint = 0;
+        gl::GenTextures(1, &mut id);
+        gl::BindTexture(gl::TEXTURE_2D, id);
+
+        // Rows of RGBA8 aren't always a multiple of 4 bytes wide in other formats
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as GLint,
+            width as i32,
+            height as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_ptr() as *const std::ffi::c_void,
+        );
+
+        let (min_filter, mag_filter) = match spec.filter {
+            Filter::Nearest => (gl::NEAREST, gl::NEAREST),
+            Filter::Linear => (gl::LINEAR, gl::LINEAR),
+            Filter::Mipmap => {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+                (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR)
+            }
+        };
+
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag_filter as GLint);
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_S,
+            spec.wrap.gl_enum() as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_T,
+            spec.wrap.gl_enum() as GLint,
+        );
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        Ok(Self {
+            id,
+            width: width as i32,
+            height: height as i32,
+        })
+    }
+
+    // Single channel float texture for data the shader reads with `texelFetch`
+    pub unsafe fn from_f32(width: usize, height: usize, data: &[f32]) -> Self {
+        let mut id: GLuint = 0;
+        gl::GenTextures(1, &mut id);
+        gl::BindTexture(gl::TEXTURE_2D, id);
+
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::R32F as GLint,
+            width as i32,
+            height as i32,
+            0,
+            gl::RED,
+            gl::FLOAT,
+            data.as_ptr() as *const std::ffi::c_void,
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_EDGE as GLint,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_EDGE as GLint,
+        );
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        Self {
+            id,
+            width: width as i32,
+            height: height as i32,
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+// Loads each texture once no matter how many channels use it
+#[derive(Debug, Default)]
+pub struct TextureCache {
+    textures: Vec<(TextureSpec, Texture)>,
+}
+
+impl TextureCache {
+    pub unsafe fn load(&mut self, spec: &TextureSpec) -> Result<&Texture, Box<dyn Error>> {
+        let index = match self.textures.iter().position(|(loaded, _)| loaded == spec) {
+            Some(index) => index,
+            None => {
+                self.textures.push((spec.clone(), Texture::load(spec)?));
+                self.textures.len() - 1
+            }
+        };
+
+        Ok(&self.textures[index].1)
+    }
+
+    // Only finds textures that were loaded already
+    pub fn get(&self, spec: &TextureSpec) -> Option<&Texture> {
+        self.textures
+            .iter()
+            .find(|(loaded, _)| loaded == spec)
+            .map(|(_, texture)| texture)
+    }
+}
+
+// Textures of the scene shader's channels
+#[derive(Debug)]
+pub struct Channels {
+    // Given on the command line, override the timeline
+    bindings: Vec<ChannelBinding>,
+    textures: TextureCache,
+}
+
+impl Channels {
+    // Loads everything the given scenes use up front so playback doesn't
+    // stall on a scene change
+    pub unsafe fn new(
+        bindings: Vec<ChannelBinding>,
+        scenes: &[Scene],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut channels = Self {
+            bindings,
+            textures: TextureCache::default(),
+        };
+
+        for &scene in scenes {
+            for spec in channels.specs(scene).iter().flatten() {
+                channels.textures.load(spec)?;
+            }
+        }
+
+        Ok(channels)
+    }
+
+    // Binds the textures of the scene to units 0-3 and sets the samplers
+    pub unsafe fn bind(
+        &mut self,
+        scene: Scene,
+        program: &ShaderProgram,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut resolutions = [[0.0; 3]; CHANNEL_COUNT];
+
+        for (channel, spec) in self.specs(scene).iter().enumerate() {
+            let id = match spec {
+                Some(spec) => {
+                    let texture = self.textures.load(spec)?;
+                    resolutions[channel] = [texture.width() as f32, texture.height() as f32, 1.0];
+                    texture.id()
+                }
+                None => 0,
+            };
+
+            gl::ActiveTexture(gl::TEXTURE0 + channel as GLenum);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            program.set_uniform_i32(&format!("iChannel{}", channel), channel as i32);
+        }
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        program.set_uniform3_f32v("iChannelResolution", &resolutions);
+
+        Ok(())
+    }
+
+    // The timeline's textures with the command line ones on top, a binding
+    // to a single scene winning over one to every scene
+    fn specs(&self, scene: Scene) -> [Option<TextureSpec>; CHANNEL_COUNT] {
+        let mut specs = scene.channels();
+
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.scene.is_none())
+        {
+            specs[binding.channel] = Some(binding.texture.clone());
+        }
+
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.scene == Some(scene))
+        {
+            specs[binding.channel] = Some(binding.texture.clone());
+        }
+
+        specs
+    }
+}
+
+// RGBA8 pixels with the bottom row first, like Shadertoy flips its images
+#[cfg(feature = "images")]
+fn decode(path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    let (width, height, pixels) = match extension.as_deref() {
+        Some("png") => decode_png(path)?,
+        Some("jpg") | Some("jpeg") => decode_jpeg(path)?,
+        _ => return Err("only PNG and JPEG images are supported".into()),
+    };
+
+    let row = width as usize * 4;
+    let flipped = pixels.chunks_exact(row).rev().flatten().copied().collect();
+
+    Ok((width, height, flipped))
+}
+
+#[cfg(not(feature = "images"))]
+fn decode(_path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+    Err("images need the images feature".into())
+}
+
+#[cfg(feature = "images")]
+fn decode_png(path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    // Palettes and low bit depths to 8 bits per channel
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => to_rgba(&buffer, 3, |p| [p[0], p[1], p[2], 255]),
+        png::ColorType::Grayscale => to_rgba(&buffer, 1, |p| [p[0], p[0], p[0], 255]),
+        png::ColorType::GrayscaleAlpha => to_rgba(&buffer, 2, |p| [p[0], p[0], p[0], p[1]]),
+        png::ColorType::Indexed => return Err("unexpanded palette".into()),
+    };
+
+    Ok((info.width, info.height, pixels))
+}
+
+#[cfg(feature = "images")]
+fn decode_jpeg(path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn Error>> {
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(File::open(path)?));
+    let buffer = decoder.decode()?;
+    let info = decoder.info().ok_or("missing image info")?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => to_rgba(&buffer, 3, |p| [p[0], p[1], p[2], 255]),
+        jpeg_decoder::PixelFormat::L8 => to_rgba(&buffer, 1, |p| [p[0], p[0], p[0], 255]),
+        // Big endian, keep the high byte
+        jpeg_decoder::PixelFormat::L16 => to_rgba(&buffer, 2, |p| [p[0], p[0], p[0], 255]),
+        jpeg_decoder::PixelFormat::CMYK32 => to_rgba(&buffer, 4, |p| {
+            let k = 255 - p[3] as u32;
+            let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+            [channel(p[0]), channel(p[1]), channel(p[2]), 255]
+        }),
+    };
+
+    Ok((info.width as u32, info.height as u32, pixels))
+}
+
+#[cfg(feature = "images")]
+fn to_rgba(buffer: &[u8], channels: usize, pixel: impl Fn(&[u8]) -> [u8; 4]) -> Vec<u8> {
+    buffer.chunks_exact(channels).flat_map(pixel).collect()
+}
+
+// Independent random values in the red, blue and alpha channels. Green is
+// red shifted by `NOISE_OFFSET`, so that two layers of 3D value noise can be
+// read with one sample at `uv + z * NOISE_OFFSET`.
+fn noise(size: u32) -> (u32, u32, Vec<u8>) {
+    let mut rng = Random::with_seed(NOISE_SEED);
+    let count = (size * size) as usize;
+
+    let red: Vec<u8> = (0..count).map(|_| rng.next_u8()).collect();
+    let mut pixels = Vec::with_capacity(count * 4);
+
+    for y in 0..size {
+        for x in 0..size {
+            let shifted = ((y + size - NOISE_OFFSET[1] % size) % size) * size
+                + (x + size - NOISE_OFFSET[0] % size) % size;
+
+            pixels.extend_from_slice(&[
+                red[(y * size + x) as usize],
+                red[shifted as usize],
+                rng.next_u8(),
+                rng.next_u8(),
+            ]);
+        }
+    }
+
+    (size, size, pixels)
+}
@@ -0,0 +1,304 @@
+use std::error::Error;
+use std::time::Duration;
+
+use gl::types::*;
+
+use crate::culling::LeipaeBuffer;
+use crate::demo::{Demo, Timeline, SCENE_ORDER};
+use crate::framebuffer::Framebuffer;
+use crate::pack::Asset;
+use crate::program::ShaderProgram;
+use crate::quality::Quality;
+use crate::renderer;
+use crate::sdf::{self, Distance, SceneSdf, Vec3, MIN_DIST};
+use crate::shader::{self, Shader};
+
+const VERTEX_SHADER: Asset = crate::asset!("shaders/vertex.glsl");
+const FRAGMENT_SHADER: Asset = crate::asset!("shaders/fragment.glsl");
+
+// Replaces the scene shader's main. Modes 0 and 1 write `sdScene` or
+// `sdLeipaeRound` at a grid of points on a horizontal plane, one per pixel
+// center, mode 2 the result of `rayMarch` through every pixel.
+const PROBE_MAIN: &str = "
+uniform int iProbeMode;
+uniform vec3 iProbeOrigin;
+uniform float iProbeSpacing;
+
+void main() {
+    vec3 p = iProbeOrigin + vec3(gl_FragCoord.x, 0.0, gl_FragCoord.y) * iProbeSpacing;
+
+    if (iProbeMode == 0) {
+        FragColor = sdScene(p);
+    } else if (iProbeMode == 1) {
+        FragColor = sdLeipaeRound(p);
+    } else {
+        vec3 viewDir = rayDirection(FOV, iResolution, gl_FragCoord.xy);
+        mat4 viewToWorld = lookAt(iCamera, iTarget, vec3(0.0, 1.0, 0.0));
+        vec3 worldDir = (viewToWorld * vec4(viewDir, 0.0)).xyz;
+        FragColor = rayMarch(iCamera, worldDir, MIN_DIST, MAX_DIST);
+    }
+}
+";
+
+const SCENE_MODE: i32 = 0;
+const LEIPAE_MODE: i32 = 1;
+const RAY_MODE: i32 = 2;
+
+// The leipae are placed the same way on every run
+const PROBE_SEED: u64 = 2022;
+const GRID_SIZE: i32 = 64;
+const RAY_SIZE: [i32; 2] = [96, 54];
+// Heights of the planes sampled around the camera
+const PLANES: [f32; 3] = [0.5, 1.0, 2.0];
+const PLANE_SPACING: f32 = 0.25;
+// Heights of the planes through a single leipae, which is about 4.5 wide
+const LEIPAE_PLANES: [f32; 3] = [-0.5, 0.0, 0.5];
+const LEIPAE_SPACING: f32 = 0.1;
+
+// Allowed difference relative to the distance, or to one closer than one
+// unit. The terrain hash works at magnitudes where the last bit of a
+// division moves the height by a few hundredths, and GLSL doesn't require
+// division to be correctly rounded, so the GPU can't be matched bit for bit.
+const DISTANCE_TOLERANCE: f32 = 0.05;
+// The leipae only have the sine hash, which is far better behaved
+const LEIPAE_TOLERANCE: f32 = 0.005;
+const MATERIAL_TOLERANCE: f32 = 0.05;
+const DEPTH_TOLERANCE: f32 = 0.05;
+// Where the material changes, two surfaces are equally close or a ray grazes
+// a surface the tiniest difference flips the result. A few of those are
+// expected in every check.
+const MISMATCHES_ALLOWED: f32 = 0.01;
+
+// Differences between the GPU and the CPU in one check
+#[derive(Debug, Default)]
+struct Comparison {
+    samples: usize,
+    max_error: f32,
+    mismatches: usize,
+}
+
+impl Comparison {
+    fn add(&mut self, error: f32, tolerance: f32) {
+        self.samples += 1;
+        self.max_error = self.max_error.max(error);
+        if error.is_nan() || error > tolerance {
+            self.mismatches += 1;
+        }
+    }
+
+    fn mismatch(&mut self) {
+        self.samples += 1;
+        self.mismatches += 1;
+    }
+
+    // Prints the result, returns whether the check passed
+    fn report(&self, subject: &str, name: &str) -> bool {
+        let passed = self.mismatches as f32 <= self.samples as f32 * MISMATCHES_ALLOWED;
+
+        println!(
+            "{:<16} {:<20} {:>6} samples, max error {:.2e}, {} over tolerance{}",
+            subject,
+            name,
+            self.samples,
+            self.max_error,
+            self.mismatches,
+            if passed { "" } else { " FAILED" }
+        );
+
+        passed
+    }
+}
+
+// Compares sdf.rs with the shader: the leipae on their own, then the scene
+// distances and ray marched depths in the middle of every scene. Prints the
+// results and fails if they disagree. Both run at the given quality.
+pub unsafe fn run(quality: Quality) -> Result<(), Box<dyn Error>> {
+    let source = FRAGMENT_SHADER
+        .text()
+        .replace("void main() {", "void renderScene() {")
+        + PROBE_MAIN;
+
+    let vs = Shader::new(&VERTEX_SHADER.text(), gl::VERTEX_SHADER)?;
+    // The analytic terrain on both, the baked heights would only add their
+    // own filtering to the differences
+    let mut defines = vec!["NO_HEIGHTMAP".to_string()];
+    defines.extend(quality.defines());
+    let fs = Shader::new(
+        &shader::with_defines(&source, &defines),
+        gl::FRAGMENT_SHADER,
+    )?;
+    let program = ShaderProgram::new(vs, fs)?;
+    // Every leipae, culling is left out of the comparison
+    let leipae = LeipaeBuffer::new();
+    leipae.attach(&program);
+
+    let grid = Framebuffer::with_format(GRID_SIZE, GRID_SIZE, gl::RGBA32F)?;
+    let rays = Framebuffer::with_format(RAY_SIZE[0], RAY_SIZE[1], gl::RGBA32F)?;
+    let (vao, vbo) = renderer::fullscreen_quad();
+
+    program.activate();
+    gl::BindVertexArray(vao);
+
+    let mut failures = 0;
+
+    let half = GRID_SIZE as f32 * LEIPAE_SPACING / 2.0;
+    for y in LEIPAE_PLANES {
+        let comparison = probe_distances(
+            &program,
+            &grid,
+            LEIPAE_MODE,
+            [-half, y, -half],
+            LEIPAE_SPACING,
+            LEIPAE_TOLERANCE,
+            sdf::sd_leipae_round,
+        );
+
+        if !comparison.report("Leipae", &format!("distance at y {:+.1}", y)) {
+            failures += 1;
+        }
+    }
+
+    let mut demo = Demo::with_seed(Timeline::default(), PROBE_SEED);
+
+    for &scene in SCENE_ORDER.iter().filter(|scene| scene.duration() > 0.0) {
+        let start = scene.start().unwrap_or(0.0);
+        demo.seek(Duration::from_secs_f32(start + scene.duration() / 2.0));
+
+        let sdf = SceneSdf::from_demo(&demo).with_quality(quality);
+        let camera = demo.camera();
+        let target = demo.target();
+
+        program.set_uniform_f32("iTime", sdf.time());
+        program.set_uniform3_f32("iCamera", camera[0], camera[1], camera[2]);
+        program.set_uniform3_f32("iTarget", target[0], target[1], target[2]);
+        leipae.bind(&program, sdf.leipae());
+
+        let subject = format!("{:?}", scene);
+
+        // Planes around the camera
+        let half = GRID_SIZE as f32 * PLANE_SPACING / 2.0;
+        for y in PLANES {
+            let comparison = probe_distances(
+                &program,
+                &grid,
+                SCENE_MODE,
+                [camera[0] - half, y, camera[2] - half],
+                PLANE_SPACING,
+                DISTANCE_TOLERANCE,
+                |p| sdf.distance(p),
+            );
+
+            if !comparison.report(&subject, &format!("distance at y {:.1}", y)) {
+                failures += 1;
+            }
+        }
+
+        let comparison = probe_rays(&program, &rays, &sdf, camera, target);
+        if !comparison.report(&subject, "ray depth") {
+            failures += 1;
+        }
+    }
+
+    gl::BindVertexArray(0);
+    gl::DeleteBuffers(1, &vbo);
+    gl::DeleteVertexArrays(1, &vao);
+
+    if failures > 0 {
+        return Err(format!(
+            "{} checks failed, sdf.rs and fragment.glsl disagree",
+            failures
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+unsafe fn probe_distances(
+    program: &ShaderProgram,
+    target: &Framebuffer,
+    mode: i32,
+    origin: Vec3,
+    spacing: f32,
+    tolerance: f32,
+    distance: impl Fn(Vec3) -> Distance,
+) -> Comparison {
+    program.set_uniform_i32("iProbeMode", mode);
+    program.set_uniform3_f32("iProbeOrigin", origin[0], origin[1], origin[2]);
+    program.set_uniform_f32("iProbeSpacing", spacing);
+
+    let pixels = draw(program, target);
+    let mut comparison = Comparison::default();
+
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        // Pixel centers, like gl_FragCoord
+        let x = (i as i32 % target.width()) as f32 + 0.5;
+        let z = (i as i32 / target.width()) as f32 + 0.5;
+        let cpu = distance([origin[0] + x * spacing, origin[1], origin[2] + z * spacing]);
+
+        let is_same_material = pixel[0] == cpu.material.index() as f32
+            && (pixel[1] - cpu.detail).abs() <= MATERIAL_TOLERANCE;
+
+        if !is_same_material {
+            comparison.mismatch();
+        } else {
+            comparison.add(relative_error(pixel[3], cpu.dist), tolerance);
+        }
+    }
+
+    comparison
+}
+
+unsafe fn probe_rays(
+    program: &ShaderProgram,
+    target: &Framebuffer,
+    sdf: &SceneSdf,
+    camera: Vec3,
+    look_at: Vec3,
+) -> Comparison {
+    let resolution = [target.width() as f32, target.height() as f32];
+
+    program.set_uniform_i32("iProbeMode", RAY_MODE);
+
+    let pixels = draw(program, target);
+    let mut comparison = Comparison::default();
+
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        let x = (i as i32 % target.width()) as f32 + 0.5;
+        let y = (i as i32 / target.width()) as f32 + 0.5;
+        let dir = sdf::camera_ray(camera, look_at, resolution, [x, y]);
+
+        // Misses are negative in the shader
+        match (
+            pixel[3] >= 0.0,
+            sdf.ray_march(camera, dir, MIN_DIST, sdf.max_dist()),
+        ) {
+            (true, Some(hit)) => {
+                comparison.add(relative_error(pixel[3], hit.depth), DEPTH_TOLERANCE)
+            }
+            (false, None) => comparison.add(0.0, DEPTH_TOLERANCE),
+            _ => comparison.mismatch(),
+        }
+    }
+
+    comparison
+}
+
+unsafe fn draw(program: &ShaderProgram, target: &Framebuffer) -> Vec<f32> {
+    target.bind();
+    program.set_uniform2_f32(
+        "iResolution",
+        target.width() as GLfloat,
+        target.height() as GLfloat,
+    );
+    gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+
+    let pixels = target.read_rgba();
+    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+    pixels
+}
+
+fn relative_error(gpu: f32, cpu: f32) -> f32 {
+    (gpu - cpu).abs() / cpu.abs().max(1.0)
+}
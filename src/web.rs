@@ -0,0 +1,178 @@
+// Entry point of the web build. Plays the demo on the canvas of index.html,
+// one frame per `requestAnimationFrame`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::convert::FromWasmAbi;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{HtmlCanvasElement, KeyboardEvent, WebGl2RenderingContext, Window};
+
+use crate::clock::{Clock, PerformanceClock};
+use crate::demo::Demo;
+use crate::options::Options;
+use crate::renderer::Renderer;
+use crate::webgl;
+
+const CANVAS_ID: &str = "leipae";
+
+struct Player {
+    demo: Demo,
+    renderer: Renderer,
+    clock: PerformanceClock,
+}
+
+impl Player {
+    // Returns whether to keep going
+    fn frame(&mut self) -> Result<bool, JsValue> {
+        self.demo.update(self.clock.tick());
+
+        unsafe {
+            if self.demo.should_exit() {
+                self.renderer.clear();
+                return Ok(false);
+            }
+
+            self.renderer.draw(&self.demo).map_err(to_js)?;
+        }
+
+        Ok(true)
+    }
+}
+
+pub fn start() {
+    if let Err(err) = run() {
+        web_sys::console::error_1(&err);
+    }
+}
+
+fn run() -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let canvas: HtmlCanvasElement = window
+        .document()
+        .and_then(|document| document.get_element_by_id(CANVAS_ID))
+        .ok_or("no canvas element with the id leipae")?
+        .dyn_into()?;
+    let (width, height) = fit_canvas(&window, &canvas);
+
+    // The canvas has to be single sampled to blit the scene to it
+    let attributes = js_sys::Object::new();
+    js_sys::Reflect::set(&attributes, &"antialias".into(), &false.into())?;
+
+    let context: WebGl2RenderingContext = canvas
+        .get_context_with_context_options("webgl2", &attributes)?
+        .ok_or("WebGL2 is not supported")?
+        .dyn_into()?;
+
+    // The scene and the passes after it render to half float textures
+    context
+        .get_extension("EXT_color_buffer_float")?
+        .ok_or("EXT_color_buffer_float is not supported")?;
+
+    webgl::load(context);
+
+    let options = Options::default();
+    let renderer = Renderer::new(width as f32, height as f32, &options).map_err(to_js)?;
+
+    let player = Rc::new(RefCell::new(Player {
+        demo: Demo::with_timeline(options.timeline()),
+        renderer,
+        clock: PerformanceClock::new(),
+    }));
+
+    listen(&canvas, "click", {
+        let player = player.clone();
+        move |_: web_sys::MouseEvent| player.borrow_mut().demo.skip_to_next()
+    })?;
+
+    listen(&window, "keydown", {
+        let player = player.clone();
+        move |event: KeyboardEvent| {
+            let mut player = player.borrow_mut();
+
+            match event.key().as_str() {
+                " " => player.demo.toggle_pause(),
+                "h" => player.renderer.toggle_hud(),
+                "v" => player.renderer.next_debug_view(),
+                "q" => {
+                    if let Err(err) = unsafe { player.renderer.next_quality() } {
+                        web_sys::console::error_1(&to_js(err));
+                    }
+                }
+                _ => (),
+            }
+        }
+    })?;
+
+    listen(&window.clone(), "resize", {
+        let player = player.clone();
+        move |_: web_sys::Event| {
+            let (width, height) = fit_canvas(&window, &canvas);
+
+            if width != 0 && height != 0 {
+                if let Err(err) = unsafe { player.borrow_mut().renderer.resize(width, height) } {
+                    web_sys::console::error_1(&to_js(err));
+                }
+            }
+        }
+    })?;
+
+    // The callback asks for the next frame with itself
+    let callback = Rc::new(RefCell::new(None::<Closure<dyn FnMut()>>));
+    *callback.borrow_mut() = Some(Closure::new({
+        let callback = callback.clone();
+        move || match player.borrow_mut().frame() {
+            Ok(true) => {
+                if let Some(callback) = callback.borrow().as_ref() {
+                    request_animation_frame(callback);
+                }
+            }
+            Ok(false) => (),
+            Err(err) => web_sys::console::error_1(&err),
+        }
+    }));
+
+    if let Some(callback) = callback.borrow().as_ref() {
+        request_animation_frame(callback);
+    }
+
+    Ok(())
+}
+
+// Sizes the canvas to its CSS size in device pixels, returns the size
+fn fit_canvas(window: &Window, canvas: &HtmlCanvasElement) -> (u32, u32) {
+    let ratio = window.device_pixel_ratio();
+    let width = (canvas.client_width() as f64 * ratio) as u32;
+    let height = (canvas.client_height() as f64 * ratio) as u32;
+
+    canvas.set_width(width);
+    canvas.set_height(height);
+
+    (width, height)
+}
+
+// The listeners live as long as the page
+fn listen<E: FromWasmAbi + 'static>(
+    target: &web_sys::EventTarget,
+    event: &str,
+    listener: impl FnMut(E) + 'static,
+) -> Result<(), JsValue> {
+    let listener = Closure::<dyn FnMut(E)>::new(listener);
+    target.add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())?;
+    listener.forget();
+
+    Ok(())
+}
+
+fn request_animation_frame(callback: &Closure<dyn FnMut()>) {
+    if let Some(window) = web_sys::window() {
+        if let Err(err) = window.request_animation_frame(callback.as_ref().unchecked_ref()) {
+            web_sys::console::error_1(&err);
+        }
+    }
+}
+
+fn to_js(err: impl ToString) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
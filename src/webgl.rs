@@ -0,0 +1,810 @@
+// OpenGL on top of WebGL2 for the web build. The renderer calls the `gl` crate
+// the same way as on the desktop, `load` hands it functions that forward the
+// calls to the context of the canvas. Only the functions the demo uses are
+// provided, the rest panic like missing functions of an old driver would.
+//
+// WebGL refers to objects by JavaScript handles instead of integer names, so
+// the handles are kept in tables and the index in a table is the name.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::ptr;
+use std::slice;
+
+use gl::types::*;
+use js_sys::{Array, Float32Array, Object, Uint8Array};
+use wasm_bindgen::JsValue;
+use web_sys::{
+    WebGl2RenderingContext, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlShader, WebGlTexture,
+    WebGlUniformLocation, WebGlVertexArrayObject,
+};
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+// Makes the `gl` functions draw with the given context
+pub fn load(context: WebGl2RenderingContext) {
+    CONTEXT.with(|current| *current.borrow_mut() = Some(Context::new(context)));
+
+    gl::load_with(proc_address);
+}
+
+// Objects of one type by their name. Zero is no object, like in OpenGL, and
+// names aren't reused.
+struct Objects<T> {
+    objects: Vec<Option<T>>,
+}
+
+impl<T> Objects<T> {
+    fn new() -> Self {
+        Self {
+            objects: vec![None],
+        }
+    }
+
+    fn insert(&mut self, object: Option<T>) -> GLuint {
+        self.objects.push(object);
+
+        (self.objects.len() - 1) as GLuint
+    }
+
+    fn get(&self, name: GLuint) -> Option<&T> {
+        self.objects.get(name as usize).and_then(Option::as_ref)
+    }
+
+    fn remove(&mut self, name: GLuint) -> Option<T> {
+        self.objects.get_mut(name as usize).and_then(Option::take)
+    }
+}
+
+struct Context {
+    gl: WebGl2RenderingContext,
+
+    buffers: Objects<WebGlBuffer>,
+    framebuffers: Objects<WebGlFramebuffer>,
+    programs: Objects<WebGlProgram>,
+    shaders: Objects<WebGlShader>,
+    textures: Objects<WebGlTexture>,
+    vertex_arrays: Objects<WebGlVertexArrayObject>,
+
+    // Uniform locations are looked up every time they're set, so each one is
+    // stored once and its index handed out as the location
+    uniforms: Vec<WebGlUniformLocation>,
+    uniform_locations: HashMap<(GLuint, String), GLint>,
+
+    // What `glGetString` has returned, kept alive for the caller
+    strings: HashMap<GLenum, CString>,
+
+    pack_alignment: usize,
+    unpack_alignment: usize,
+}
+
+impl Context {
+    fn new(gl: WebGl2RenderingContext) -> Self {
+        Self {
+            gl,
+            buffers: Objects::new(),
+            framebuffers: Objects::new(),
+            programs: Objects::new(),
+            shaders: Objects::new(),
+            textures: Objects::new(),
+            vertex_arrays: Objects::new(),
+            uniforms: Vec::new(),
+            uniform_locations: HashMap::new(),
+            strings: HashMap::new(),
+            pack_alignment: 4,
+            unpack_alignment: 4,
+        }
+    }
+
+    fn uniform(&self, location: GLint) -> Option<&WebGlUniformLocation> {
+        usize::try_from(location)
+            .ok()
+            .and_then(|location| self.uniforms.get(location))
+    }
+}
+
+fn with<R>(f: impl FnOnce(&mut Context) -> R) -> R {
+    CONTEXT.with(|context| {
+        f(context
+            .borrow_mut()
+            .as_mut()
+            .expect("the WebGL context is not loaded"))
+    })
+}
+
+fn proc_address(symbol: &str) -> *const c_void {
+    match symbol {
+        "glActiveTexture" => active_texture as *const c_void,
+        "glAttachShader" => attach_shader as *const c_void,
+        "glBindBuffer" => bind_buffer as *const c_void,
+        "glBindBufferBase" => bind_buffer_base as *const c_void,
+        "glBindFramebuffer" => bind_framebuffer as *const c_void,
+        "glBindTexture" => bind_texture as *const c_void,
+        "glBindVertexArray" => bind_vertex_array as *const c_void,
+        "glBlendFunc" => blend_func as *const c_void,
+        "glBlitFramebuffer" => blit_framebuffer as *const c_void,
+        "glBufferData" => buffer_data as *const c_void,
+        "glBufferSubData" => buffer_sub_data as *const c_void,
+        "glCheckFramebufferStatus" => check_framebuffer_status as *const c_void,
+        "glClear" => clear as *const c_void,
+        "glClearColor" => clear_color as *const c_void,
+        "glCompileShader" => compile_shader as *const c_void,
+        "glCreateProgram" => create_program as *const c_void,
+        "glCreateShader" => create_shader as *const c_void,
+        "glDeleteBuffers" => delete_buffers as *const c_void,
+        "glDeleteFramebuffers" => delete_framebuffers as *const c_void,
+        "glDeleteProgram" => delete_program as *const c_void,
+        "glDeleteShader" => delete_shader as *const c_void,
+        "glDeleteTextures" => delete_textures as *const c_void,
+        "glDeleteVertexArrays" => delete_vertex_arrays as *const c_void,
+        "glDisable" => disable as *const c_void,
+        "glDrawArrays" => draw_arrays as *const c_void,
+        "glDrawBuffers" => draw_buffers as *const c_void,
+        "glEnable" => enable as *const c_void,
+        "glEnableVertexAttribArray" => enable_vertex_attrib_array as *const c_void,
+        "glFinish" => finish as *const c_void,
+        "glFramebufferTexture2D" => framebuffer_texture_2d as *const c_void,
+        "glGenBuffers" => gen_buffers as *const c_void,
+        "glGenFramebuffers" => gen_framebuffers as *const c_void,
+        "glGenTextures" => gen_textures as *const c_void,
+        "glGenVertexArrays" => gen_vertex_arrays as *const c_void,
+        "glGenerateMipmap" => generate_mipmap as *const c_void,
+        "glGetProgramInfoLog" => get_program_info_log as *const c_void,
+        "glGetProgramiv" => get_programiv as *const c_void,
+        "glGetShaderInfoLog" => get_shader_info_log as *const c_void,
+        "glGetShaderiv" => get_shaderiv as *const c_void,
+        "glGetString" => get_string as *const c_void,
+        "glGetUniformBlockIndex" => get_uniform_block_index as *const c_void,
+        "glGetUniformLocation" => get_uniform_location as *const c_void,
+        "glLinkProgram" => link_program as *const c_void,
+        "glPixelStorei" => pixel_storei as *const c_void,
+        "glReadPixels" => read_pixels as *const c_void,
+        "glShaderSource" => shader_source as *const c_void,
+        "glTexImage2D" => tex_image_2d as *const c_void,
+        "glTexParameteri" => tex_parameteri as *const c_void,
+        "glUniform1f" => uniform1f as *const c_void,
+        "glUniform1i" => uniform1i as *const c_void,
+        "glUniform2f" => uniform2f as *const c_void,
+        "glUniform3f" => uniform3f as *const c_void,
+        "glUniform3fv" => uniform3fv as *const c_void,
+        "glUniform4f" => uniform4f as *const c_void,
+        "glUniform4fv" => uniform4fv as *const c_void,
+        "glUniformBlockBinding" => uniform_block_binding as *const c_void,
+        "glUseProgram" => use_program as *const c_void,
+        "glVertexAttribPointer" => vertex_attrib_pointer as *const c_void,
+        "glViewport" => viewport as *const c_void,
+        _ => ptr::null(),
+    }
+}
+
+// Size in bytes of an image in client memory, with its rows padded to the
+// alignment set with `glPixelStorei`
+fn image_size(
+    width: GLsizei,
+    height: GLsizei,
+    format: GLenum,
+    type_: GLenum,
+    alignment: usize,
+) -> usize {
+    let components = match format {
+        gl::RED => 1,
+        gl::RG => 2,
+        gl::RGB => 3,
+        _ => 4,
+    };
+    let component_size = match type_ {
+        gl::FLOAT => 4,
+        gl::HALF_FLOAT => 2,
+        _ => 1,
+    };
+
+    let row = width as usize * components * component_size;
+    let stride = row.div_ceil(alignment) * alignment;
+
+    match height as usize {
+        0 => 0,
+        height => stride * (height - 1) + row,
+    }
+}
+
+// A view of client memory for WebGL, which wants float data as a Float32Array.
+// Valid until the next allocation.
+unsafe fn view(data: *const c_void, size: usize, type_: GLenum) -> Object {
+    if type_ == gl::FLOAT {
+        Float32Array::view(slice::from_raw_parts(data as *const f32, size / 4)).into()
+    } else {
+        Uint8Array::view(slice::from_raw_parts(data as *const u8, size)).into()
+    }
+}
+
+// Copies the log to the caller's buffer the way `glGet*InfoLog` does
+unsafe fn write_log(log: &str, size: GLsizei, length: *mut GLsizei, buffer: *mut GLchar) {
+    let count = log.len().min((size.max(1) - 1) as usize);
+
+    if !buffer.is_null() && size > 0 {
+        ptr::copy_nonoverlapping(log.as_ptr(), buffer as *mut u8, count);
+        *buffer.add(count) = 0;
+    }
+
+    if !length.is_null() {
+        *length = count as GLsizei;
+    }
+}
+
+unsafe fn write_names(n: GLsizei, names: *mut GLuint, mut create: impl FnMut() -> GLuint) {
+    for i in 0..n as usize {
+        *names.add(i) = create();
+    }
+}
+
+unsafe fn read_names<'a>(n: GLsizei, names: *const GLuint) -> &'a [GLuint] {
+    slice::from_raw_parts(names, n as usize)
+}
+
+extern "system" fn active_texture(texture: GLenum) {
+    with(|c| c.gl.active_texture(texture))
+}
+
+extern "system" fn attach_shader(program: GLuint, shader: GLuint) {
+    with(|c| {
+        if let (Some(program), Some(shader)) = (c.programs.get(program), c.shaders.get(shader)) {
+            c.gl.attach_shader(program, shader);
+        }
+    })
+}
+
+extern "system" fn bind_buffer(target: GLenum, buffer: GLuint) {
+    with(|c| c.gl.bind_buffer(target, c.buffers.get(buffer)))
+}
+
+extern "system" fn bind_buffer_base(target: GLenum, index: GLuint, buffer: GLuint) {
+    with(|c| c.gl.bind_buffer_base(target, index, c.buffers.get(buffer)))
+}
+
+extern "system" fn bind_framebuffer(target: GLenum, framebuffer: GLuint) {
+    with(|c| {
+        c.gl.bind_framebuffer(target, c.framebuffers.get(framebuffer))
+    })
+}
+
+extern "system" fn bind_texture(target: GLenum, texture: GLuint) {
+    with(|c| c.gl.bind_texture(target, c.textures.get(texture)))
+}
+
+extern "system" fn bind_vertex_array(array: GLuint) {
+    with(|c| c.gl.bind_vertex_array(c.vertex_arrays.get(array)))
+}
+
+extern "system" fn blend_func(sfactor: GLenum, dfactor: GLenum) {
+    with(|c| c.gl.blend_func(sfactor, dfactor))
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "system" fn blit_framebuffer(
+    src_x0: GLint,
+    src_y0: GLint,
+    src_x1: GLint,
+    src_y1: GLint,
+    dst_x0: GLint,
+    dst_y0: GLint,
+    dst_x1: GLint,
+    dst_y1: GLint,
+    mask: GLbitfield,
+    filter: GLenum,
+) {
+    with(|c| {
+        c.gl.blit_framebuffer(
+            src_x0, src_y0, src_x1, src_y1, dst_x0, dst_y0, dst_x1, dst_y1, mask, filter,
+        )
+    })
+}
+
+unsafe extern "system" fn buffer_data(
+    target: GLenum,
+    size: GLsizeiptr,
+    data: *const c_void,
+    usage: GLenum,
+) {
+    with(|c| {
+        if data.is_null() {
+            c.gl.buffer_data_with_i32(target, size as i32, usage);
+        } else {
+            let data = slice::from_raw_parts(data as *const u8, size as usize);
+            c.gl.buffer_data_with_u8_array(target, data, usage);
+        }
+    })
+}
+
+unsafe extern "system" fn buffer_sub_data(
+    target: GLenum,
+    offset: GLintptr,
+    size: GLsizeiptr,
+    data: *const c_void,
+) {
+    let data = slice::from_raw_parts(data as *const u8, size as usize);
+    with(|c| {
+        c.gl.buffer_sub_data_with_i32_and_u8_array(target, offset as i32, data)
+    })
+}
+
+extern "system" fn check_framebuffer_status(target: GLenum) -> GLenum {
+    with(|c| c.gl.check_framebuffer_status(target))
+}
+
+extern "system" fn clear(mask: GLbitfield) {
+    with(|c| c.gl.clear(mask))
+}
+
+extern "system" fn clear_color(red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {
+    with(|c| c.gl.clear_color(red, green, blue, alpha))
+}
+
+extern "system" fn compile_shader(shader: GLuint) {
+    with(|c| {
+        if let Some(shader) = c.shaders.get(shader) {
+            c.gl.compile_shader(shader);
+        }
+    })
+}
+
+extern "system" fn create_program() -> GLuint {
+    with(|c| {
+        let program = c.gl.create_program();
+        c.programs.insert(program)
+    })
+}
+
+extern "system" fn create_shader(type_: GLenum) -> GLuint {
+    with(|c| {
+        let shader = c.gl.create_shader(type_);
+        c.shaders.insert(shader)
+    })
+}
+
+unsafe extern "system" fn delete_buffers(n: GLsizei, buffers: *const GLuint) {
+    with(|c| {
+        for &name in read_names(n, buffers) {
+            c.gl.delete_buffer(c.buffers.remove(name).as_ref());
+        }
+    })
+}
+
+unsafe extern "system" fn delete_framebuffers(n: GLsizei, framebuffers: *const GLuint) {
+    with(|c| {
+        for &name in read_names(n, framebuffers) {
+            c.gl.delete_framebuffer(c.framebuffers.remove(name).as_ref());
+        }
+    })
+}
+
+extern "system" fn delete_program(program: GLuint) {
+    with(|c| {
+        c.uniform_locations.retain(|(name, _), _| *name != program);
+        c.gl.delete_program(c.programs.remove(program).as_ref());
+    })
+}
+
+extern "system" fn delete_shader(shader: GLuint) {
+    with(|c| c.gl.delete_shader(c.shaders.remove(shader).as_ref()))
+}
+
+unsafe extern "system" fn delete_textures(n: GLsizei, textures: *const GLuint) {
+    with(|c| {
+        for &name in read_names(n, textures) {
+            c.gl.delete_texture(c.textures.remove(name).as_ref());
+        }
+    })
+}
+
+unsafe extern "system" fn delete_vertex_arrays(n: GLsizei, arrays: *const GLuint) {
+    with(|c| {
+        for &name in read_names(n, arrays) {
+            c.gl.delete_vertex_array(c.vertex_arrays.remove(name).as_ref());
+        }
+    })
+}
+
+extern "system" fn disable(cap: GLenum) {
+    with(|c| c.gl.disable(cap))
+}
+
+extern "system" fn draw_arrays(mode: GLenum, first: GLint, count: GLsizei) {
+    with(|c| c.gl.draw_arrays(mode, first, count))
+}
+
+unsafe extern "system" fn draw_buffers(n: GLsizei, bufs: *const GLenum) {
+    let buffers: Array = slice::from_raw_parts(bufs, n as usize)
+        .iter()
+        .map(|&buffer| JsValue::from(buffer))
+        .collect();
+
+    with(|c| c.gl.draw_buffers(&buffers))
+}
+
+extern "system" fn enable(cap: GLenum) {
+    with(|c| c.gl.enable(cap))
+}
+
+extern "system" fn enable_vertex_attrib_array(index: GLuint) {
+    with(|c| c.gl.enable_vertex_attrib_array(index))
+}
+
+extern "system" fn finish() {
+    with(|c| c.gl.finish())
+}
+
+extern "system" fn framebuffer_texture_2d(
+    target: GLenum,
+    attachment: GLenum,
+    textarget: GLenum,
+    texture: GLuint,
+    level: GLint,
+) {
+    with(|c| {
+        c.gl.framebuffer_texture_2d(
+            target,
+            attachment,
+            textarget,
+            c.textures.get(texture),
+            level,
+        )
+    })
+}
+
+unsafe extern "system" fn gen_buffers(n: GLsizei, buffers: *mut GLuint) {
+    with(|c| {
+        write_names(n, buffers, || {
+            let buffer = c.gl.create_buffer();
+            c.buffers.insert(buffer)
+        })
+    })
+}
+
+unsafe extern "system" fn gen_framebuffers(n: GLsizei, framebuffers: *mut GLuint) {
+    with(|c| {
+        write_names(n, framebuffers, || {
+            let framebuffer = c.gl.create_framebuffer();
+            c.framebuffers.insert(framebuffer)
+        })
+    })
+}
+
+unsafe extern "system" fn gen_textures(n: GLsizei, textures: *mut GLuint) {
+    with(|c| {
+        write_names(n, textures, || {
+            let texture = c.gl.create_texture();
+            c.textures.insert(texture)
+        })
+    })
+}
+
+unsafe extern "system" fn gen_vertex_arrays(n: GLsizei, arrays: *mut GLuint) {
+    with(|c| {
+        write_names(n, arrays, || {
+            let array = c.gl.create_vertex_array();
+            c.vertex_arrays.insert(array)
+        })
+    })
+}
+
+extern "system" fn generate_mipmap(target: GLenum) {
+    with(|c| c.gl.generate_mipmap(target))
+}
+
+unsafe extern "system" fn get_program_info_log(
+    program: GLuint,
+    size: GLsizei,
+    length: *mut GLsizei,
+    info_log: *mut GLchar,
+) {
+    let log = with(|c| {
+        c.programs
+            .get(program)
+            .and_then(|program| c.gl.get_program_info_log(program))
+    });
+
+    write_log(&log.unwrap_or_default(), size, length, info_log);
+}
+
+unsafe extern "system" fn get_programiv(program: GLuint, pname: GLenum, params: *mut GLint) {
+    *params = with(|c| {
+        let program = match c.programs.get(program) {
+            Some(program) => program,
+            None => return 0,
+        };
+
+        match pname {
+            gl::INFO_LOG_LENGTH => {
+                c.gl.get_program_info_log(program)
+                    .map_or(0, |log| log.len() as GLint + 1)
+            }
+            _ => {
+                c.gl.get_program_parameter(program, pname)
+                    .as_bool()
+                    .unwrap_or(false) as GLint
+            }
+        }
+    });
+}
+
+unsafe extern "system" fn get_shader_info_log(
+    shader: GLuint,
+    size: GLsizei,
+    length: *mut GLsizei,
+    info_log: *mut GLchar,
+) {
+    let log = with(|c| {
+        c.shaders
+            .get(shader)
+            .and_then(|shader| c.gl.get_shader_info_log(shader))
+    });
+
+    write_log(&log.unwrap_or_default(), size, length, info_log);
+}
+
+unsafe extern "system" fn get_shaderiv(shader: GLuint, pname: GLenum, params: *mut GLint) {
+    *params = with(|c| {
+        let shader = match c.shaders.get(shader) {
+            Some(shader) => shader,
+            None => return 0,
+        };
+
+        match pname {
+            gl::INFO_LOG_LENGTH => {
+                c.gl.get_shader_info_log(shader)
+                    .map_or(0, |log| log.len() as GLint + 1)
+            }
+            _ => {
+                c.gl.get_shader_parameter(shader, pname)
+                    .as_bool()
+                    .unwrap_or(false) as GLint
+            }
+        }
+    });
+}
+
+extern "system" fn get_string(name: GLenum) -> *const GLubyte {
+    with(|c| {
+        let value =
+            c.gl.get_parameter(name)
+                .ok()
+                .and_then(|value| value.as_string())
+                .unwrap_or_default();
+
+        let string = c
+            .strings
+            .entry(name)
+            .or_insert_with(|| CString::new(value).unwrap_or_default());
+
+        string.as_ptr() as *const GLubyte
+    })
+}
+
+unsafe extern "system" fn get_uniform_block_index(program: GLuint, name: *const GLchar) -> GLuint {
+    let name = CStr::from_ptr(name).to_string_lossy();
+
+    with(|c| match c.programs.get(program) {
+        Some(program) => c.gl.get_uniform_block_index(program, &name),
+        None => gl::INVALID_INDEX,
+    })
+}
+
+unsafe extern "system" fn get_uniform_location(program: GLuint, name: *const GLchar) -> GLint {
+    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+    with(|c| {
+        if let Some(&location) = c.uniform_locations.get(&(program, name.clone())) {
+            return location;
+        }
+
+        let location = match c
+            .programs
+            .get(program)
+            .and_then(|handle| c.gl.get_uniform_location(handle, &name))
+        {
+            Some(location) => {
+                c.uniforms.push(location);
+                (c.uniforms.len() - 1) as GLint
+            }
+            None => -1,
+        };
+
+        c.uniform_locations.insert((program, name), location);
+
+        location
+    })
+}
+
+extern "system" fn link_program(program: GLuint) {
+    with(|c| {
+        if let Some(program) = c.programs.get(program) {
+            c.gl.link_program(program);
+        }
+    })
+}
+
+extern "system" fn pixel_storei(pname: GLenum, param: GLint) {
+    with(|c| {
+        match pname {
+            gl::PACK_ALIGNMENT => c.pack_alignment = param as usize,
+            gl::UNPACK_ALIGNMENT => c.unpack_alignment = param as usize,
+            _ => (),
+        }
+
+        c.gl.pixel_storei(pname, param);
+    })
+}
+
+unsafe extern "system" fn read_pixels(
+    x: GLint,
+    y: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *mut c_void,
+) {
+    with(|c| {
+        let size = image_size(width, height, format, type_, c.pack_alignment);
+        let pixels = view(pixels, size, type_);
+
+        if let Err(err) = c.gl.read_pixels_with_opt_array_buffer_view(
+            x,
+            y,
+            width,
+            height,
+            format,
+            type_,
+            Some(&pixels),
+        ) {
+            web_sys::console::error_2(&"glReadPixels failed:".into(), &err);
+        }
+    })
+}
+
+unsafe extern "system" fn shader_source(
+    shader: GLuint,
+    count: GLsizei,
+    strings: *const *const GLchar,
+    lengths: *const GLint,
+) {
+    let mut source = String::new();
+
+    for i in 0..count as usize {
+        let string = *strings.add(i);
+        let length = if lengths.is_null() {
+            -1
+        } else {
+            *lengths.add(i)
+        };
+
+        // Negative lengths mean the string is null terminated
+        let bytes = match usize::try_from(length) {
+            Ok(length) => slice::from_raw_parts(string as *const u8, length),
+            Err(_) => CStr::from_ptr(string).to_bytes(),
+        };
+
+        source.push_str(&String::from_utf8_lossy(bytes));
+    }
+
+    with(|c| {
+        if let Some(shader) = c.shaders.get(shader) {
+            c.gl.shader_source(shader, &source);
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "system" fn tex_image_2d(
+    target: GLenum,
+    level: GLint,
+    internalformat: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    border: GLint,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *const c_void,
+) {
+    with(|c| {
+        let pixels = if pixels.is_null() {
+            None
+        } else {
+            let size = image_size(width, height, format, type_, c.unpack_alignment);
+            Some(view(pixels, size, type_))
+        };
+
+        if let Err(err) = c
+            .gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+                target,
+                level,
+                internalformat,
+                width,
+                height,
+                border,
+                format,
+                type_,
+                pixels.as_ref(),
+            )
+        {
+            web_sys::console::error_2(&"glTexImage2D failed:".into(), &err);
+        }
+    })
+}
+
+extern "system" fn tex_parameteri(target: GLenum, pname: GLenum, param: GLint) {
+    with(|c| c.gl.tex_parameteri(target, pname, param))
+}
+
+extern "system" fn uniform1f(location: GLint, v0: GLfloat) {
+    with(|c| c.gl.uniform1f(c.uniform(location), v0))
+}
+
+extern "system" fn uniform1i(location: GLint, v0: GLint) {
+    with(|c| c.gl.uniform1i(c.uniform(location), v0))
+}
+
+extern "system" fn uniform2f(location: GLint, v0: GLfloat, v1: GLfloat) {
+    with(|c| c.gl.uniform2f(c.uniform(location), v0, v1))
+}
+
+extern "system" fn uniform3f(location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat) {
+    with(|c| c.gl.uniform3f(c.uniform(location), v0, v1, v2))
+}
+
+unsafe extern "system" fn uniform3fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    let value = slice::from_raw_parts(value, count as usize * 3);
+
+    with(|c| c.gl.uniform3fv_with_f32_array(c.uniform(location), value))
+}
+
+extern "system" fn uniform4f(location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat, v3: GLfloat) {
+    with(|c| c.gl.uniform4f(c.uniform(location), v0, v1, v2, v3))
+}
+
+unsafe extern "system" fn uniform4fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    let value = slice::from_raw_parts(value, count as usize * 4);
+
+    with(|c| c.gl.uniform4fv_with_f32_array(c.uniform(location), value))
+}
+
+extern "system" fn uniform_block_binding(program: GLuint, index: GLuint, binding: GLuint) {
+    with(|c| {
+        if let Some(program) = c.programs.get(program) {
+            c.gl.uniform_block_binding(program, index, binding);
+        }
+    })
+}
+
+extern "system" fn use_program(program: GLuint) {
+    with(|c| c.gl.use_program(c.programs.get(program)))
+}
+
+extern "system" fn vertex_attrib_pointer(
+    index: GLuint,
+    size: GLint,
+    type_: GLenum,
+    normalized: GLboolean,
+    stride: GLsizei,
+    pointer: *const c_void,
+) {
+    // Always an offset into the bound buffer, WebGL has no client arrays
+    with(|c| {
+        c.gl.vertex_attrib_pointer_with_i32(
+            index,
+            size,
+            type_,
+            normalized == gl::TRUE,
+            stride,
+            pointer as i32,
+        )
+    })
+}
+
+extern "system" fn viewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    with(|c| c.gl.viewport(x, y, width, height))
+}